@@ -1,65 +1,515 @@
+use crate::app::jobs::JobStatus;
+use crate::artifacts::{ArtifactRef, ArtifactStatus};
+use crate::calibration::{AppliedCalibration, CalibrationEntry, Observation};
+use crate::app::pricing::{AppliedFinishing, AppliedSurcharge};
+use crate::config::Config;
+use crate::fx::ExchangeRate;
+use crate::machine::Machine;
+use crate::materials::Material;
+use crate::mesh::{dfm::DfmWarning, orient::Rotation, ModelFormat};
+use crate::money::{Currency, Money};
+use crate::quote_token::QuoteClaims;
+use crate::rules::{FinishingUnit, PricingRules, DEFAULT_TIER};
+use crate::slicer::{FeatureMetrics, PlateMetrics};
+use crate::upload::Principal;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct QuoteRequest {
-    pub file_url: String,
+    /// Presigned model URL; give either this or `file_id`
+    pub file_url: Option<String>,
+    /// Upload service file id, resolved by this service
+    pub file_id: Option<Uuid>,
+    /// Requesting principal, required with `file_id`: exactly one of these
+    pub user_id: Option<Uuid>,
+    pub session_id: Option<Uuid>,
     pub material: String,
     pub infill: u8,           // 10-100 (percentage)
-    pub layer_thickness: u16,  // micrometers (100, 200, 300)
+    pub layer_thickness: u16,  // micrometers (allowed values per material)
+    #[serde(default = "default_quantity")]
+    pub quantity: u32,
+    /// Customer tier from the pricing rules (margin)
+    #[serde(default = "default_tier")]
+    pub customer_tier: String,
+    /// Rush tier from the pricing rules (surcharge and shorter lead time)
+    #[serde(default = "default_tier")]
+    pub rush: String,
+    /// PLN, EUR or USD (default `DEFAULT_CURRENCY`)
+    pub currency: Option<String>,
+    /// Post-processing add-ons from the pricing rules, applied to every copy
+    #[serde(default)]
+    pub finishing: Vec<FinishingRequest>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FinishingRequest {
+    /// Finishing option id from the pricing rules
+    pub id: String,
+    /// Inserts per part; required for `per_insert` options only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
+
+fn default_quantity() -> u32 {
+    1
+}
+
+fn default_tier() -> String {
+    DEFAULT_TIER.to_string()
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct QuoteResponse {
     pub quote_id: Uuid,
-    pub total_usd: f64,
-    pub material_cost_usd: f64,
-    pub machine_cost_usd: f64,
-    pub base_fee_usd: f64,
+    pub created_at: DateTime<Utc>,
+    /// `created_at + QUOTE_VALID_HOURS`; also the token expiry
+    pub expires_at: DateTime<Utc>,
+    pub quantity: u32,
+    /// Currency of every `*_minor` amount (integer grosze/cents)
+    pub currency: Currency,
+    /// Rate used to convert the USD pricing rules into `currency`
+    pub exchange_rate: ExchangeRate,
+    /// Net price per copy
+    pub unit_price_minor: Money,
+    /// `unit_price_minor * quantity`
+    pub total_net_minor: Money,
+    pub vat_percent: f64,
+    pub vat_minor: Money,
+    /// `total_net_minor + vat_minor`
+    pub total_gross_minor: Money,
+    pub material_cost_minor: Money,
+    pub machine_cost_minor: Money,
+    pub support_removal_minor: Money,
+    pub base_fee_minor: Money,
+    /// Size/complexity surcharges from the pricing rules
+    pub surcharges: Vec<AppliedSurcharge>,
+    /// Requested post-processing, one line per option
+    pub finishing: Vec<AppliedFinishing>,
+    pub customer_tier: String,
+    pub margin_multiplier: f64,
+    /// Quantity-break discount applied to the batch
+    pub discount_percent: f64,
+    pub discount_minor: Money,
+    pub rush: String,
+    pub rush_surcharge_minor: Money,
+    /// Added to reach the minimum order value
+    pub minimum_order_adjustment_minor: Money,
     pub lead_time_days: u8,
+    /// Cheapest printer the part fits on that supports the material
+    pub machine: MachineInfo,
+    /// Rotation applied to the uploaded model; production prints this orientation
+    pub orientation: OrientationInfo,
+    /// Copies nested per build plate and plates needed for the batch
+    pub copies_per_plate: u32,
+    pub batch_plates: u32,
+    /// Printer time for the whole batch, including plate setup
+    pub machine_hours: f64,
+    /// Per copy, summed over all plates
     pub print_time_hours: f64,
     pub filament_weight_g: f64,
+    pub filament_length_mm: f64,
+    pub support_weight_g: f64,
+    /// Production correction applied to the slicer's time and filament estimates above
+    pub calibration: AppliedCalibration,
+    /// Time and filament by G-code feature (walls, infill, support, travel, ...)
+    pub features: Vec<FeatureMetrics>,
+    /// Per-plate breakdown when Orca arranged the model onto several plates
+    pub plates: Vec<PlateMetrics>,
     pub volume_cm3: f64,
+    pub surface_area_cm2: f64,
+    pub dimensions_mm: [f64; 3],
+    pub triangle_count: usize,
+    /// False only for defects within `MAX_BOUNDARY_EDGES`/`MAX_NON_MANIFOLD_EDGES`; `volume_cm3`
+    /// is then approximate
+    pub is_watertight: bool,
+    /// Printability problems found on the mesh, most severe first
+    pub warnings: Vec<DfmWarning>,
+    /// Sliced project stored for production; null when `ARTIFACT_STORE=none`,
+    /// the backend produces none, or the upload failed
+    pub artifact: Option<ArtifactRef>,
+    /// Set when quoted by `file_id`
+    pub file_id: Option<Uuid>,
+    /// Detected from file content (stl, 3mf, obj, step)
+    pub model_format: ModelFormat,
+    /// SHA-256 of the downloaded file (checked against the upload service's record for `file_id`)
+    pub model_sha256: String,
+    /// Slice metrics were served from the cache (no Orca run)
+    pub cached: bool,
+    /// Version of the pricing rules file used for this quote
+    pub pricing_rules_version: String,
+    /// Signed `QuoteClaims` (HS256 JWT) to verify before turning the quote into an order;
+    /// null when `QUOTE_TOKEN_SECRET` is not set
+    pub quote_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MachineInfo {
+    pub id: String,
+    pub display_name: String,
+    pub build_volume_mm: [f64; 3],
+    pub nozzle_diameter_mm: f64,
+}
+
+impl From<&Machine> for MachineInfo {
+    fn from(machine: &Machine) -> Self {
+        MachineInfo {
+            id: machine.id.clone(),
+            display_name: machine.display_name.clone(),
+            build_volume_mm: machine.profile.build_volume_mm(),
+            nozzle_diameter_mm: machine.profile.nozzle_diameter_mm,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrientationInfo {
+    /// Row-major 3x3 matrix applied to model coordinates (`v' = R · v`)
+    pub rotation: Rotation,
+    /// Mesh estimate of support below overhangs in this orientation
+    pub support_volume_cm3: f64,
+    pub overhang_area_cm2: f64,
+    /// Best-scored orientations sliced and priced (at most `ORIENT_SLICE_CANDIDATES`)
+    pub candidates_sliced: usize,
+}
+
+// POST /internal/pricing/fdm/quotes
+#[derive(Debug, Deserialize)]
+pub struct QuoteJobRequest {
+    #[serde(flatten)]
+    pub quote: QuoteRequest,
+    /// Notified with the finished job (POST, JSON body) when set
+    pub callback_url: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
+pub struct QuoteJobAccepted {
+    pub job_id: Uuid,
+    pub status: JobStatus,
+    pub status_url: String,
+}
+
+// POST /internal/pricing/fdm/quotes/verify
+#[derive(Debug, Deserialize)]
+pub struct VerifyQuoteRequest {
+    pub token: String,
+}
+
+// POST /internal/pricing/fdm/artifacts/:quote_id/accept
+#[derive(Debug, Serialize)]
+pub struct ArtifactAcceptResponse {
+    pub quote_id: Uuid,
+    pub status: ArtifactStatus,
+    pub keys: Vec<String>,
+}
+
+// GET /internal/pricing/fdm/options
+/// What a quote request may ask for, built from the loaded catalog and pricing rules
+#[derive(Debug, Serialize)]
+pub struct OptionsResponse {
+    /// Enabled materials, in catalog order
+    pub materials: Vec<MaterialOption>,
+    pub machines: Vec<MachineOption>,
+    /// Post-processing add-ons, by id
+    pub finishing: Vec<FinishingOption>,
+    pub customer_tiers: Vec<String>,
+    pub rush_tiers: Vec<RushOption>,
+    pub currencies: Vec<Currency>,
+    pub default_currency: Currency,
+    pub max_quantity: u32,
+    pub pricing_rules_version: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MaterialOption {
+    pub id: String,
+    pub display_name: String,
+    pub colors: Vec<String>,
+    pub density_g_cm3: f64,
+    /// Filament price before margin, as used for `material_cost_minor`
+    pub price_per_g_usd: f64,
+    /// Layer heights at least one printer can print this material at
+    pub layer_heights_um: Vec<u16>,
+    pub min_infill: u8,
+    pub max_infill: u8,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MachineOption {
+    #[serde(flatten)]
+    pub machine: MachineInfo,
+    pub materials: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FinishingOption {
+    pub id: String,
+    pub display_name: String,
+    pub unit: FinishingUnit,
+    /// Per unit, before margin
+    pub price_usd: f64,
+    pub lead_time_days: u8,
+    /// Material ids the option is offered for; empty means every material
+    pub materials: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RushOption {
+    pub id: String,
+    pub surcharge_percent: f64,
+    pub lead_time_days_saved: u8,
+}
+
+impl OptionsResponse {
+    pub fn new(config: &Config, rules: &PricingRules) -> Self {
+        let materials = config
+            .materials
+            .enabled()
+            .map(|material| MaterialOption {
+                id: material.id.clone(),
+                display_name: material.display_name.clone(),
+                colors: material.colors.clone(),
+                density_g_cm3: material.density_g_cm3,
+                price_per_g_usd: material.cost_per_g_usd,
+                layer_heights_um: material
+                    .layer_heights_um
+                    .iter()
+                    .copied()
+                    .filter(|&layer| config.machines.supports(material, layer))
+                    .collect(),
+                min_infill: material.min_infill,
+                max_infill: material.max_infill,
+            })
+            .filter(|option| !option.layer_heights_um.is_empty())
+            .collect();
+
+        let machines = config
+            .machines
+            .all()
+            .iter()
+            .map(|machine| MachineOption {
+                machine: MachineInfo::from(machine),
+                materials: machine.materials.clone(),
+            })
+            .collect();
+
+        // Rules keep options and tiers in maps; sort for a stable response
+        let mut finishing: Vec<FinishingOption> = rules
+            .finishing
+            .iter()
+            .map(|(id, option)| FinishingOption {
+                id: id.clone(),
+                display_name: option.display_name.clone(),
+                unit: option.unit,
+                price_usd: option.price_usd,
+                lead_time_days: option.lead_time_days,
+                materials: option.materials.clone(),
+            })
+            .collect();
+        finishing.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut customer_tiers: Vec<String> = rules.customer_tiers.keys().cloned().collect();
+        customer_tiers.sort();
+        let mut rush_tiers: Vec<RushOption> = rules
+            .rush_tiers
+            .iter()
+            .map(|(id, tier)| RushOption {
+                id: id.clone(),
+                surcharge_percent: tier.surcharge_percent,
+                lead_time_days_saved: tier.lead_time_days_saved,
+            })
+            .collect();
+        rush_tiers.sort_by(|a, b| {
+            a.lead_time_days_saved
+                .cmp(&b.lead_time_days_saved)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        OptionsResponse {
+            materials,
+            machines,
+            finishing,
+            customer_tiers,
+            rush_tiers,
+            currencies: Currency::ALL.to_vec(),
+            default_currency: config.default_currency,
+            max_quantity: config.max_quantity,
+            pricing_rules_version: rules.version.clone(),
+        }
+    }
+}
+
+// POST /internal/pricing/fdm/calibration/reports
+/// Production feedback for one quoted job
+#[derive(Debug, Deserialize)]
+pub struct CalibrationReport {
+    /// `quote_token` of the quote the job was printed from; machine, material, layer height and
+    /// the slicer estimates are taken from its claims
+    pub quote_token: String,
+    /// What printing all `quantity` copies of the quote took
+    pub actual_print_time_hours: f64,
+    pub actual_filament_weight_g: f64,
+}
+
+impl CalibrationReport {
+    /// Actuals next to the signed slicer estimates for the whole quantity
+    pub fn observation(&self, claims: &QuoteClaims) -> Observation {
+        let copies = f64::from(claims.quantity);
+        Observation {
+            estimated_print_time_hours: claims.sliced_print_time_hours * copies,
+            actual_print_time_hours: self.actual_print_time_hours,
+            estimated_filament_weight_g: claims.sliced_filament_weight_g * copies,
+            actual_filament_weight_g: self.actual_filament_weight_g,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalibrationFactor {
+    #[serde(flatten)]
+    pub entry: CalibrationEntry,
+    /// Applied to quotes (enough samples)
+    pub active: bool,
+}
+
+// GET /internal/pricing/fdm/calibration
+#[derive(Debug, Serialize)]
+pub struct CalibrationResponse {
+    pub min_samples: u32,
+    pub factors: Vec<CalibrationFactor>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CapacityResponse {
+    pub active_slices: usize,
+    pub queued_slices: usize,
+    pub max_concurrent_slices: usize,
+    pub max_queued_slices: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct ErrorResponse {
     pub error: String,
     pub message: String,
 }
 
 impl QuoteRequest {
-    pub fn validate(&self) -> Result<(), String> {
+    /// Validate the request against the material catalog and pricing rules
+    /// and return the selected material and quote currency
+    pub fn validate<'a>(
+        &self,
+        config: &'a Config,
+        rules: &PricingRules,
+    ) -> Result<(&'a Material, Currency), String> {
         // Validate material
-        if !["pla", "abs", "petg", "abs-esd", "asa", "nylon", "pc", "tpu"]
-            .contains(&self.material.to_lowercase().as_str())
-        {
-            return Err(format!("Invalid material: {}", self.material));
-        }
+        let material = config
+            .materials
+            .get(&self.material)
+            .filter(|m| m.enabled)
+            .ok_or_else(|| format!("Invalid material: {}", self.material))?;
 
         // Validate infill
-        if self.infill < 10 || self.infill > 100 {
-            return Err(format!("Infill must be between 10-100%, got: {}", self.infill));
+        if !material.supports_infill(self.infill) {
+            return Err(format!(
+                "Infill for {} must be between {}-{}%, got: {}",
+                material.id, material.min_infill, material.max_infill, self.infill
+            ));
         }
 
         // Validate layer thickness
-        if ![100, 200, 300].contains(&self.layer_thickness) {
+        if !material.supports_layer_height(self.layer_thickness) {
             return Err(format!(
-                "Layer thickness must be 100, 200, or 300 micrometers, got: {}",
-                self.layer_thickness
+                "Layer thickness for {} must be one of {:?} micrometers, got: {}",
+                material.id, material.layer_heights_um, self.layer_thickness
             ));
         }
 
-        // Validate file_url
-        if self.file_url.is_empty() {
-            return Err("file_url cannot be empty".to_string());
+        // At least one printer's nozzle must suit the layer height
+        if !config.machines.supports(material, self.layer_thickness) {
+            return Err(format!(
+                "No printer supports {} at {} micrometer layers",
+                material.id, self.layer_thickness
+            ));
         }
 
-        Ok(())
+        // Validate quantity
+        if self.quantity == 0 || self.quantity > config.max_quantity {
+            return Err(format!(
+                "Quantity must be between 1-{}, got: {}",
+                config.max_quantity, self.quantity
+            ));
+        }
+
+        // Validate tiers
+        if rules.customer_tier(&self.customer_tier).is_none() {
+            return Err(format!("Invalid customer_tier: {}", self.customer_tier));
+        }
+        if rules.rush_tier(&self.rush).is_none() {
+            return Err(format!("Invalid rush: {}", self.rush));
+        }
+
+        // Validate finishing options
+        let mut chosen = std::collections::HashSet::new();
+        for finishing in &self.finishing {
+            let option = rules
+                .finishing(&finishing.id)
+                .ok_or_else(|| format!("Invalid finishing option: {}", finishing.id))?;
+            if !chosen.insert(finishing.id.to_lowercase()) {
+                return Err(format!("Duplicate finishing option: {}", finishing.id));
+            }
+            if !option.supports(&material.id) {
+                return Err(format!(
+                    "Finishing option {} is not available for {}",
+                    finishing.id, material.id
+                ));
+            }
+            match (option.unit, finishing.count) {
+                (FinishingUnit::PerInsert, Some(1..)) => {}
+                (FinishingUnit::PerInsert, _) => {
+                    return Err(format!(
+                        "Finishing option {} requires a count of at least 1",
+                        finishing.id
+                    ));
+                }
+                (_, Some(_)) => {
+                    return Err(format!(
+                        "Finishing option {} does not take a count",
+                        finishing.id
+                    ));
+                }
+                (_, None) => {}
+            }
+        }
+
+        // Validate currency
+        let currency = match &self.currency {
+            Some(code) => code.parse().map_err(|e: anyhow::Error| e.to_string())?,
+            None => config.default_currency,
+        };
+
+        // Validate the model source
+        match (&self.file_url, self.file_id) {
+            (Some(url), None) if url.is_empty() => {
+                return Err("file_url cannot be empty".to_string());
+            }
+            (Some(_), None) => {}
+            (None, Some(_)) if self.principal().is_none() => {
+                return Err("file_id requires exactly one of user_id or session_id".to_string());
+            }
+            (None, Some(_)) => {}
+            _ => return Err("Exactly one of file_url or file_id is required".to_string()),
+        }
+
+        Ok((material, currency))
     }
 
-    pub fn layer_height_mm(&self) -> f32 {
-        self.layer_thickness as f32 / 1000.0
+    /// Who the quote is for, when exactly one of user_id or session_id is given
+    pub fn principal(&self) -> Option<Principal> {
+        match (self.user_id, self.session_id) {
+            (Some(user), None) => Some(Principal::User(user)),
+            (None, Some(session)) => Some(Principal::Session(session)),
+            _ => None,
+        }
     }
 
     pub fn quality_preset(&self) -> &'static str {
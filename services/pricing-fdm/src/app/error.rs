@@ -0,0 +1,131 @@
+use crate::app::dto::ErrorResponse;
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+
+/// Quote pipeline errors, mapped to HTTP status codes
+#[derive(Debug, thiserror::Error)]
+pub enum QuoteError {
+    #[error("{0}")]
+    InvalidRequest(String),
+
+    #[error("Failed to download file: {0}")]
+    Download(String),
+
+    /// Unknown `file_id`, or a file owned by someone else (not told apart on purpose)
+    #[error("File not found: {0}")]
+    FileNotFound(uuid::Uuid),
+
+    #[error("Upload service error: {0}")]
+    UploadService(String),
+
+    #[error("Unsupported model format: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("Invalid model: {0}")]
+    InvalidModel(String),
+
+    #[error("Part too large: {0}")]
+    PartTooLarge(String),
+
+    #[error("Slicing capacity exhausted, retry in {retry_after_secs}s")]
+    Busy { retry_after_secs: u64 },
+
+    #[error("Model slicing timed out after {0}s")]
+    SliceTimeout(u64),
+
+    #[error("Model slicing failed: {0}")]
+    SlicingFailed(String),
+
+    #[error("Pricing calculation failed: {0}")]
+    Pricing(String),
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+impl QuoteError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            QuoteError::InvalidRequest(_) | QuoteError::Download(_) => StatusCode::BAD_REQUEST,
+            QuoteError::FileNotFound(_) => StatusCode::NOT_FOUND,
+            QuoteError::UploadService(_) => StatusCode::BAD_GATEWAY,
+            QuoteError::UnsupportedFormat(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            QuoteError::InvalidModel(_)
+            | QuoteError::PartTooLarge(_)
+            | QuoteError::SlicingFailed(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            QuoteError::Busy { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            QuoteError::SliceTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            QuoteError::Pricing(_) | QuoteError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// `outcome` label for quote metrics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            QuoteError::InvalidRequest(_) => "invalid_request",
+            QuoteError::Download(_) => "download_failed",
+            QuoteError::FileNotFound(_) => "file_not_found",
+            QuoteError::UploadService(_) => "upload_service_error",
+            QuoteError::UnsupportedFormat(_) => "unsupported_format",
+            QuoteError::InvalidModel(_) => "invalid_model",
+            QuoteError::PartTooLarge(_) => "part_too_large",
+            QuoteError::Busy { .. } => "busy",
+            QuoteError::SliceTimeout(_) => "slice_timeout",
+            QuoteError::SlicingFailed(_) => "slicing_failed",
+            QuoteError::Pricing(_) => "pricing_failed",
+            QuoteError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn to_error_response(&self) -> ErrorResponse {
+        ErrorResponse {
+            error: self.status().to_string(),
+            message: self.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for QuoteError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let body = Json(self.to_error_response());
+
+        match self {
+            QuoteError::Busy { retry_after_secs } => (
+                status,
+                [(header::RETRY_AFTER, retry_after_secs.to_string())],
+                body,
+            )
+                .into_response(),
+            _ => (status, body).into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_busy_sets_retry_after() {
+        let response = QuoteError::Busy {
+            retry_after_secs: 7,
+        }
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "7");
+    }
+
+    #[test]
+    fn test_error_body_keeps_message() {
+        let err = QuoteError::Download("status 404".to_string());
+        let body = err.to_error_response();
+
+        assert_eq!(body.error, "400 Bad Request");
+        assert_eq!(body.message, "Failed to download file: status 404");
+    }
+}
@@ -1,98 +1,257 @@
-use crate::app::{dto::*, pricing};
-use crate::slicer;
-use crate::utils;
+use crate::app::{dto::*, error::QuoteError, jobs, service};
+use crate::artifacts::ArtifactStatus;
+use crate::metrics;
+use crate::utils::download::DownloadPolicy;
+use crate::quote_token::{self, QuoteClaims, TokenError};
 use crate::AppState;
-use axum::{extract::State, http::StatusCode, Json};
-use tracing::{error, info};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
 pub async fn quote(
     State(state): State<AppState>,
     Json(req): Json<QuoteRequest>,
-) -> Result<Json<QuoteResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Validate request
-    if let Err(e) = req.validate() {
-        return Err(error_response(StatusCode::BAD_REQUEST, &e));
+) -> Result<Json<QuoteResponse>, QuoteError> {
+    service::run_quote(&state, &req, None).await.map(Json)
+}
+
+/// POST /internal/pricing/fdm/quotes
+///
+/// Validates the request, then runs the quote pipeline in the background.
+pub async fn create_quote_job(
+    State(state): State<AppState>,
+    Json(req): Json<QuoteJobRequest>,
+) -> Result<(StatusCode, Json<QuoteJobAccepted>), QuoteError> {
+    req.quote
+        .validate(&state.config, &state.rules.current())
+        .map_err(QuoteError::InvalidRequest)?;
+
+    // Reject up front instead of accepting a job that can only fail; the job keeps its queue
+    // position until it gets a slicing slot
+    let busy = || QuoteError::Busy {
+        retry_after_secs: state.config.slice_retry_after_secs,
+    };
+    let ticket = state.slice_pool.reserve().map_err(|_| busy())?;
+
+    let policy = DownloadPolicy::for_callbacks(&state.config);
+    if let Some(url) = req.callback_url.as_deref() {
+        jobs::check_callback_url(url, &policy)
+            .await
+            .map_err(|e| QuoteError::InvalidRequest(format!("{:#}", e)))?;
     }
 
-    info!(
-        "Processing quote request for material={}, infill={}, layer_thickness={}um",
-        req.material, req.infill, req.layer_thickness
-    );
+    let job_id = state.jobs.create(req.callback_url).map_err(|e| {
+        warn!("{}", e);
+        busy()
+    })?;
+    info!("Accepted quote job {}", job_id);
 
-    // Download STL file from presigned URL
-    let stl_path = match utils::download::download_stl(&req.file_url, &state.config.temp_dir).await
-    {
-        Ok(path) => path,
-        Err(e) => {
-            error!("Failed to download STL: {}", e);
-            return Err(error_response(
-                StatusCode::BAD_REQUEST,
-                &format!("Failed to download file: {}", e),
-            ));
+    let worker_state = state.clone();
+    let quote = req.quote;
+    tokio::spawn(async move {
+        worker_state.jobs.set_running(job_id);
+        let result = service::run_quote(&worker_state, &quote, Some(ticket)).await;
+        if let Some(job) = worker_state.jobs.finish(job_id, result) {
+            info!("Quote job {} finished: {:?}", job_id, job.status);
+            jobs::notify_callback(&job, &policy).await;
         }
+    });
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(QuoteJobAccepted {
+            job_id,
+            status: jobs::JobStatus::Pending,
+            status_url: format!("/internal/pricing/fdm/quotes/{}", job_id),
+        }),
+    ))
+}
+
+/// GET /internal/pricing/fdm/quotes/:id
+pub async fn get_quote_job(
+    State(state): State<AppState>,
+    Path(job_id): Path<Uuid>,
+) -> Result<Json<jobs::QuoteJob>, (StatusCode, Json<ErrorResponse>)> {
+    state.jobs.get(job_id).map(Json).ok_or_else(|| {
+        error_response(
+            StatusCode::NOT_FOUND,
+            &format!("Quote job not found: {}", job_id),
+        )
+    })
+}
+
+/// POST /internal/pricing/fdm/artifacts/:quote_id/accept
+///
+/// Keeps the sliced artifacts of an accepted quote past the pending TTL. Idempotent.
+pub async fn accept_artifacts(
+    State(state): State<AppState>,
+    Path(quote_id): Path<Uuid>,
+) -> Result<Json<ArtifactAcceptResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let Some(artifacts) = &state.artifacts else {
+        return Err(error_response(StatusCode::NOT_FOUND, "Artifact storage is disabled"));
     };
 
-    // Slice model with Orca Slicer
-    let metrics = match slicer::slice_model(
-        &stl_path,
-        &req.material,
-        req.infill,
-        req.layer_height_mm(),
-        &state.config,
-    )
-    .await
-    {
-        Ok(m) => m,
+    match artifacts.accept(quote_id).await {
+        Ok(Some(keys)) => {
+            info!("Accepted artifacts for quote {}: {:?}", quote_id, keys);
+            Ok(Json(ArtifactAcceptResponse {
+                quote_id,
+                status: ArtifactStatus::Accepted,
+                keys,
+            }))
+        }
+        Ok(None) => Err(error_response(
+            StatusCode::NOT_FOUND,
+            &format!("No artifacts stored for quote: {}", quote_id),
+        )),
         Err(e) => {
-            error!("Slicing failed: {}", e);
-            // Cleanup temp file
-            let _ = tokio::fs::remove_file(&stl_path).await;
-            return Err(error_response(
-                StatusCode::UNPROCESSABLE_ENTITY,
-                &format!("Model slicing failed: {}", e),
-            ));
+            error!("Failed to accept artifacts for quote {}: {:#}", quote_id, e);
+            Err(error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()))
         }
+    }
+}
+
+/// POST /internal/pricing/fdm/quotes/verify
+///
+/// Returns the signed claims of a quote token; orders should be priced from these.
+pub async fn verify_quote(
+    State(state): State<AppState>,
+    Json(req): Json<VerifyQuoteRequest>,
+) -> Result<Json<QuoteClaims>, (StatusCode, Json<ErrorResponse>)> {
+    let Some(secret) = &state.config.quote_token_secret else {
+        return Err(error_response(StatusCode::NOT_FOUND, "Quote tokens are disabled"));
     };
 
-    // Cleanup temp file
-    if let Err(e) = tokio::fs::remove_file(&stl_path).await {
-        error!("Failed to cleanup temp file: {}", e);
+    match quote_token::verify(&req.token, secret) {
+        Ok(claims) => Ok(Json(claims)),
+        Err(e @ TokenError::Expired) => Err(error_response(StatusCode::GONE, &e.to_string())),
+        Err(e @ TokenError::Invalid(_)) => {
+            warn!("Rejected quote token: {}", e);
+            Err(error_response(StatusCode::UNPROCESSABLE_ENTITY, &e.to_string()))
+        }
     }
+}
 
-    info!(
-        "Slicing successful: print_time={}h, weight={}g",
-        metrics.print_time_hours, metrics.filament_weight_g
-    );
+/// GET /internal/pricing/fdm/options
+///
+/// Materials, printers and tiers a quote request can use, so clients need not hardcode them.
+pub async fn options(State(state): State<AppState>) -> Json<OptionsResponse> {
+    Json(OptionsResponse::new(&state.config, &state.rules.current()))
+}
 
-    // Calculate pricing
-    let price = match pricing::calculate_price(&metrics, &req.material, &state.config) {
-        Ok(p) => p,
-        Err(e) => {
-            error!("Pricing calculation failed: {}", e);
-            return Err(error_response(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                &format!("Pricing calculation failed: {}", e),
-            ));
-        }
+/// POST /internal/pricing/fdm/calibration/reports
+///
+/// Folds actual print time and filament use of a quoted job into the correction factors,
+/// against the slicer estimates signed into its quote token. Each quote counts once.
+pub async fn report_calibration(
+    State(state): State<AppState>,
+    Json(report): Json<CalibrationReport>,
+) -> Result<Json<CalibrationFactor>, (StatusCode, Json<ErrorResponse>)> {
+    let Some(secret) = &state.config.quote_token_secret else {
+        return Err(error_response(StatusCode::NOT_FOUND, "Quote tokens are disabled"));
     };
+    // Jobs are usually printed after the quote expired; the signature is what matters here
+    let claims = quote_token::verify_signature(&report.quote_token, secret).map_err(|e| {
+        warn!("Rejected calibration report token: {}", e);
+        error_response(StatusCode::UNPROCESSABLE_ENTITY, &e.to_string())
+    })?;
+
+    let observation = report.observation(&claims);
+    let checked = observation
+        .ratios()
+        .and_then(|_| state.calibration.check_report_window(claims.exp));
+    if let Err(e) = checked {
+        metrics::CALIBRATION_REPORTS
+            .with_label_values(&[&claims.machine_id, "rejected"])
+            .inc();
+        warn!("Rejected calibration report for quote {}: {}", claims.quote_id, e);
+        return Err(error_response(StatusCode::UNPROCESSABLE_ENTITY, &e.to_string()));
+    }
 
-    let quote_id = Uuid::new_v4();
-    let response = QuoteResponse {
-        quote_id,
-        total_usd: price.total_usd,
-        material_cost_usd: price.material_cost_usd,
-        machine_cost_usd: price.machine_cost_usd,
-        base_fee_usd: price.base_fee_usd,
-        lead_time_days: price.lead_time_days,
-        print_time_hours: metrics.print_time_hours,
-        filament_weight_g: metrics.filament_weight_g,
-        volume_cm3: metrics.volume_cm3,
+    let recorded = state
+        .calibration
+        .record(
+            claims.quote_id,
+            claims.exp,
+            &claims.machine_id,
+            &claims.material,
+            claims.layer_thickness,
+            &observation,
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to record calibration report: {:#}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string())
+        })?;
+    let entry = match recorded {
+        Some(entry) => {
+            metrics::CALIBRATION_REPORTS
+                .with_label_values(&[&claims.machine_id, "recorded"])
+                .inc();
+            info!(
+                "Calibration for {}/{}/{}um: time x{:.3}, filament x{:.3} after {} reports",
+                entry.machine_id,
+                entry.material,
+                entry.layer_thickness,
+                entry.print_time_factor,
+                entry.filament_weight_factor,
+                entry.samples
+            );
+            entry
+        }
+        None => {
+            metrics::CALIBRATION_REPORTS
+                .with_label_values(&[&claims.machine_id, "duplicate"])
+                .inc();
+            info!("Quote {} already reported, ignoring", claims.quote_id);
+            state
+                .calibration
+                .entry(&claims.machine_id, &claims.material, claims.layer_thickness)
+                .ok_or_else(|| {
+                    error_response(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        &format!("No calibration entry for reported quote {}", claims.quote_id),
+                    )
+                })?
+        }
     };
 
-    info!("Quote generated: id={}, total=${}", quote_id, price.total_usd);
+    Ok(Json(CalibrationFactor {
+        active: entry.samples >= state.calibration.min_samples(),
+        entry,
+    }))
+}
+
+/// GET /internal/pricing/fdm/calibration
+pub async fn calibration(State(state): State<AppState>) -> Json<CalibrationResponse> {
+    let min_samples = state.calibration.min_samples();
+    Json(CalibrationResponse {
+        min_samples,
+        factors: state
+            .calibration
+            .entries()
+            .into_iter()
+            .map(|entry| CalibrationFactor {
+                active: entry.samples >= min_samples,
+                entry,
+            })
+            .collect(),
+    })
+}
 
-    Ok(Json(response))
+/// GET /internal/pricing/fdm/capacity
+pub async fn capacity(State(state): State<AppState>) -> Json<CapacityResponse> {
+    let stats = state.slice_pool.stats();
+    Json(CapacityResponse {
+        active_slices: stats.active,
+        queued_slices: stats.queued,
+        max_concurrent_slices: stats.max_concurrent,
+        max_queued_slices: stats.max_queued,
+    })
 }
 
 fn error_response(status: StatusCode, message: &str) -> (StatusCode, Json<ErrorResponse>) {
@@ -0,0 +1,270 @@
+use crate::app::dto::{ErrorResponse, QuoteResponse};
+use crate::app::error::QuoteError;
+use crate::utils::download::{self, DownloadPolicy};
+use anyhow::Context;
+use reqwest::Url;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+const CALLBACK_ATTEMPTS: u32 = 3;
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuoteJob {
+    pub job_id: Uuid,
+    pub status: JobStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quote: Option<QuoteResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorResponse>,
+    #[serde(skip)]
+    pub callback_url: Option<String>,
+    #[serde(skip)]
+    finished_at: Option<Instant>,
+}
+
+/// In-memory store of asynchronous quote jobs.
+///
+/// Finished jobs are kept for `ttl` so callers can poll the result, then pruned. At most
+/// `max_unfinished` jobs may be pending or running at once.
+#[derive(Clone)]
+pub struct JobStore {
+    jobs: Arc<RwLock<HashMap<Uuid, QuoteJob>>>,
+    ttl: Duration,
+    max_unfinished: usize,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Too many unfinished quote jobs (limit {0})")]
+pub struct TooManyJobs(usize);
+
+impl JobStore {
+    pub fn new(ttl: Duration, max_unfinished: usize) -> Self {
+        JobStore {
+            jobs: Arc::new(RwLock::new(HashMap::new())),
+            ttl,
+            max_unfinished,
+        }
+    }
+
+    pub fn create(&self, callback_url: Option<String>) -> Result<Uuid, TooManyJobs> {
+        self.prune();
+
+        let job_id = Uuid::new_v4();
+        let job = QuoteJob {
+            job_id,
+            status: JobStatus::Pending,
+            quote: None,
+            error: None,
+            callback_url,
+            finished_at: None,
+        };
+        let mut jobs = self.jobs.write().unwrap();
+        let unfinished = jobs.values().filter(|j| j.finished_at.is_none()).count();
+        if unfinished >= self.max_unfinished {
+            return Err(TooManyJobs(self.max_unfinished));
+        }
+        jobs.insert(job_id, job);
+        Ok(job_id)
+    }
+
+    pub fn get(&self, job_id: Uuid) -> Option<QuoteJob> {
+        self.jobs.read().unwrap().get(&job_id).cloned()
+    }
+
+    pub fn set_running(&self, job_id: Uuid) {
+        if let Some(job) = self.jobs.write().unwrap().get_mut(&job_id) {
+            job.status = JobStatus::Running;
+        }
+    }
+
+    /// Record the outcome and return the finished job
+    pub fn finish(&self, job_id: Uuid, result: Result<QuoteResponse, QuoteError>) -> Option<QuoteJob> {
+        let mut jobs = self.jobs.write().unwrap();
+        let job = jobs.get_mut(&job_id)?;
+
+        match result {
+            Ok(quote) => {
+                job.status = JobStatus::Succeeded;
+                job.quote = Some(quote);
+            }
+            Err(e) => {
+                job.status = JobStatus::Failed;
+                job.error = Some(e.to_error_response());
+            }
+        }
+        job.finished_at = Some(Instant::now());
+
+        Some(job.clone())
+    }
+
+    /// Drop finished jobs older than the TTL
+    fn prune(&self) {
+        let ttl = self.ttl;
+        self.jobs
+            .write()
+            .unwrap()
+            .retain(|_, job| job.finished_at.is_none_or(|t| t.elapsed() < ttl));
+    }
+}
+
+/// Check a callback URL before accepting a job: https only, on an allowed host and (unless
+/// allowed) not on a private network
+pub async fn check_callback_url(url: &str, policy: &DownloadPolicy) -> anyhow::Result<()> {
+    callback_client(url, policy).await.map(|_| ())
+}
+
+/// Client pinned to the callback host's checked addresses, without redirects
+async fn callback_client(url: &str, policy: &DownloadPolicy) -> anyhow::Result<reqwest::Client> {
+    let url = Url::parse(url).context("Invalid callback_url")?;
+    if url.scheme() != "https" {
+        anyhow::bail!("callback_url must use https");
+    }
+    download::checked_client_builder(&url, policy)
+        .await?
+        .timeout(CALLBACK_TIMEOUT)
+        .build()
+        .context("Failed to build callback client")
+}
+
+/// POST the finished job to its callback URL, retrying with backoff.
+///
+/// The host is resolved and checked again for every attempt and the client is pinned to the
+/// checked addresses; redirects are not followed.
+pub async fn notify_callback(job: &QuoteJob, policy: &DownloadPolicy) {
+    let Some(url) = job.callback_url.as_deref() else {
+        return;
+    };
+
+    for attempt in 1..=CALLBACK_ATTEMPTS {
+        let client = match callback_client(url, policy).await {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("Callback for job {} refused: {:#}", job.job_id, e);
+                return;
+            }
+        };
+
+        match client.post(url).json(job).send().await {
+            Ok(resp) if resp.status().is_success() => {
+                debug!("Callback delivered for job {}", job.job_id);
+                return;
+            }
+            Ok(resp) => warn!(
+                "Callback for job {} returned {} (attempt {})",
+                job.job_id,
+                resp.status(),
+                attempt
+            ),
+            Err(e) => warn!(
+                "Callback for job {} failed: {} (attempt {})",
+                job.job_id, e, attempt
+            ),
+        }
+        if attempt < CALLBACK_ATTEMPTS {
+            tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_lifecycle() {
+        let store = JobStore::new(Duration::from_secs(60), 8);
+        let id = store.create(None).unwrap();
+        assert_eq!(store.get(id).unwrap().status, JobStatus::Pending);
+
+        store.set_running(id);
+        assert_eq!(store.get(id).unwrap().status, JobStatus::Running);
+
+        let job = store
+            .finish(id, Err(QuoteError::SliceTimeout(60)))
+            .unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.error.unwrap().error, "504 Gateway Timeout");
+    }
+
+    #[test]
+    fn test_finished_jobs_are_pruned_after_ttl() {
+        let store = JobStore::new(Duration::ZERO, 8);
+        let finished = store.create(None).unwrap();
+        store.finish(finished, Err(QuoteError::Pricing("boom".to_string())));
+        let pending = store.create(None).unwrap();
+
+        assert!(store.get(finished).is_none());
+        assert!(store.get(pending).is_some());
+    }
+
+    #[test]
+    fn test_serializes_status_lowercase() {
+        let store = JobStore::new(Duration::from_secs(60), 8);
+        let id = store.create(Some("http://api/callback".to_string())).unwrap();
+        let json = serde_json::to_value(store.get(id).unwrap()).unwrap();
+
+        assert_eq!(json["status"], "pending");
+        assert!(json.get("callback_url").is_none());
+        assert!(json.get("quote").is_none());
+    }
+
+    #[test]
+    fn test_caps_unfinished_jobs() {
+        let store = JobStore::new(Duration::from_secs(60), 2);
+        let first = store.create(None).unwrap();
+        store.create(None).unwrap();
+        assert!(store.create(None).is_err());
+
+        // Finished jobs no longer count
+        store.finish(first, Err(QuoteError::Pricing("boom".to_string())));
+        assert!(store.create(None).is_ok());
+    }
+
+    #[test]
+    fn test_callbacks_have_their_own_allowlist() {
+        let mut config = crate::config::Config::for_tests();
+        config.download_allowed_hosts = vec!["storage.example.com".to_string()];
+        config.download_allow_private_networks = true;
+        config.callback_allowed_hosts = vec!["api.example.com".to_string()];
+
+        let policy = DownloadPolicy::for_callbacks(&config);
+        assert_eq!(policy.allowed_hosts, vec!["api.example.com".to_string()]);
+        assert!(!policy.allow_private_networks);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unsafe_callback_urls() {
+        let policy = DownloadPolicy {
+            allowed_hosts: vec!["127.0.0.1".to_string(), "localhost".to_string()],
+            allow_private_networks: false,
+            max_bytes: 1024,
+            connect_timeout: Duration::from_secs(2),
+            read_timeout: Duration::from_secs(2),
+            max_redirects: 0,
+        };
+
+        assert!(check_callback_url("not a url", &policy).await.is_err());
+        assert!(check_callback_url("http://127.0.0.1/cb", &policy).await.is_err());
+        assert!(check_callback_url("https://api.example.com/cb", &policy).await.is_err());
+        // Allowlisted, but loopback
+        assert!(check_callback_url("https://127.0.0.1/cb", &policy).await.is_err());
+        assert!(check_callback_url("https://localhost/cb", &policy).await.is_err());
+
+        let policy = DownloadPolicy { allow_private_networks: true, ..policy };
+        assert!(check_callback_url("https://127.0.0.1/cb", &policy).await.is_ok());
+    }
+}
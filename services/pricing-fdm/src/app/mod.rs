@@ -1,3 +1,32 @@
 pub mod dto;
+pub mod error;
 pub mod handlers;
+pub mod jobs;
 pub mod pricing;
+pub mod service;
+
+use crate::AppState;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+
+/// Internal pricing API routes
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/internal/pricing/fdm/quote", post(handlers::quote))
+        .route("/internal/pricing/fdm/quotes", post(handlers::create_quote_job))
+        .route("/internal/pricing/fdm/quotes/:id", get(handlers::get_quote_job))
+        .route("/internal/pricing/fdm/quotes/verify", post(handlers::verify_quote))
+        .route("/internal/pricing/fdm/options", get(handlers::options))
+        .route("/internal/pricing/fdm/calibration", get(handlers::calibration))
+        .route(
+            "/internal/pricing/fdm/calibration/reports",
+            post(handlers::report_calibration),
+        )
+        .route("/internal/pricing/fdm/capacity", get(handlers::capacity))
+        .route(
+            "/internal/pricing/fdm/artifacts/:quote_id/accept",
+            post(handlers::accept_artifacts),
+        )
+}
@@ -1,49 +1,258 @@
+use crate::app::dto::FinishingRequest;
 use crate::config::Config;
-use crate::slicer::SliceMetrics;
+use crate::fx::ExchangeRate;
+use crate::machine::Machine;
+use crate::mesh::MeshAnalysis;
+use crate::money::Money;
+use crate::rules::{FinishingUnit, PricingRules, SurchargeMetric};
+use crate::slicer::{SliceMetrics, SliceSettings};
+use serde::Serialize;
 
+/// Price in the quote currency (`exchange_rate.to`), in minor units
 pub struct PriceBreakdown {
-    pub total_usd: f64,
-    pub material_cost_usd: f64,
-    pub machine_cost_usd: f64,
-    pub base_fee_usd: f64,
+    pub exchange_rate: ExchangeRate,
+    /// Net total divided by quantity; `total_net == unit_price * quantity`
+    pub unit_price: Money,
+    pub total_net: Money,
+    pub vat_percent: f64,
+    pub vat: Money,
+    pub total_gross: Money,
+    pub material_cost: Money,
+    pub machine_cost: Money,
+    /// Manual support removal, charged per gram of support material
+    pub support_removal: Money,
+    pub base_fee: Money,
+    /// Size/complexity surcharges triggered by the part
+    pub surcharges: Vec<AppliedSurcharge>,
+    /// Post-processing add-ons, in request order
+    pub finishing: Vec<AppliedFinishing>,
+    pub margin_multiplier: f64,
+    pub discount_percent: f64,
+    pub discount: Money,
+    pub rush_surcharge: Money,
+    /// Added to reach the minimum order value
+    pub minimum_order_adjustment: Money,
+    /// Printer time for the whole batch, including plate setup
+    pub machine_hours: f64,
     pub lead_time_days: u8,
+    pub rules_version: String,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedSurcharge {
+    pub name: String,
+    pub amount_minor: Money,
+}
+
+/// Finishing option priced for the batch
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedFinishing {
+    pub id: String,
+    pub display_name: String,
+    pub unit: FinishingUnit,
+    /// Charged units per copy: cm² of surface, 1 part, or the insert count
+    pub units_per_part: f64,
+    /// For the whole batch, before margin
+    pub amount_minor: Money,
+    /// Days this option adds to the lead time
+    pub lead_time_days: u8,
+}
+
+/// Customer and rush tier and finishing chosen for a quote
+pub struct Choices<'a> {
+    pub customer: &'a str,
+    pub rush: &'a str,
+    pub finishing: &'a [FinishingRequest],
+}
+
+/// How a batch of copies is laid out on build plates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Batch {
+    pub quantity: u32,
+    pub copies_per_plate: u32,
+    pub plates: u32,
+}
+
+impl Batch {
+    /// Plan a batch on the chosen machine from the sliced metrics and the part's XY footprint.
+    ///
+    /// A model Orca already spread over several plates is printed one copy at a time.
+    pub fn plan(
+        quantity: u32,
+        footprint_mm: [f64; 2],
+        metrics: &SliceMetrics,
+        machine: &Machine,
+        config: &Config,
+    ) -> Self {
+        let plates_per_copy = metrics.plates.len().max(1) as u32;
+        if plates_per_copy > 1 {
+            return Batch {
+                quantity,
+                copies_per_plate: 1,
+                plates: quantity * plates_per_copy,
+            };
+        }
+
+        // Orca checks the real fit when slicing; never plan fewer than one copy per plate
+        let copies_per_plate = machine
+            .profile
+            .copies_per_plate(footprint_mm, config.nesting_spacing_mm)
+            .max(1);
+
+        Batch {
+            quantity,
+            copies_per_plate,
+            plates: quantity.div_ceil(copies_per_plate),
+        }
+    }
+}
+
+/// Price a batch printed with the given settings. Rules are in USD; each cost line is converted to the quote
+/// currency and rounded once, then margin, discounts and VAT are applied in minor units.
 pub fn calculate_price(
     metrics: &SliceMetrics,
-    material: &str,
-    config: &Config,
+    analysis: &MeshAnalysis,
+    settings: &SliceSettings,
+    batch: &Batch,
+    choices: &Choices,
+    rules: &PricingRules,
+    fx: &ExchangeRate,
 ) -> anyhow::Result<PriceBreakdown> {
-    // Get material cost per gram
-    let cost_per_g = config
-        .material_costs
-        .get(material)
-        .ok_or_else(|| anyhow::anyhow!("Unknown material: {}", material))?;
+    if batch.quantity == 0 {
+        anyhow::bail!("Quantity must be at least 1");
+    }
+    let quantity = batch.quantity as f64;
+    let material = settings.material;
+    let customer = rules
+        .customer_tier(choices.customer)
+        .ok_or_else(|| anyhow::anyhow!("Unknown customer tier: {}", choices.customer))?;
+    let rush = rules
+        .rush_tier(choices.rush)
+        .ok_or_else(|| anyhow::anyhow!("Unknown rush tier: {}", choices.rush))?;
 
     // Calculate material cost
-    let material_cost_usd = metrics.filament_weight_g * cost_per_g;
+    let material_cost = fx.to_minor(metrics.filament_weight_g * material.cost_per_g_usd * quantity);
+
+    // Machine time: every copy, plus heat-up and bed clearing per plate
+    let machine_hours =
+        metrics.print_time_hours * quantity + batch.plates as f64 * rules.plate_setup_minutes / 60.0;
+    let machine_cost = fx.to_minor(machine_hours * rules.machine_rate(&settings.machine.id, &material.id));
 
-    // Calculate machine cost (time * rate)
-    let machine_cost_usd = metrics.print_time_hours * config.machine_rate_usd_per_hour;
+    // Support structures are removed by hand
+    let support_removal =
+        fx.to_minor(metrics.support_weight_g() * rules.support_removal_usd_per_g * quantity);
 
-    // Calculate subtotal
-    let subtotal = material_cost_usd + machine_cost_usd + config.base_fee_usd;
+    let base_fee = fx.to_minor(rules.base_fee_usd);
+    let base_subtotal = material_cost + machine_cost + support_removal + base_fee;
 
-    // Apply margin
-    let total_usd = subtotal * config.margin_multiplier;
+    // Size/complexity surcharges on the batch
+    let surcharges: Vec<AppliedSurcharge> = rules
+        .surcharges
+        .iter()
+        .filter(|s| surcharge_metric(s.metric, metrics, analysis) > s.threshold)
+        .map(|s| AppliedSurcharge {
+            name: s.name.clone(),
+            amount_minor: match s.flat_usd {
+                Some(flat) => fx.to_minor(flat),
+                None => base_subtotal.percent(s.percent.unwrap_or(0.0)),
+            },
+        })
+        .collect();
 
-    // Estimate lead time (days) based on print time
-    let lead_time_days = estimate_lead_time(metrics.print_time_hours);
+    // Post-processing is done by hand on every copy
+    let finishing = choices
+        .finishing
+        .iter()
+        .map(|requested| {
+            let option = rules
+                .finishing(&requested.id)
+                .ok_or_else(|| anyhow::anyhow!("Unknown finishing option: {}", requested.id))?;
+            let units_per_part = match option.unit {
+                FinishingUnit::PerCm2 => analysis.surface_area_cm2,
+                FinishingUnit::PerPart => 1.0,
+                FinishingUnit::PerInsert => requested.count.unwrap_or(0) as f64,
+            };
+            Ok(AppliedFinishing {
+                id: requested.id.to_lowercase(),
+                display_name: option.display_name.clone(),
+                unit: option.unit,
+                units_per_part: (units_per_part * 100.0).round() / 100.0,
+                amount_minor: fx.to_minor(units_per_part * option.price_usd * quantity),
+                lead_time_days: option.lead_time_days,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let subtotal = base_subtotal
+        + surcharges.iter().map(|s| s.amount_minor).sum()
+        + finishing.iter().map(|f| f.amount_minor).sum();
+
+    // Apply the customer's margin, then the quantity break, then rush
+    let gross = subtotal.scale(customer.margin_multiplier);
+    let discount_percent = rules.quantity_discount(batch.quantity);
+    let discount = gross.percent(discount_percent);
+    let rush_surcharge = (gross - discount).percent(rush.surcharge_percent);
+    let net = gross - discount + rush_surcharge;
+
+    // Split into a unit price so the total is an exact multiple of it
+    let minimum_order = fx.to_minor(rules.minimum_order_usd);
+    let (unit_price, minimum_order_adjustment) = if net < minimum_order {
+        let unit_price = minimum_order.div_ceil(batch.quantity);
+        (unit_price, unit_price.times(batch.quantity) - net)
+    } else {
+        (net.div_round(batch.quantity), Money::ZERO)
+    };
+    let total_net = unit_price.times(batch.quantity);
+    let vat = total_net.percent(rules.vat_percent);
+
+    // Estimate lead time (days) based on print time, shortened by rush; finishing steps
+    // run one after another and are not shortened
+    let finishing_days = finishing.iter().map(|f| f.lead_time_days).fold(0, u8::saturating_add);
+    let lead_time_days = estimate_lead_time(machine_hours)
+        .saturating_sub(rush.lead_time_days_saved)
+        .max(1)
+        .saturating_add(finishing_days);
 
     Ok(PriceBreakdown {
-        total_usd: (total_usd * 100.0).round() / 100.0, // Round to 2 decimals
-        material_cost_usd: (material_cost_usd * 100.0).round() / 100.0,
-        machine_cost_usd: (machine_cost_usd * 100.0).round() / 100.0,
-        base_fee_usd: config.base_fee_usd,
+        exchange_rate: fx.clone(),
+        unit_price,
+        total_net,
+        vat_percent: rules.vat_percent,
+        vat,
+        total_gross: total_net + vat,
+        material_cost,
+        machine_cost,
+        support_removal,
+        base_fee,
+        surcharges,
+        finishing,
+        margin_multiplier: customer.margin_multiplier,
+        discount_percent,
+        discount,
+        rush_surcharge,
+        minimum_order_adjustment,
+        machine_hours,
         lead_time_days,
+        rules_version: rules.version.clone(),
     })
 }
 
+/// Value of the part property a surcharge rule is triggered by
+fn surcharge_metric(metric: SurchargeMetric, metrics: &SliceMetrics, analysis: &MeshAnalysis) -> f64 {
+    match metric {
+        SurchargeMetric::MaxDimensionMm => {
+            analysis.bounding_box.size().into_iter().fold(0.0, f64::max)
+        }
+        SurchargeMetric::VolumeCm3 => analysis.volume_cm3,
+        SurchargeMetric::TriangleCount => analysis.triangle_count as f64,
+        SurchargeMetric::ShellCount => analysis.shell_count as f64,
+        SurchargeMetric::SupportPercent if metrics.filament_weight_g > 0.0 => {
+            metrics.support_weight_g() / metrics.filament_weight_g * 100.0
+        }
+        SurchargeMetric::SupportPercent => 0.0,
+    }
+}
+
 fn estimate_lead_time(print_time_hours: f64) -> u8 {
     // Simple lead time estimation
     // < 8h = 1 day
@@ -64,6 +273,197 @@ fn estimate_lead_time(print_time_hours: f64) -> u8 {
 mod tests {
     use super::*;
 
+    fn metrics(plates: usize) -> SliceMetrics {
+        let plate = crate::slicer::PlateMetrics {
+            plate: 1,
+            print_time_hours: 1.0,
+            filament_weight_g: 20.0,
+            filament_length_mm: 6700.0,
+            filament_volume_cm3: 16.1,
+            objects: Vec::new(),
+            layer_count: 100,
+            max_z_mm: 20.0,
+            travel_mm: 0.0,
+            features: Vec::new(),
+        };
+        SliceMetrics::from_plates(vec![plate; plates])
+    }
+
+    #[test]
+    fn test_batch_nests_copies_per_plate() {
+        let config = Config::for_tests();
+        let machine = config.machines.get("generic-200").unwrap();
+
+        let batch = Batch::plan(100, [20.0, 20.0], &metrics(1), machine, &config);
+        assert_eq!(batch.copies_per_plate, 64);
+        assert_eq!(batch.plates, 2);
+
+        // (350 + 5) / (20 + 5) = 14.2 -> 196 per plate on the large printer
+        let large = config.machines.get("large-350").unwrap();
+        let batch = Batch::plan(100, [20.0, 20.0], &metrics(1), large, &config);
+        assert_eq!((batch.copies_per_plate, batch.plates), (196, 1));
+
+        // Multi-plate models are not nested
+        let batch = Batch::plan(3, [20.0, 20.0], &metrics(2), machine, &config);
+        assert_eq!((batch.copies_per_plate, batch.plates), (1, 6));
+    }
+
+    fn cube(size: f64) -> MeshAnalysis {
+        let mut builder = crate::mesh::MeshBuilder::default();
+        for tri in crate::mesh::tests::cube(size, [0.0; 3]) {
+            builder.add_triangle(tri).unwrap();
+        }
+        builder.finish().analyze()
+    }
+
+    /// PLA on the 200 mm printer at 0.2 mm layers
+    fn pla(config: &Config) -> SliceSettings<'_> {
+        SliceSettings {
+            material: config.materials.get("pla").unwrap(),
+            machine: config.machines.get("generic-200").unwrap(),
+            infill: 20,
+            layer_thickness_um: 200,
+            timeout: std::time::Duration::from_secs(60),
+        }
+    }
+
+    fn usd() -> ExchangeRate {
+        ExchangeRate::identity(crate::money::Currency::Usd)
+    }
+
+    const STANDARD: Choices = Choices {
+        customer: "standard",
+        rush: "standard",
+        finishing: &[],
+    };
+
+    #[test]
+    fn test_quantity_price_and_discount() {
+        let config = Config::for_tests();
+        let rules = crate::rules::tests::bundled();
+        let pla = pla(&config);
+        let (m, part) = (metrics(1), cube(20.0));
+
+        let batch = Batch::plan(1, [20.0, 20.0], &m, pla.machine, &config);
+        let single = calculate_price(&m, &part, &pla, &batch, &STANDARD, &rules, &usd()).unwrap();
+        let batch = Batch::plan(10, [20.0, 20.0], &m, pla.machine, &config);
+        let ten = calculate_price(&m, &part, &pla, &batch, &STANDARD, &rules, &usd()).unwrap();
+
+        assert_eq!(single.discount_percent, 0.0);
+        assert_eq!(ten.discount_percent, 5.0);
+        assert_eq!(ten.rules_version, rules.version);
+        // 10 hours printing plus one 15 minute plate setup
+        assert!((ten.machine_hours - 10.25).abs() < 1e-9);
+        assert!(ten.unit_price < single.unit_price);
+        assert_eq!(ten.total_net, ten.unit_price.times(10));
+    }
+
+    #[test]
+    fn test_tiers_surcharges_and_minimum_order() {
+        let config = Config::for_tests();
+        let rules = crate::rules::tests::bundled();
+        let pla = pla(&config);
+        let m = metrics(1);
+        let batch = Batch::plan(1, [20.0, 20.0], &m, pla.machine, &config);
+
+        let small = calculate_price(&m, &cube(20.0), &pla, &batch, &STANDARD, &rules, &usd()).unwrap();
+        let large = calculate_price(&m, &cube(160.0), &pla, &batch, &STANDARD, &rules, &usd()).unwrap();
+        assert!(small.surcharges.is_empty());
+        assert_eq!(large.surcharges[0].name, "large_part");
+        assert!(large.total_net > small.total_net);
+
+        let rushed = Choices {
+            customer: "partner",
+            rush: "express",
+            finishing: &[],
+        };
+        let rush = calculate_price(&m, &cube(20.0), &pla, &batch, &rushed, &rules, &usd()).unwrap();
+        assert_eq!(rush.margin_multiplier, 1.15);
+        assert!(rush.rush_surcharge > Money::ZERO);
+
+        // A two-minute print falls below the minimum order value
+        let mut tiny = metrics(1);
+        tiny.print_time_hours = 0.03;
+        tiny.filament_weight_g = 1.0;
+        let price = calculate_price(&tiny, &cube(5.0), &pla, &batch, &STANDARD, &rules, &usd()).unwrap();
+        assert_eq!(price.total_net, Money::from_major(rules.minimum_order_usd));
+        assert!(price.minimum_order_adjustment > Money::ZERO);
+
+        let unknown = Choices {
+            customer: "vip",
+            rush: "standard",
+            finishing: &[],
+        };
+        assert!(calculate_price(&m, &cube(20.0), &pla, &batch, &unknown, &rules, &usd()).is_err());
+    }
+
+    #[test]
+    fn test_finishing_line_items() {
+        let config = Config::for_tests();
+        let rules = crate::rules::tests::bundled();
+        let pla = pla(&config);
+        let m = metrics(1);
+        let batch = Batch::plan(3, [20.0, 20.0], &m, pla.machine, &config);
+        let finishing = [
+            FinishingRequest {
+                id: "sanding".to_string(),
+                count: None,
+            },
+            FinishingRequest {
+                id: "heat_set_inserts".to_string(),
+                count: Some(4),
+            },
+        ];
+
+        let plain = calculate_price(&m, &cube(20.0), &pla, &batch, &STANDARD, &rules, &usd()).unwrap();
+        let choices = Choices {
+            finishing: &finishing,
+            ..STANDARD
+        };
+        let finished = calculate_price(&m, &cube(20.0), &pla, &batch, &choices, &rules, &usd()).unwrap();
+
+        // 24 cm² sanded and 4 inserts on each of 3 copies
+        assert_eq!(finished.finishing[0].units_per_part, 24.0);
+        assert_eq!(finished.finishing[0].amount_minor, Money::from_major(0.02 * 24.0 * 3.0));
+        assert_eq!(finished.finishing[1].amount_minor, Money::from_major(0.60 * 4.0 * 3.0));
+        assert!(finished.total_net > plain.total_net);
+        assert_eq!(finished.lead_time_days, plain.lead_time_days + 1);
+
+        let unknown = [FinishingRequest {
+            id: "gold_plating".to_string(),
+            count: None,
+        }];
+        let choices = Choices {
+            finishing: &unknown,
+            ..STANDARD
+        };
+        assert!(calculate_price(&m, &cube(20.0), &pla, &batch, &choices, &rules, &usd()).is_err());
+    }
+
+    #[test]
+    fn test_converted_price_with_vat() {
+        let config = Config::for_tests();
+        let rules = crate::rules::tests::bundled();
+        let pla = pla(&config);
+        let m = metrics(1);
+        let batch = Batch::plan(3, [20.0, 20.0], &m, pla.machine, &config);
+        let pln = ExchangeRate {
+            to: crate::money::Currency::Pln,
+            rate: 3.65,
+            ..usd()
+        };
+
+        let in_usd = calculate_price(&m, &cube(20.0), &pla, &batch, &STANDARD, &rules, &usd()).unwrap();
+        let in_pln = calculate_price(&m, &cube(20.0), &pla, &batch, &STANDARD, &rules, &pln).unwrap();
+
+        assert_eq!(in_pln.base_fee, Money::from_major(rules.base_fee_usd * 3.65));
+        // Rounding happens in each currency's minor units, so totals agree to a few grosze
+        assert!((in_pln.total_net.to_major() - in_usd.total_net.to_major() * 3.65).abs() < 0.2);
+        assert_eq!(in_pln.total_net, in_pln.unit_price.times(3));
+        assert_eq!(in_pln.vat, in_pln.total_net.percent(23.0));
+        assert_eq!(in_pln.total_gross, in_pln.total_net + in_pln.vat);
+    }
+
     #[test]
     fn test_estimate_lead_time() {
         assert_eq!(estimate_lead_time(4.0), 1);
@@ -0,0 +1,722 @@
+use crate::app::{dto::*, error::QuoteError, pricing};
+use crate::calibration::AppliedCalibration;
+use crate::fx::ExchangeRate;
+use crate::machine::Machine;
+use crate::materials::Material;
+use crate::mesh::{self, dfm, orient, orient::Orientation, Mesh, MeshAnalysis};
+use crate::metrics;
+use crate::money::BASE_CURRENCY;
+use crate::quote_token;
+use crate::rules::PricingRules;
+use crate::slicer::{self, QueueTicket, SliceError, SliceMetrics, SliceOutput, SliceSettings};
+use crate::upload;
+use crate::utils;
+use crate::AppState;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Smallest part volume worth slicing (1 mm³)
+const MIN_MODEL_VOLUME_CM3: f64 = 0.001;
+
+/// Run the full download -> detect -> analyze -> slice -> price pipeline for one request.
+///
+/// `reserved` is a slicing queue position taken when an async job was accepted; the first
+/// slice waits on it instead of queueing again.
+pub async fn run_quote(
+    state: &AppState,
+    req: &QuoteRequest,
+    reserved: Option<QueueTicket>,
+) -> Result<QuoteResponse, QuoteError> {
+    let started = Instant::now();
+    let reserved = Mutex::new(reserved);
+    let result = quote_pipeline(state, req, &reserved).await;
+
+    let outcome = match &result {
+        Ok(_) => "ok",
+        Err(e) => e.kind(),
+    };
+    // Only catalog ids as labels, so bad requests can't grow the series count
+    let material = state
+        .config
+        .materials
+        .get(&req.material)
+        .map_or("unknown", |m| m.id.as_str());
+
+    metrics::QUOTES.with_label_values(&[material, outcome]).inc();
+    metrics::QUOTE_DURATION
+        .with_label_values(&[outcome])
+        .observe(started.elapsed().as_secs_f64());
+    if let Ok(quote) = &result {
+        metrics::QUOTE_TOTALS
+            .with_label_values(&[quote.currency.code(), material])
+            .observe(quote.total_net_minor.to_major());
+    }
+
+    result
+}
+
+async fn quote_pipeline(
+    state: &AppState,
+    req: &QuoteRequest,
+    reserved: &Mutex<Option<QueueTicket>>,
+) -> Result<QuoteResponse, QuoteError> {
+    // Price the whole request with one version of the rules, even if they reload mid-slice
+    let rules = state.rules.current();
+
+    // Validate request
+    let (material, currency) = req
+        .validate(&state.config, &rules)
+        .map_err(QuoteError::InvalidRequest)?;
+
+    // Fix the exchange rate before slicing so a missing rate fails fast
+    let created_at = chrono::Utc::now();
+    let today = created_at.date_naive();
+    let fx = match state.config.exchange_rates.rate(BASE_CURRENCY, currency, today) {
+        Ok(rate) => rate,
+        Err(e) => {
+            error!("Exchange rate lookup failed: {}", e);
+            return Err(QuoteError::Pricing(e.to_string()));
+        }
+    };
+
+    info!(
+        "Processing quote request for material={}, infill={}, layer_thickness={}um, quantity={}, currency={}",
+        req.material, req.infill, req.layer_thickness, req.quantity, currency
+    );
+
+    // Resolve file_id to a presigned URL, only for the file's owner
+    let uploaded = match req.file_id {
+        Some(file_id) => Some(resolve_upload(state, req, file_id).await?),
+        None => None,
+    };
+    let file_url = match (&uploaded, &req.file_url) {
+        (Some(file), _) => file.url.as_str(),
+        (None, Some(url)) => url.as_str(),
+        (None, None) => {
+            return Err(QuoteError::InvalidRequest(
+                "Exactly one of file_url or file_id is required".to_string(),
+            ))
+        }
+    };
+
+    // Download model file from presigned URL
+    let policy = utils::download::DownloadPolicy::from_config(&state.config);
+    let download_started = Instant::now();
+    let downloaded =
+        utils::download::download_model(file_url, &state.config.temp_dir, &policy).await;
+    metrics::DOWNLOAD_DURATION
+        .with_label_values(&[if downloaded.is_ok() { "ok" } else { "error" }])
+        .observe(download_started.elapsed().as_secs_f64());
+    let download_path = match downloaded {
+        Ok(path) => path,
+        Err(e) => {
+            error!("Failed to download model: {}", e);
+            return Err(QuoteError::Download(e.to_string()));
+        }
+    };
+    let mut temp_files = TempFiles(vec![download_path.clone()]);
+
+    // Detect the format from content; Orca picks its importer by extension
+    let model_format = match mesh::ModelFormat::detect_file(&download_path).await {
+        Ok(f) => f,
+        Err(e) => {
+            warn!("Rejected model upload: {}", e);
+            return Err(QuoteError::UnsupportedFormat(e.to_string()));
+        }
+    };
+    let model_path = download_path.with_extension(model_format.extension());
+    if let Err(e) = tokio::fs::rename(&download_path, &model_path).await {
+        error!("Failed to rename downloaded model: {}", e);
+        return Err(QuoteError::Internal(e.to_string()));
+    }
+    temp_files.0 = vec![model_path.clone()];
+
+    // Hash the uploaded model so repeat quotes can skip slicing
+    let model_sha256 = match utils::hash::sha256_file(&model_path).await {
+        Ok(h) => h,
+        Err(e) => {
+            error!("Failed to hash model: {}", e);
+            return Err(QuoteError::InvalidModel(e.to_string()));
+        }
+    };
+    if let Some(expected) = uploaded.as_ref().and_then(|f| f.sha256.as_deref()) {
+        if !expected.eq_ignore_ascii_case(&model_sha256) {
+            error!("Downloaded file {} does not match its recorded SHA-256", model_sha256);
+            return Err(QuoteError::Download(
+                "file content does not match the SHA-256 recorded at upload".to_string(),
+            ));
+        }
+    }
+
+    // STEP has no mesh: let Orca tessellate it, then analyze and slice the 3MF
+    let mesh_path = if model_format.is_mesh() {
+        model_path
+    } else {
+        let slot = acquire_slot(state, reserved).await?;
+        let converted = state.slicer.convert_step(&model_path, &state.config).await;
+        drop(slot);
+
+        match converted {
+            Ok(path) => {
+                temp_files.0.push(path.clone());
+                path
+            }
+            Err(SliceError::Timeout(secs)) => {
+                error!("STEP conversion timed out after {}s", secs);
+                return Err(QuoteError::SliceTimeout(secs));
+            }
+            Err(e) => {
+                error!("STEP conversion failed: {}", e);
+                return Err(QuoteError::InvalidModel(format!(
+                    "STEP model could not be imported: {}",
+                    e
+                )));
+            }
+        }
+    };
+
+    // Load the mesh and score candidate orientations off the async runtime
+    let limits = mesh::ParseLimits::from_config(&state.config);
+    let model = match mesh::load_file(&mesh_path, limits).await {
+        Ok(m) => m,
+        Err(e) => {
+            error!("Mesh analysis failed: {}", e);
+            return Err(QuoteError::InvalidModel(e.to_string()));
+        }
+    };
+    let (model, analysis, orientations) = tokio::task::spawn_blocking(move || {
+        let analysis = model.analyze();
+        let orientations = orient::candidates(&model);
+        (model, analysis, orientations)
+    })
+    .await
+    .map_err(|e| QuoteError::Internal(e.to_string()))?;
+
+    // Reject broken models before slicing
+    if analysis.triangle_count == 0 || analysis.volume_cm3 < MIN_MODEL_VOLUME_CM3 {
+        return Err(QuoteError::InvalidModel(
+            "mesh has no printable volume".to_string(),
+        ));
+    }
+
+    // The volume of an open or non-manifold mesh is meaningless; only small defects that the
+    // slicer closes are tolerated
+    if analysis.boundary_edges > state.config.max_boundary_edges
+        || analysis.non_manifold_edges > state.config.max_non_manifold_edges
+    {
+        warn!(
+            "Rejected broken mesh: boundary_edges={}, non_manifold_edges={}",
+            analysis.boundary_edges, analysis.non_manifold_edges
+        );
+        return Err(QuoteError::InvalidModel(format!(
+            "mesh is not watertight ({} open edges, {} non-manifold edges); repair it and upload again",
+            analysis.boundary_edges, analysis.non_manifold_edges
+        )));
+    }
+    if !analysis.is_watertight {
+        warn!(
+            "Model is not watertight: boundary_edges={}, non_manifold_edges={}",
+            analysis.boundary_edges, analysis.non_manifold_edges
+        );
+    }
+
+    // Keep the best-scored orientations that fit a printer, each on the cheapest one
+    let mut planned = Vec::new();
+    let mut too_large = None;
+    for orientation in orientations {
+        let selected = state.config.machines.select(
+            orientation.score.size_mm,
+            material,
+            req.layer_thickness,
+            |m| rules.machine_rate(&m.id, &material.id),
+        );
+        match selected {
+            Ok(machine) => planned.push((orientation, machine)),
+            Err(e) => {
+                too_large.get_or_insert(e);
+            }
+        }
+        if planned.len() == state.config.orient_slice_candidates {
+            break;
+        }
+    }
+    if let Some(e) = too_large.filter(|_| planned.is_empty()) {
+        warn!("Rejected oversized part: {}", e);
+        return Err(QuoteError::PartTooLarge(e.to_string()));
+    }
+
+    // Slice and price each candidate, quoting the cheapest. A failed candidate is skipped, and
+    // all share one deadline so extra candidates cannot multiply the wait
+    let quote = QuoteContext {
+        state,
+        req,
+        material,
+        rules: &rules,
+        fx: &fx,
+        reserved,
+    };
+    let model = Arc::new(model);
+    // Generated up front so stored artifacts are scoped to the quote
+    let quote_id = Uuid::new_v4();
+    let timeout_secs = state.config.request_timeout_secs;
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(timeout_secs);
+    let mut best: Option<PricedOrientation> = None;
+    let mut first_error = None;
+    let mut candidates_priced = 0;
+    for (index, (orientation, machine)) in planned.iter().enumerate() {
+        let oriented_path = download_path.with_extension(format!("orient{}.stl", index));
+        temp_files.0.push(oriented_path.clone());
+
+        let priced =
+            slice_and_price(&quote, &model, orientation, machine, &oriented_path, deadline);
+        let priced = match priced.await {
+            Ok(priced) => priced,
+            Err(e @ QuoteError::SliceTimeout(_)) => {
+                warn!(
+                    "Orientation candidates exceeded {}s, stopped at {} of {}",
+                    timeout_secs,
+                    index + 1,
+                    planned.len()
+                );
+                first_error.get_or_insert(e);
+                break;
+            }
+            Err(e) => {
+                warn!("Orientation candidate {} of {} failed: {}", index + 1, planned.len(), e);
+                first_error.get_or_insert(e);
+                continue;
+            }
+        };
+        candidates_priced += 1;
+        if best.as_ref().is_none_or(|b| priced.price.total_net < b.price.total_net) {
+            best = Some(priced);
+        }
+    }
+    let best = match (best, first_error) {
+        (Some(best), _) => best,
+        (None, Some(e)) => return Err(e),
+        (None, None) => return Err(QuoteError::Internal("no orientation was priced".to_string())),
+    };
+    let PricedOrientation {
+        orientation,
+        machine,
+        analysis,
+        metrics,
+        calibration,
+        batch,
+        price,
+        cached: cache_hit,
+        project,
+        temp_files: project_files,
+    } = best;
+
+    // Hand the exact sliced project to production; the quote stands without it
+    let artifact = match (&state.artifacts, &project) {
+        (Some(artifacts), Some(project)) => match artifacts.upload(quote_id, project).await {
+            Ok(stored) => Some(stored),
+            Err(e) => {
+                warn!("Failed to store sliced artifact for quote {}: {:#}", quote_id, e);
+                None
+            }
+        },
+        _ => None,
+    };
+
+    // Cleanup temp files
+    drop(temp_files);
+    drop(project_files);
+
+    // Printability checks for the orientation and machine we quote
+    let limits = dfm::DfmLimits {
+        nozzle_diameter_mm: machine.profile.nozzle_diameter_mm,
+        rotation: orientation.rotation,
+        support_percent: if metrics.filament_weight_g > 0.0 {
+            metrics.support_weight_g() / metrics.filament_weight_g * 100.0
+        } else {
+            0.0
+        },
+    };
+    let warnings = tokio::task::spawn_blocking(move || dfm::check(&model, &limits))
+        .await
+        .map_err(|e| QuoteError::Internal(e.to_string()))?;
+
+    let mut response = QuoteResponse {
+        quote_id,
+        created_at,
+        expires_at: created_at + chrono::Duration::hours(state.config.quote_valid_hours as i64),
+        quantity: batch.quantity,
+        currency,
+        exchange_rate: price.exchange_rate,
+        unit_price_minor: price.unit_price,
+        total_net_minor: price.total_net,
+        vat_percent: price.vat_percent,
+        vat_minor: price.vat,
+        total_gross_minor: price.total_gross,
+        material_cost_minor: price.material_cost,
+        machine_cost_minor: price.machine_cost,
+        support_removal_minor: price.support_removal,
+        base_fee_minor: price.base_fee,
+        surcharges: price.surcharges,
+        finishing: price.finishing,
+        customer_tier: req.customer_tier.to_lowercase(),
+        margin_multiplier: price.margin_multiplier,
+        discount_percent: price.discount_percent,
+        discount_minor: price.discount,
+        rush: req.rush.to_lowercase(),
+        rush_surcharge_minor: price.rush_surcharge,
+        minimum_order_adjustment_minor: price.minimum_order_adjustment,
+        lead_time_days: price.lead_time_days,
+        machine: machine.into(),
+        orientation: OrientationInfo {
+            rotation: orientation.rotation,
+            support_volume_cm3: (orientation.score.support_volume_cm3 * 100.0).round() / 100.0,
+            overhang_area_cm2: (orientation.score.overhang_area_cm2 * 100.0).round() / 100.0,
+            candidates_sliced: candidates_priced,
+        },
+        copies_per_plate: batch.copies_per_plate,
+        batch_plates: batch.plates,
+        machine_hours: (price.machine_hours * 100.0).round() / 100.0,
+        print_time_hours: metrics.print_time_hours,
+        filament_weight_g: metrics.filament_weight_g,
+        filament_length_mm: metrics.filament_length_mm,
+        support_weight_g: (metrics.support_weight_g() * 100.0).round() / 100.0,
+        calibration,
+        features: metrics.features,
+        plates: metrics.plates,
+        volume_cm3: analysis.volume_cm3,
+        surface_area_cm2: analysis.surface_area_cm2,
+        dimensions_mm: analysis.bounding_box.size(),
+        triangle_count: analysis.triangle_count,
+        is_watertight: analysis.is_watertight,
+        warnings,
+        artifact,
+        file_id: req.file_id,
+        model_format,
+        model_sha256,
+        cached: cache_hit,
+        pricing_rules_version: price.rules_version,
+        quote_token: None,
+    };
+
+    // Sign what was quoted, so the order can be checked against it later
+    if let Some(secret) = &state.config.quote_token_secret {
+        let claims =
+            quote_token::QuoteClaims::new(&response, material, req.infill, req.layer_thickness);
+        match quote_token::sign(&claims, secret) {
+            Ok(token) => response.quote_token = Some(token),
+            Err(e) => {
+                error!("Failed to sign quote {}: {}", quote_id, e);
+                return Err(QuoteError::Internal(e.to_string()));
+            }
+        }
+    }
+
+    info!(
+        "Quote generated: id={}, machine={}, quantity={}, unit={} {}, net={} {}, rules={}",
+        quote_id,
+        machine.id,
+        batch.quantity,
+        response.unit_price_minor.to_major(),
+        currency,
+        response.total_net_minor.to_major(),
+        currency,
+        response.pricing_rules_version
+    );
+
+    Ok(response)
+}
+
+/// Request-wide inputs shared by every orientation candidate
+struct QuoteContext<'a> {
+    state: &'a AppState,
+    req: &'a QuoteRequest,
+    material: &'a Material,
+    rules: &'a PricingRules,
+    fx: &'a ExchangeRate,
+    reserved: &'a Mutex<Option<QueueTicket>>,
+}
+
+/// One orientation, sliced on its machine and priced
+struct PricedOrientation<'a> {
+    orientation: Orientation,
+    machine: &'a Machine,
+    /// Geometry as it sits on the bed
+    analysis: MeshAnalysis,
+    /// Calibrated metrics the price is based on
+    metrics: SliceMetrics,
+    calibration: AppliedCalibration,
+    batch: pricing::Batch,
+    price: pricing::PriceBreakdown,
+    cached: bool,
+    /// Sliced project, kept only when artifacts are stored
+    project: Option<PathBuf>,
+    /// Removes the project when this candidate is dropped
+    temp_files: TempFiles,
+}
+
+/// Rotate the model, slice it (or reuse a cached slice) and price the batch
+async fn slice_and_price<'a>(
+    quote: &QuoteContext<'a>,
+    model: &Arc<Mesh>,
+    orientation: &Orientation,
+    machine: &'a Machine,
+    oriented_path: &Path,
+    deadline: tokio::time::Instant,
+) -> Result<PricedOrientation<'a>, QuoteError> {
+    let state = quote.state;
+    let req = quote.req;
+
+    // Orca slices the rotated copy as-is, so production prints what we priced
+    let (model, rotation, path) = (Arc::clone(model), orientation.rotation, oriented_path.to_path_buf());
+    let written = tokio::task::spawn_blocking(move || {
+        let oriented = model.rotated(&rotation);
+        mesh::write_stl(&oriented, &path).map(|_| oriented.analyze())
+    })
+    .await
+    .map_err(|e| QuoteError::Internal(e.to_string()))?;
+    let analysis = match written {
+        Ok(a) => a,
+        Err(e) => {
+            error!("Failed to write oriented model: {}", e);
+            return Err(QuoteError::Internal(e.to_string()));
+        }
+    };
+    let oriented_sha256 = match utils::hash::sha256_file(oriented_path).await {
+        Ok(h) => h,
+        Err(e) => {
+            error!("Failed to hash oriented model: {}", e);
+            return Err(QuoteError::Internal(e.to_string()));
+        }
+    };
+
+    let mut settings = SliceSettings {
+        material: quote.material,
+        machine,
+        infill: req.infill,
+        layer_thickness_um: req.layer_thickness,
+        timeout: deadline.saturating_duration_since(tokio::time::Instant::now()),
+    };
+
+    let cache_key = if state.slice_cache.is_enabled() {
+        match state
+            .slice_cache
+            .key(state.slicer.name(), &oriented_sha256, &settings, &state.config)
+            .await
+        {
+            Ok(key) => Some(key),
+            Err(e) => {
+                warn!("Slice cache disabled for this request: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // With artifacts on, a hit also needs the sliced project, otherwise slice again
+    let keep_project = state.artifacts.is_some();
+    let cached = match &cache_key {
+        Some(key) => match state.slice_cache.get(key).await {
+            Some(metrics) if keep_project => state
+                .slice_cache
+                .get_project(key, &oriented_path.with_extension("sliced.3mf"))
+                .await
+                .map(|project| SliceOutput {
+                    metrics,
+                    project_path: Some(project),
+                }),
+            Some(metrics) => Some(SliceOutput {
+                metrics,
+                project_path: None,
+            }),
+            None => None,
+        },
+        None => None,
+    };
+    let cache_hit = cached.is_some();
+
+    let sliced = match cached {
+        Some(output) => {
+            info!("Slice cache hit for oriented model {}", oriented_sha256);
+            Ok(output)
+        }
+        None => {
+            let slot = acquire_slot(state, quote.reserved).await?;
+
+            // The slicer gets what is left of the shared deadline and enforces it itself,
+            // so a timeout still kills Orca and records the failure below
+            settings.timeout = deadline.saturating_duration_since(tokio::time::Instant::now());
+            if settings.timeout.is_zero() {
+                return Err(QuoteError::SliceTimeout(state.config.request_timeout_secs));
+            }
+
+            // Slice model with the configured backend
+            let slice_started = Instant::now();
+            let sliced = state
+                .slicer
+                .slice(oriented_path, &settings, &state.config)
+                .await;
+            drop(slot);
+
+            let backend = state.slicer.name();
+            let outcome = match &sliced {
+                Ok(_) => "ok",
+                Err(e) => {
+                    metrics::SLICE_FAILURES.with_label_values(&[backend, e.reason()]).inc();
+                    e.reason()
+                }
+            };
+            metrics::SLICE_DURATION
+                .with_label_values(&[backend, outcome])
+                .observe(slice_started.elapsed().as_secs_f64());
+            sliced
+        }
+    };
+
+    let SliceOutput {
+        metrics,
+        project_path,
+    } = match sliced {
+        Ok(output) => output,
+        Err(SliceError::Timeout(secs)) => {
+            error!("Slicing timed out after {}s", secs);
+            return Err(QuoteError::SliceTimeout(state.config.request_timeout_secs));
+        }
+        Err(e) => {
+            error!("Slicing failed: {}", e);
+            return Err(QuoteError::SlicingFailed(e.to_string()));
+        }
+    };
+
+    let temp_files = TempFiles(project_path.iter().cloned().collect());
+    let project = project_path.filter(|_| keep_project);
+
+    if let (Some(key), false) = (&cache_key, cache_hit) {
+        if let Err(e) = state.slice_cache.put(key, &metrics).await {
+            warn!("Failed to store slice cache entry: {}", e);
+        }
+        if let Some(project) = &project {
+            if let Err(e) = state.slice_cache.put_project(key, project).await {
+                warn!("Failed to store sliced project in cache: {}", e);
+            }
+        }
+    }
+
+    info!(
+        "Slicing successful: machine={}, plates={}, print_time={}h, weight={}g",
+        machine.id,
+        metrics.plates.len(),
+        metrics.print_time_hours,
+        metrics.filament_weight_g
+    );
+
+    // Correct the estimates by what production measured for this combination; the cache keeps
+    // the slicer's own numbers
+    let (metrics, calibration) = state.calibration.apply(
+        &machine.id,
+        &quote.material.id,
+        req.layer_thickness,
+        metrics,
+    );
+
+    // Nest copies onto plates and price the whole batch
+    let size = analysis.bounding_box.size();
+    let batch = pricing::Batch::plan(req.quantity, [size[0], size[1]], &metrics, machine, &state.config);
+    let choices = pricing::Choices {
+        customer: &req.customer_tier,
+        rush: &req.rush,
+        finishing: &req.finishing,
+    };
+    let price = match pricing::calculate_price(
+        &metrics,
+        &analysis,
+        &settings,
+        &batch,
+        &choices,
+        quote.rules,
+        quote.fx,
+    ) {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Pricing calculation failed: {}", e);
+            return Err(QuoteError::Pricing(e.to_string()));
+        }
+    };
+
+    Ok(PricedOrientation {
+        orientation: *orientation,
+        machine,
+        analysis,
+        metrics,
+        calibration,
+        batch,
+        price,
+        cached: cache_hit,
+        project,
+        temp_files,
+    })
+}
+
+/// Look up an uploaded file, hiding files owned by someone else behind 404
+async fn resolve_upload(
+    state: &AppState,
+    req: &QuoteRequest,
+    file_id: Uuid,
+) -> Result<upload::UploadedFile, QuoteError> {
+    let principal = req.principal().ok_or_else(|| {
+        QuoteError::InvalidRequest("file_id requires exactly one of user_id or session_id".to_string())
+    })?;
+
+    let file = match upload::resolve_file(file_id, &state.config).await {
+        Ok(file) => file,
+        Err(upload::ResolveError::NotFound(_)) => return Err(QuoteError::FileNotFound(file_id)),
+        Err(upload::ResolveError::Failed(e)) => {
+            error!("Failed to resolve file {}: {:#}", file_id, e);
+            return Err(QuoteError::UploadService(e.to_string()));
+        }
+    };
+
+    if !file.is_owned_by(principal) {
+        warn!("Rejected quote for file {} by {:?}: not the owner", file_id, principal);
+        return Err(QuoteError::FileNotFound(file_id));
+    }
+
+    Ok(file)
+}
+
+/// Wait for a free slicing slot (bounded queue, reject when full). An async job waits on its
+/// reserved queue position the first time.
+async fn acquire_slot(
+    state: &AppState,
+    reserved: &Mutex<Option<QueueTicket>>,
+) -> Result<slicer::SliceSlot, QuoteError> {
+    let ticket = reserved.lock().unwrap().take();
+    let slot = match ticket {
+        Some(ticket) => Ok(state.slice_pool.acquire_reserved(ticket).await),
+        None => state.slice_pool.acquire().await,
+    };
+    slot.map_err(|e| {
+        warn!("{}: {:?}", e, state.slice_pool.stats());
+        QuoteError::Busy {
+            retry_after_secs: state.config.slice_retry_after_secs,
+        }
+    })
+}
+
+/// Temporary model files, removed on every exit path of the pipeline
+struct TempFiles(Vec<PathBuf>);
+
+impl Drop for TempFiles {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            if let Err(e) = std::fs::remove_file(path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    error!("Failed to cleanup temp file {:?}: {}", path, e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use super::{check_key, ArtifactStore, StoredObject};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use uuid::Uuid;
+
+/// Artifacts in a local directory, for development and tests
+pub struct LocalArtifactStore {
+    root: PathBuf,
+}
+
+impl LocalArtifactStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        LocalArtifactStore { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        check_key(key)?;
+        Ok(self.root.join(key))
+    }
+
+    /// Write via a dot-prefixed temp file and rename, so `list` never sees partial objects
+    async fn write_from(&self, key: &str, source: &Path) -> Result<()> {
+        let path = self.path(key)?;
+        let dir = path.parent().context("Artifact key has no parent")?;
+        tokio::fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("Failed to create artifact directory {:?}", dir))?;
+
+        let tmp_path = dir.join(format!(".{}.tmp", Uuid::new_v4()));
+        tokio::fs::copy(source, &tmp_path)
+            .await
+            .with_context(|| format!("Failed to copy {:?} to the artifact store", source))?;
+        tokio::fs::rename(&tmp_path, &path).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for LocalArtifactStore {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn put(&self, key: &str, path: &Path) -> Result<()> {
+        self.write_from(key, path).await
+    }
+
+    async fn copy(&self, from_key: &str, to_key: &str) -> Result<()> {
+        self.write_from(to_key, &self.path(from_key)?).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let path = self.path(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to delete {:?}", path)),
+        }
+        // Drop the quote directory once it is empty; fails harmlessly otherwise
+        if let Some(dir) = path.parent() {
+            let _ = tokio::fs::remove_dir(dir).await;
+        }
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>> {
+        // Walk the deepest directory the prefix names, then filter by the full prefix
+        let start = match prefix.rfind('/') {
+            Some(i) => self.path(&prefix[..i])?,
+            None => self.root.clone(),
+        };
+
+        let mut objects = Vec::new();
+        let mut dirs = vec![start];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e).with_context(|| format!("Failed to list {:?}", dir)),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                let meta = entry.metadata().await?;
+                if meta.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+
+                let key = path
+                    .strip_prefix(&self.root)?
+                    .to_string_lossy()
+                    .replace(std::path::MAIN_SEPARATOR, "/");
+                if key.starts_with(prefix) {
+                    objects.push(StoredObject {
+                        key,
+                        modified: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                    });
+                }
+            }
+        }
+
+        Ok(objects)
+    }
+}
@@ -0,0 +1,274 @@
+mod local;
+mod s3;
+
+pub use local::LocalArtifactStore;
+pub use s3::S3ArtifactStore;
+
+use crate::config::Config;
+use crate::utils::hash;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Object written by an `ArtifactStore`
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub key: String,
+    pub modified: SystemTime,
+}
+
+/// Object storage for sliced projects.
+///
+/// Selected with `ARTIFACT_STORE`; keys are `/`-separated and never contain `..`.
+#[async_trait]
+pub trait ArtifactStore: Send + Sync {
+    /// Backend name, for logs
+    fn name(&self) -> &'static str;
+
+    async fn put(&self, key: &str, path: &Path) -> Result<()>;
+
+    async fn copy(&self, from_key: &str, to_key: &str) -> Result<()>;
+
+    /// Deleting a missing key is not an error
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    /// Objects whose key starts with `prefix`
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>>;
+}
+
+/// Lifecycle of a stored artifact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactStatus {
+    /// Removed after `ARTIFACT_PENDING_TTL_HOURS` unless the quote is accepted
+    Pending,
+    /// Kept for production
+    Accepted,
+}
+
+/// Where the sliced project of a quote was stored
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtifactRef {
+    /// Object key in the artifact store (the S3 bucket for `ARTIFACT_STORE=s3`)
+    pub key: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+    pub status: ArtifactStatus,
+}
+
+/// Quote-scoped sliced artifacts.
+///
+/// Uploads go to `{prefix}/quotes/pending/{quote_id}/` and are swept after the pending TTL.
+/// Accepting a quote moves them to `{prefix}/quotes/accepted/{quote_id}/`, which is never swept.
+#[derive(Clone)]
+pub struct Artifacts {
+    store: Arc<dyn ArtifactStore>,
+    prefix: String,
+    pending_ttl: Duration,
+}
+
+impl Artifacts {
+    pub fn new(store: Arc<dyn ArtifactStore>, prefix: &str, pending_ttl: Duration) -> Self {
+        Artifacts {
+            store,
+            prefix: prefix.trim_matches('/').to_string(),
+            pending_ttl,
+        }
+    }
+
+    pub fn store_name(&self) -> &'static str {
+        self.store.name()
+    }
+
+    /// Upload a sliced project for a quote that has not been accepted yet, as `sliced.{ext}`
+    pub async fn upload(&self, quote_id: Uuid, path: &Path) -> Result<ArtifactRef> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .context("Artifact path has no extension")?;
+        let key = format!(
+            "{}sliced.{}",
+            self.quote_prefix(ArtifactStatus::Pending, quote_id),
+            extension
+        );
+
+        let size_bytes = tokio::fs::metadata(path)
+            .await
+            .with_context(|| format!("Failed to stat artifact {:?}", path))?
+            .len();
+        let sha256 = hash::sha256_file(path).await?;
+        self.store.put(&key, path).await?;
+
+        info!("Stored sliced artifact {} ({} bytes)", key, size_bytes);
+        Ok(ArtifactRef {
+            key,
+            size_bytes,
+            sha256,
+            status: ArtifactStatus::Pending,
+        })
+    }
+
+    /// Move the artifacts of a quote out of the pending area, returning their new keys.
+    ///
+    /// Accepting twice returns the same keys; `None` when the quote has no artifacts
+    /// (never stored, or already swept).
+    pub async fn accept(&self, quote_id: Uuid) -> Result<Option<Vec<String>>> {
+        let pending_prefix = self.quote_prefix(ArtifactStatus::Pending, quote_id);
+        let accepted_prefix = self.quote_prefix(ArtifactStatus::Accepted, quote_id);
+
+        // Copy everything before deleting anything, so a failure leaves the pending copy
+        let pending = self.store.list(&pending_prefix).await?;
+        for object in &pending {
+            let to_key = object.key.replacen(&pending_prefix, &accepted_prefix, 1);
+            self.store.copy(&object.key, &to_key).await?;
+        }
+        for object in &pending {
+            self.store.delete(&object.key).await?;
+        }
+
+        let mut accepted: Vec<String> = self
+            .store
+            .list(&accepted_prefix)
+            .await?
+            .into_iter()
+            .map(|o| o.key)
+            .collect();
+        if accepted.is_empty() {
+            return Ok(None);
+        }
+        accepted.sort();
+        Ok(Some(accepted))
+    }
+
+    /// Delete pending artifacts older than the TTL, returning how many were removed
+    pub async fn sweep(&self) -> Result<usize> {
+        let cutoff = SystemTime::now() - self.pending_ttl;
+        let mut removed = 0;
+        for object in self.store.list(&self.status_prefix(ArtifactStatus::Pending)).await? {
+            if object.modified < cutoff {
+                self.store.delete(&object.key).await?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Sweep expired pending artifacts every `interval` in the background
+    pub fn spawn_sweeper(&self, interval: Duration) {
+        let artifacts = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match artifacts.sweep().await {
+                    Ok(0) => {}
+                    Ok(n) => info!("Removed {} expired pending artifacts", n),
+                    Err(e) => warn!("Failed to sweep pending artifacts: {:#}", e),
+                }
+            }
+        });
+    }
+
+    fn status_prefix(&self, status: ArtifactStatus) -> String {
+        let status = match status {
+            ArtifactStatus::Pending => "pending",
+            ArtifactStatus::Accepted => "accepted",
+        };
+        if self.prefix.is_empty() {
+            format!("quotes/{}/", status)
+        } else {
+            format!("{}/quotes/{}/", self.prefix, status)
+        }
+    }
+
+    fn quote_prefix(&self, status: ArtifactStatus, quote_id: Uuid) -> String {
+        format!("{}{}/", self.status_prefix(status), quote_id)
+    }
+}
+
+/// Reject keys that could escape the bucket prefix or the local root
+fn check_key(key: &str) -> Result<()> {
+    if key.is_empty() || key.starts_with('/') || key.split('/').any(|part| part == "..") {
+        bail!("Invalid artifact key: {:?}", key);
+    }
+    Ok(())
+}
+
+/// Build the store configured by `ARTIFACT_STORE`, or `None` when artifacts are disabled
+pub async fn from_config(config: &Config) -> Result<Option<Artifacts>> {
+    let store: Arc<dyn ArtifactStore> = match config.artifact_store.as_str() {
+        "none" => return Ok(None),
+        "local" => Arc::new(LocalArtifactStore::new(&config.artifact_local_dir)),
+        "s3" => Arc::new(S3ArtifactStore::new(config).await?),
+        other => bail!("Unknown ARTIFACT_STORE: {} (expected none, s3 or local)", other),
+    };
+
+    Ok(Some(Artifacts::new(
+        store,
+        &config.artifact_prefix,
+        Duration::from_secs(config.artifact_pending_ttl_hours * 3600),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifacts(root: &Path, ttl: Duration) -> Artifacts {
+        Artifacts::new(Arc::new(LocalArtifactStore::new(root)), "pricing-fdm/", ttl)
+    }
+
+    fn sliced_file(dir: &Path) -> std::path::PathBuf {
+        let path = dir.join("model.orient0.sliced.3mf");
+        std::fs::write(&path, b"PK sliced project").unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_upload_then_accept() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = artifacts(&dir.path().join("store"), Duration::from_secs(3600));
+        let quote_id = Uuid::new_v4();
+
+        let stored = store.upload(quote_id, &sliced_file(dir.path())).await.unwrap();
+        assert_eq!(stored.key, format!("pricing-fdm/quotes/pending/{}/sliced.3mf", quote_id));
+        assert_eq!(stored.size_bytes, 17);
+        assert_eq!(stored.sha256, hash::sha256_hex(b"PK sliced project"));
+
+        let accepted_key = format!("pricing-fdm/quotes/accepted/{}/sliced.3mf", quote_id);
+        assert_eq!(store.accept(quote_id).await.unwrap(), Some(vec![accepted_key.clone()]));
+        assert!(!dir.path().join("store").join(&stored.key).exists());
+        assert!(dir.path().join("store").join(&accepted_key).exists());
+
+        // Idempotent
+        assert_eq!(store.accept(quote_id).await.unwrap(), Some(vec![accepted_key]));
+        assert_eq!(store.accept(Uuid::new_v4()).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_sweep_keeps_accepted_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = artifacts(&dir.path().join("store"), Duration::ZERO);
+        let (pending, accepted) = (Uuid::new_v4(), Uuid::new_v4());
+        store.upload(pending, &sliced_file(dir.path())).await.unwrap();
+        store.upload(accepted, &sliced_file(dir.path())).await.unwrap();
+        store.accept(accepted).await.unwrap();
+
+        assert_eq!(store.sweep().await.unwrap(), 1);
+        assert_eq!(store.accept(pending).await.unwrap(), None);
+        assert!(store.accept(accepted).await.unwrap().is_some());
+    }
+
+    #[test]
+    fn test_rejects_escaping_keys() {
+        assert!(check_key("pricing-fdm/quotes/pending/x/sliced.3mf").is_ok());
+        assert!(check_key("../etc/passwd").is_err());
+        assert!(check_key("a/../../b").is_err());
+        assert!(check_key("/abs").is_err());
+    }
+}
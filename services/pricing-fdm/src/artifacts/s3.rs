@@ -0,0 +1,129 @@
+use super::{check_key, ArtifactStore, StoredObject};
+use crate::config::Config;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use aws_config::BehaviorVersion;
+use aws_sdk_s3::config::{Credentials, Region};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Artifacts in the S3 bucket shared with the upload service
+pub struct S3ArtifactStore {
+    client: Client,
+    bucket: String,
+}
+
+impl S3ArtifactStore {
+    pub async fn new(config: &Config) -> Result<Self> {
+        let credentials = Credentials::new(
+            &config.s3_access_key_id,
+            &config.s3_secret_access_key,
+            None, // session token
+            None, // expiry
+            "pricing-fdm",
+        );
+
+        let base_config = aws_config::defaults(BehaviorVersion::latest())
+            .endpoint_url(&config.s3_endpoint)
+            .region(Region::new(config.s3_region.clone()))
+            .credentials_provider(credentials)
+            .load()
+            .await;
+
+        let client = Client::from_conf(
+            aws_sdk_s3::Config::from(&base_config)
+                .to_builder()
+                .force_path_style(true) // Required for Hetzner/MinIO S3
+                .build(),
+        );
+
+        Ok(S3ArtifactStore {
+            client,
+            bucket: config.s3_bucket.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for S3ArtifactStore {
+    fn name(&self) -> &'static str {
+        "s3"
+    }
+
+    async fn put(&self, key: &str, path: &Path) -> Result<()> {
+        check_key(key)?;
+        let body = ByteStream::from_path(path)
+            .await
+            .with_context(|| format!("Failed to read artifact {:?}", path))?;
+
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("Failed to upload artifact {}", key))?;
+        Ok(())
+    }
+
+    async fn copy(&self, from_key: &str, to_key: &str) -> Result<()> {
+        check_key(from_key)?;
+        check_key(to_key)?;
+
+        self.client
+            .copy_object()
+            .bucket(&self.bucket)
+            .copy_source(format!("{}/{}", self.bucket, from_key))
+            .key(to_key)
+            .send()
+            .await
+            .with_context(|| format!("Failed to copy artifact {} to {}", from_key, to_key))?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        check_key(key)?;
+
+        // S3 treats deleting a missing key as success
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .with_context(|| format!("Failed to delete artifact {}", key))?;
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>> {
+        let mut objects = Vec::new();
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(prefix)
+            .into_paginator()
+            .send();
+
+        while let Some(page) = pages.next().await {
+            let page = page.with_context(|| format!("Failed to list artifacts under {}", prefix))?;
+            for object in page.contents() {
+                let Some(key) = object.key() else {
+                    continue;
+                };
+                objects.push(StoredObject {
+                    key: key.to_string(),
+                    modified: object
+                        .last_modified()
+                        .and_then(|t| SystemTime::try_from(*t).ok())
+                        .unwrap_or(SystemTime::UNIX_EPOCH),
+                });
+            }
+        }
+
+        Ok(objects)
+    }
+}
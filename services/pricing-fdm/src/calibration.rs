@@ -0,0 +1,515 @@
+use crate::config::Config;
+use crate::slicer::SliceMetrics;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use tracing::info;
+use uuid::Uuid;
+
+/// Reports whose actual/estimated ratio falls outside this range are rejected as bad data
+/// (a failed print, a mistyped value) instead of skewing the factor
+pub const MIN_RATIO: f64 = 0.5;
+pub const MAX_RATIO: f64 = 2.0;
+
+/// What production measured for a sliced job, next to what the slicer estimated for it.
+///
+/// Any unit of work will do (one copy, a plate, a batch) as long as both sides cover the same job.
+#[derive(Debug, Clone, Copy)]
+pub struct Observation {
+    pub estimated_print_time_hours: f64,
+    pub actual_print_time_hours: f64,
+    pub estimated_filament_weight_g: f64,
+    pub actual_filament_weight_g: f64,
+}
+
+impl Observation {
+    /// Actual/estimated print time and filament weight, rejecting implausible reports
+    pub fn ratios(&self) -> Result<(f64, f64)> {
+        Ok((
+            ratio(
+                "print time",
+                self.actual_print_time_hours,
+                self.estimated_print_time_hours,
+            )?,
+            ratio(
+                "filament weight",
+                self.actual_filament_weight_g,
+                self.estimated_filament_weight_g,
+            )?,
+        ))
+    }
+}
+
+/// Correction for one (machine, material, layer height), as actual / estimated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationEntry {
+    pub machine_id: String,
+    pub material: String,
+    pub layer_thickness: u16,
+    pub print_time_factor: f64,
+    pub filament_weight_factor: f64,
+    /// Reports folded into the factors
+    pub samples: u32,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Correction applied to a quote's slice metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedCalibration {
+    /// 1.0 until the combination has `CALIBRATION_MIN_SAMPLES` reports
+    pub print_time_factor: f64,
+    pub filament_weight_factor: f64,
+    pub samples: u32,
+    /// Slicer estimates per copy before correction; production reports against these
+    pub sliced_print_time_hours: f64,
+    pub sliced_filament_weight_g: f64,
+}
+
+/// On-disk format of `CALIBRATION_PATH`
+#[derive(Default, Serialize, Deserialize)]
+struct CalibrationFile {
+    entries: Vec<CalibrationEntry>,
+    /// Quotes already folded in, so a retried report is not counted twice
+    #[serde(default)]
+    reported_quotes: Vec<ReportedQuote>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReportedQuote {
+    quote_id: Uuid,
+    /// End of the quote's report window, Unix seconds; forgotten after that
+    forget_after: i64,
+}
+
+type Key = (String, String, u16);
+
+/// Print-time and filament correction factors maintained from production reports.
+///
+/// Each report moves the factors towards its actual/estimated ratio by `CALIBRATION_SMOOTHING`
+/// (an exponential moving average; the first report sets them). Factors are persisted to
+/// `CALIBRATION_PATH` after every report and only applied once a combination has
+/// `CALIBRATION_MIN_SAMPLES` reports.
+///
+/// Reports are accepted until `CALIBRATION_REPORT_WINDOW_DAYS` after the quote expired, which
+/// bounds how long a reported quote has to be remembered.
+#[derive(Clone)]
+pub struct CalibrationStore {
+    path: PathBuf,
+    entries: Arc<RwLock<HashMap<Key, CalibrationEntry>>>,
+    /// Quotes already reported, with the end of their report window. Only touched by reports,
+    /// so it doubles as the lock that serializes them: a slower save can never overwrite a
+    /// newer one, and quotes reading `entries` do not wait on the disk
+    reported: Arc<Mutex<HashMap<Uuid, i64>>>,
+    smoothing: f64,
+    min_samples: u32,
+    report_window_secs: i64,
+}
+
+impl CalibrationStore {
+    /// Load saved factors; a missing file starts with none
+    pub fn load(path: impl Into<PathBuf>, config: &Config) -> Result<Self> {
+        let path = path.into();
+        let file = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid calibration file: {:?}", path))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => CalibrationFile::default(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read calibration file: {:?}", path))
+            }
+        };
+        info!(
+            "Loaded {} calibration entries from {} reports",
+            file.entries.len(),
+            file.reported_quotes.len()
+        );
+
+        let entries = file
+            .entries
+            .into_iter()
+            .map(|e| (key(&e.machine_id, &e.material, e.layer_thickness), e))
+            .collect();
+        Ok(CalibrationStore {
+            path,
+            entries: Arc::new(RwLock::new(entries)),
+            reported: Arc::new(Mutex::new(
+                file.reported_quotes
+                    .into_iter()
+                    .map(|r| (r.quote_id, r.forget_after))
+                    .collect(),
+            )),
+            smoothing: config.calibration_smoothing,
+            min_samples: config.calibration_min_samples,
+            report_window_secs: config.calibration_report_window_days as i64 * 24 * 3600,
+        })
+    }
+
+    /// Refuse reports for quotes that expired (`expires_at`, Unix seconds) more than the
+    /// report window ago
+    pub fn check_report_window(&self, expires_at: i64) -> Result<()> {
+        if Utc::now().timestamp() > expires_at.saturating_add(self.report_window_secs) {
+            bail!(
+                "Quote expired more than {} days ago; reports are no longer accepted",
+                self.report_window_secs / (24 * 3600)
+            );
+        }
+        Ok(())
+    }
+
+    pub fn min_samples(&self) -> u32 {
+        self.min_samples
+    }
+
+    /// All entries, including those with too few samples to be applied
+    pub fn entries(&self) -> Vec<CalibrationEntry> {
+        let mut entries: Vec<CalibrationEntry> =
+            self.entries.read().unwrap().values().cloned().collect();
+        entries.sort_by(|a, b| {
+            (&a.machine_id, &a.material, a.layer_thickness)
+                .cmp(&(&b.machine_id, &b.material, b.layer_thickness))
+        });
+        entries
+    }
+
+    /// The entry for one combination, whether or not it is applied yet
+    pub fn entry(
+        &self,
+        machine_id: &str,
+        material: &str,
+        layer_thickness: u16,
+    ) -> Option<CalibrationEntry> {
+        self.entries
+            .read()
+            .unwrap()
+            .get(&key(machine_id, material, layer_thickness))
+            .cloned()
+    }
+
+    /// Fold a production report for a quote into the factors for its combination and save them.
+    ///
+    /// Returns `None` without changing anything when the quote was already reported. The file
+    /// is written on the blocking thread pool.
+    pub async fn record(
+        &self,
+        quote_id: Uuid,
+        expires_at: i64,
+        machine_id: &str,
+        material: &str,
+        layer_thickness: u16,
+        observation: &Observation,
+    ) -> Result<Option<CalibrationEntry>> {
+        let store = self.clone();
+        let key = key(machine_id, material, layer_thickness);
+        let observation = *observation;
+        tokio::task::spawn_blocking(move || {
+            store.record_blocking(quote_id, expires_at, key, &observation)
+        })
+        .await
+        .context("Calibration task panicked")?
+    }
+
+    fn record_blocking(
+        &self,
+        quote_id: Uuid,
+        expires_at: i64,
+        key: Key,
+        observation: &Observation,
+    ) -> Result<Option<CalibrationEntry>> {
+        let (time_ratio, weight_ratio) = observation.ratios()?;
+        self.check_report_window(expires_at)?;
+
+        // Only keep the new factors once they are on disk; the entries lock is held just long
+        // enough to copy them and to insert the result
+        let mut reported = self.reported.lock().unwrap();
+        if reported.contains_key(&quote_id) {
+            return Ok(None);
+        }
+        // Quotes past their window can no longer be reported, so need not be remembered
+        let now = Utc::now().timestamp();
+        reported.retain(|_, forget_after| *forget_after >= now);
+        let forget_after = expires_at.saturating_add(self.report_window_secs);
+        let current = self.entries.read().unwrap().get(&key).cloned();
+        let entry = match current {
+            Some(current) => CalibrationEntry {
+                print_time_factor: current.print_time_factor
+                    + self.smoothing * (time_ratio - current.print_time_factor),
+                filament_weight_factor: current.filament_weight_factor
+                    + self.smoothing * (weight_ratio - current.filament_weight_factor),
+                samples: current.samples + 1,
+                updated_at: Utc::now(),
+                ..current
+            },
+            None => CalibrationEntry {
+                machine_id: key.0.clone(),
+                material: key.1.clone(),
+                layer_thickness: key.2,
+                print_time_factor: time_ratio,
+                filament_weight_factor: weight_ratio,
+                samples: 1,
+                updated_at: Utc::now(),
+            },
+        };
+
+        let mut snapshot: Vec<CalibrationEntry> = self
+            .entries
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(k, _)| **k != key)
+            .map(|(_, e)| e.clone())
+            .collect();
+        snapshot.push(entry.clone());
+        let mut reported_quotes: Vec<ReportedQuote> = reported
+            .iter()
+            .map(|(&quote_id, &forget_after)| ReportedQuote {
+                quote_id,
+                forget_after,
+            })
+            .collect();
+        reported_quotes.push(ReportedQuote {
+            quote_id,
+            forget_after,
+        });
+        save(
+            &self.path,
+            CalibrationFile {
+                entries: snapshot,
+                reported_quotes,
+            },
+        )?;
+
+        reported.insert(quote_id, forget_after);
+        self.entries.write().unwrap().insert(key, entry.clone());
+        Ok(Some(entry))
+    }
+
+    /// Scale slice metrics by the factors for the combination, when it has enough samples
+    pub fn apply(
+        &self,
+        machine_id: &str,
+        material: &str,
+        layer_thickness: u16,
+        metrics: SliceMetrics,
+    ) -> (SliceMetrics, AppliedCalibration) {
+        let entry = self
+            .entries
+            .read()
+            .unwrap()
+            .get(&key(machine_id, material, layer_thickness))
+            .filter(|e| e.samples >= self.min_samples)
+            .cloned();
+
+        let mut applied = AppliedCalibration {
+            print_time_factor: 1.0,
+            filament_weight_factor: 1.0,
+            samples: 0,
+            sliced_print_time_hours: metrics.print_time_hours,
+            sliced_filament_weight_g: metrics.filament_weight_g,
+        };
+        let Some(entry) = entry else {
+            return (metrics, applied);
+        };
+
+        applied.print_time_factor = entry.print_time_factor;
+        applied.filament_weight_factor = entry.filament_weight_factor;
+        applied.samples = entry.samples;
+        (
+            metrics.scaled(entry.print_time_factor, entry.filament_weight_factor),
+            applied,
+        )
+    }
+}
+
+fn key(machine_id: &str, material: &str, layer_thickness: u16) -> Key {
+    (machine_id.to_lowercase(), material.to_lowercase(), layer_thickness)
+}
+
+fn ratio(name: &str, actual: f64, estimated: f64) -> Result<f64> {
+    if !(actual.is_finite() && actual > 0.0 && estimated.is_finite() && estimated > 0.0) {
+        bail!("Estimated and actual {} must be positive", name);
+    }
+    let ratio = actual / estimated;
+    if !(MIN_RATIO..=MAX_RATIO).contains(&ratio) {
+        bail!(
+            "Actual {} is {:.2}x the estimate, outside {}-{}x; not recorded",
+            name,
+            ratio,
+            MIN_RATIO,
+            MAX_RATIO
+        );
+    }
+    Ok(ratio)
+}
+
+/// Write through a temporary file so a crash never leaves a truncated file behind
+fn save(path: &Path, file: CalibrationFile) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create calibration directory: {:?}", dir))?;
+    }
+    let content = serde_json::to_string_pretty(&file)?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, content).with_context(|| format!("Failed to write {:?}", tmp))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {:?}", path))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(path: &Path) -> CalibrationStore {
+        let mut config = Config::for_tests();
+        config.calibration_smoothing = 0.5;
+        config.calibration_min_samples = 2;
+        CalibrationStore::load(path, &config).unwrap()
+    }
+
+    fn observation(actual_hours: f64, actual_g: f64) -> Observation {
+        Observation {
+            estimated_print_time_hours: 2.0,
+            actual_print_time_hours: actual_hours,
+            estimated_filament_weight_g: 100.0,
+            actual_filament_weight_g: actual_g,
+        }
+    }
+
+    /// Quote expiry `days` from now, as in the token
+    fn expires_in(days: i64) -> i64 {
+        Utc::now().timestamp() + days * 24 * 3600
+    }
+
+    fn metrics() -> SliceMetrics {
+        let plate = crate::slicer::PlateMetrics {
+            plate: 1,
+            print_time_hours: 1.0,
+            filament_weight_g: 20.0,
+            filament_length_mm: 6700.0,
+            filament_volume_cm3: 16.1,
+            objects: Vec::new(),
+            layer_count: 100,
+            max_z_mm: 20.0,
+            travel_mm: 0.0,
+            features: Vec::new(),
+        };
+        SliceMetrics::from_plates(vec![plate])
+    }
+
+    #[tokio::test]
+    async fn test_factors_follow_reports_and_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("calibration.json");
+        let store = open(&path);
+
+        let quote_id = Uuid::new_v4();
+        let first = store
+            .record(quote_id, expires_in(1), "generic-200", "PLA", 200, &observation(2.4, 105.0))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!((first.print_time_factor - 1.2).abs() < 1e-9);
+        assert_eq!(first.samples, 1);
+
+        // A retried report for the same quote is ignored
+        let retried = store
+            .record(quote_id, expires_in(1), "generic-200", "pla", 200, &observation(2.6, 95.0))
+            .await
+            .unwrap();
+        assert!(retried.is_none());
+        assert_eq!(store.entry("generic-200", "pla", 200), Some(first));
+
+        // Below min_samples nothing is applied
+        let (raw, applied) = store.apply("generic-200", "pla", 200, metrics());
+        assert_eq!(raw.print_time_hours, 1.0);
+        assert_eq!(applied.print_time_factor, 1.0);
+
+        // Halfway from 1.2 towards 1.3 with smoothing 0.5
+        let second = store
+            .record(
+                Uuid::new_v4(),
+                expires_in(1),
+                "generic-200",
+                "pla",
+                200,
+                &observation(2.6, 95.0),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert!((second.print_time_factor - 1.25).abs() < 1e-9);
+        assert!((second.filament_weight_factor - 1.0).abs() < 1e-9);
+
+        let (calibrated, applied) = store.apply("GENERIC-200", "pla", 200, metrics());
+        assert!((calibrated.print_time_hours - 1.25).abs() < 1e-9);
+        assert!((calibrated.plates[0].print_time_hours - 1.25).abs() < 1e-9);
+        assert_eq!(applied.samples, 2);
+        assert_eq!(applied.sliced_print_time_hours, 1.0);
+
+        // Other layer heights are calibrated separately
+        assert_eq!(store.apply("generic-200", "pla", 100, metrics()).1.samples, 0);
+
+        let reloaded = open(&path);
+        assert_eq!(reloaded.entries(), store.entries());
+        let retried = reloaded
+            .record(quote_id, expires_in(1), "generic-200", "pla", 200, &observation(2.6, 95.0))
+            .await
+            .unwrap();
+        assert!(retried.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reported_quotes_are_forgotten_after_their_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("calibration.json");
+        let store = open(&path);
+
+        // A quote whose report window ended yesterday
+        let old = Uuid::new_v4();
+        store.reported.lock().unwrap().insert(old, expires_in(-1));
+        let late = store
+            .record(
+                Uuid::new_v4(),
+                expires_in(-91),
+                "generic-200",
+                "pla",
+                200,
+                &observation(2.0, 100.0),
+            )
+            .await;
+        assert!(late.unwrap_err().to_string().contains("no longer accepted"));
+
+        store
+            .record(
+                Uuid::new_v4(),
+                expires_in(-89),
+                "generic-200",
+                "pla",
+                200,
+                &observation(2.0, 100.0),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        let reported = store.reported.lock().unwrap().clone();
+        assert_eq!(reported.len(), 1);
+        assert!(!reported.contains_key(&old));
+        assert_eq!(open(&path).reported.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_implausible_reports() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = open(&dir.path().join("calibration.json"));
+
+        for observation in [observation(5.0, 100.0), observation(2.0, 0.0)] {
+            let recorded = store
+                .record(Uuid::new_v4(), expires_in(1), "generic-200", "pla", 200, &observation)
+                .await;
+            assert!(recorded.is_err());
+        }
+        assert!(store.entries().is_empty());
+        assert!(!dir.path().join("calibration.json").exists());
+    }
+}
@@ -1,66 +1,125 @@
-use anyhow::{Context, Result};
-use serde::Deserialize;
+use crate::fx::{ExchangeRates, EXCHANGE_RATES_FILE};
+use crate::machine::MachineRegistry;
+use crate::materials::MaterialCatalog;
+use crate::money::Currency;
+use crate::rules::RULES_FILE;
+use anyhow::{bail, Context, Result};
+use std::path::Path;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone)]
 pub struct Config {
     // Service
     pub host: String,
     pub port: u16,
 
+    // Slicer backend ("orca" or "mock"; the mock reads canned metrics from the fixture if set)
+    pub slicer_backend: String,
+    pub slicer_mock_fixture: Option<String>,
+
     // Orca Slicer
     pub orca_profiles_dir: String,
     pub orca_binary: String,
     pub temp_dir: String,
 
-    // Pricing parameters
-    pub base_fee_usd: f64,
-    pub machine_rate_usd_per_hour: f64,
-    pub margin_multiplier: f64,
+    // Pricing rules file (defaults to orca_profiles_dir/pricing.toml), polled for changes
+    pub pricing_rules_path: String,
+    pub pricing_rules_reload_secs: u64,
+
+    // Material catalog (loaded from orca_profiles_dir/materials.json)
+    pub materials: MaterialCatalog,
+
+    // Printer fleet (loaded from orca_profiles_dir/machines.json)
+    pub machines: MachineRegistry,
+
+    // Quote currency (loaded from EXCHANGE_RATES_PATH, default orca_profiles_dir/exchange_rates.toml)
+    pub default_currency: Currency,
+    pub exchange_rates: ExchangeRates,
 
-    // Material costs (per gram)
-    pub material_costs: MaterialCosts,
+    // Batch pricing
+    pub max_quantity: u32,
+    pub nesting_spacing_mm: f64,
+
+    // Orientation (best mesh-scored candidates sliced per quote; the cheapest is quoted)
+    pub orient_slice_candidates: usize,
 
     // Request limits
     pub max_file_size_mb: u64,
+    /// Largest a single 3MF zip entry may decompress to
+    pub max_archive_entry_mb: u64,
+    /// Most triangles a 3MF may instantiate through nested components
+    pub max_triangles: usize,
+    /// Mesh defects tolerated before a model is rejected; above 0, quotes may be based on a
+    /// volume that is off by the holes
+    pub max_boundary_edges: usize,
+    pub max_non_manifold_edges: usize,
     pub request_timeout_secs: u64,
-}
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct MaterialCosts {
-    pub pla: f64,
-    pub abs: f64,
-    pub petg: f64,
-    pub abs_esd: f64,
-    pub asa: f64,
-    pub nylon: f64,
-    pub pc: f64,
-    pub tpu: f64,
-}
+    // Model download (hosts are exact names or `*.suffix`; empty refuses all)
+    pub download_allowed_hosts: Vec<String>,
+    pub download_allow_private_networks: bool,
+    pub download_connect_timeout_secs: u64,
+    pub download_read_timeout_secs: u64,
+    pub download_max_redirects: usize,
 
-impl MaterialCosts {
-    pub fn get(&self, material: &str) -> Option<f64> {
-        match material.to_lowercase().as_str() {
-            "pla" => Some(self.pla),
-            "abs" => Some(self.abs),
-            "petg" => Some(self.petg),
-            "abs-esd" => Some(self.abs_esd),
-            "asa" => Some(self.asa),
-            "nylon" => Some(self.nylon),
-            "pc" => Some(self.pc),
-            "tpu" => Some(self.tpu),
-            _ => None,
-        }
-    }
+    // Upload service, for quotes by file_id (token sent as X-Internal-Token)
+    pub upload_service_url: String,
+    pub internal_service_token: Option<String>,
 
-    pub fn all_materials() -> Vec<&'static str> {
-        vec!["pla", "abs", "petg", "abs-esd", "asa", "nylon", "pc", "tpu"]
-    }
+    // Slicing concurrency
+    pub max_concurrent_slices: usize,
+    pub max_queued_slices: usize,
+    pub slice_retry_after_secs: u64,
+
+    // Slice result cache (0 MB disables it)
+    pub slice_cache_dir: String,
+    pub slice_cache_max_mb: u64,
+
+    // Async quote jobs (callback hosts like download hosts, but a separate list)
+    pub quote_job_ttl_secs: u64,
+    /// Pending or running jobs accepted before new ones are rejected with 503
+    pub max_unfinished_jobs: usize,
+    pub callback_allowed_hosts: Vec<String>,
+    pub callback_allow_private_networks: bool,
+
+    // Quote validity and token signing (no tokens are issued without a secret)
+    pub quote_valid_hours: u64,
+    pub quote_token_secret: Option<String>,
+
+    // Sliced artifacts ("none", "s3" or "local"); pending ones are removed after the TTL
+    pub artifact_store: String,
+    pub artifact_local_dir: String,
+    pub artifact_prefix: String,
+    pub artifact_pending_ttl_hours: u64,
+
+    // Print-time and filament calibration from production reports
+    pub calibration_path: String,
+    pub calibration_smoothing: f64,
+    pub calibration_min_samples: u32,
+    /// Days after a quote expires that production may still report it
+    pub calibration_report_window_days: u64,
+
+    // S3 (shared bucket with the upload service, used when ARTIFACT_STORE=s3)
+    pub s3_endpoint: String,
+    pub s3_bucket: String,
+    pub s3_region: String,
+    pub s3_access_key_id: String,
+    pub s3_secret_access_key: String,
 }
 
 impl Config {
     pub fn from_env() -> Result<Self> {
         dotenvy::dotenv().ok();
 
+        let orca_profiles_dir =
+            std::env::var("ORCA_PROFILES_DIR").unwrap_or_else(|_| "/app/profiles".to_string());
+        let materials = MaterialCatalog::load(&orca_profiles_dir)?;
+        let machines = MachineRegistry::load(&orca_profiles_dir, &materials)?;
+        let exchange_rates = ExchangeRates::load(Path::new(
+            &std::env::var("EXCHANGE_RATES_PATH")
+                .unwrap_or_else(|_| format!("{}/{}", orca_profiles_dir, EXCHANGE_RATES_FILE)),
+        ))?;
+        let temp_dir = std::env::var("TEMP_DIR").unwrap_or_else(|_| "/tmp".to_string());
+
         let config = Config {
             host: std::env::var("PRICING_FDM_HOST")
                 .unwrap_or_else(|_| "0.0.0.0".to_string()),
@@ -69,64 +128,215 @@ impl Config {
                 .parse()
                 .context("PRICING_FDM_PORT must be a valid u16")?,
 
-            orca_profiles_dir: std::env::var("ORCA_PROFILES_DIR")
-                .unwrap_or_else(|_| "/app/profiles".to_string()),
+            slicer_backend: std::env::var("SLICER_BACKEND")
+                .unwrap_or_else(|_| "orca".to_string())
+                .to_lowercase(),
+            slicer_mock_fixture: std::env::var("SLICER_MOCK_FIXTURE").ok(),
+
+            pricing_rules_path: std::env::var("PRICING_RULES_PATH")
+                .unwrap_or_else(|_| format!("{}/{}", orca_profiles_dir, RULES_FILE)),
+            pricing_rules_reload_secs: std::env::var("PRICING_RULES_RELOAD_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .context("PRICING_RULES_RELOAD_SECS must be a valid u64")?,
+
+            orca_profiles_dir,
             orca_binary: std::env::var("ORCA_BINARY")
                 .unwrap_or_else(|_| "orca-slicer".to_string()),
-            temp_dir: std::env::var("TEMP_DIR")
-                .unwrap_or_else(|_| "/tmp".to_string()),
+            slice_cache_dir: std::env::var("SLICE_CACHE_DIR")
+                .unwrap_or_else(|_| format!("{}/slice_cache", temp_dir)),
+            temp_dir,
 
-            base_fee_usd: std::env::var("BASE_FEE_USD")
-                .unwrap_or_else(|_| "5.00".to_string())
+            materials,
+            machines,
+
+            default_currency: std::env::var("DEFAULT_CURRENCY")
+                .unwrap_or_else(|_| "PLN".to_string())
                 .parse()
-                .context("BASE_FEE_USD must be a valid f64")?,
-            machine_rate_usd_per_hour: std::env::var("MACHINE_RATE_USD_PER_HOUR")
-                .unwrap_or_else(|_| "10.00".to_string())
+                .context("DEFAULT_CURRENCY must be PLN, EUR or USD")?,
+            exchange_rates,
+
+            max_quantity: std::env::var("MAX_QUANTITY")
+                .unwrap_or_else(|_| "1000".to_string())
                 .parse()
-                .context("MACHINE_RATE_USD_PER_HOUR must be a valid f64")?,
-            margin_multiplier: std::env::var("MARGIN_MULTIPLIER")
-                .unwrap_or_else(|_| "1.30".to_string())
+                .context("MAX_QUANTITY must be a valid u32")?,
+            nesting_spacing_mm: std::env::var("NESTING_SPACING_MM")
+                .unwrap_or_else(|_| "5.0".to_string())
                 .parse()
-                .context("MARGIN_MULTIPLIER must be a valid f64")?,
+                .context("NESTING_SPACING_MM must be a valid f64")?,
 
-            material_costs: MaterialCosts {
-                pla: Self::parse_env_f64("MATERIAL_PLA_COST_PER_G", 0.02)?,
-                abs: Self::parse_env_f64("MATERIAL_ABS_COST_PER_G", 0.025)?,
-                petg: Self::parse_env_f64("MATERIAL_PETG_COST_PER_G", 0.03)?,
-                abs_esd: Self::parse_env_f64("MATERIAL_ABS_ESD_COST_PER_G", 0.035)?,
-                asa: Self::parse_env_f64("MATERIAL_ASA_COST_PER_G", 0.028)?,
-                nylon: Self::parse_env_f64("MATERIAL_NYLON_COST_PER_G", 0.04)?,
-                pc: Self::parse_env_f64("MATERIAL_PC_COST_PER_G", 0.045)?,
-                tpu: Self::parse_env_f64("MATERIAL_TPU_COST_PER_G", 0.035)?,
-            },
+            orient_slice_candidates: std::env::var("ORIENT_SLICE_CANDIDATES")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse()
+                .context("ORIENT_SLICE_CANDIDATES must be a valid usize")?,
 
             max_file_size_mb: std::env::var("MAX_FILE_SIZE_MB")
                 .unwrap_or_else(|_| "100".to_string())
                 .parse()
                 .context("MAX_FILE_SIZE_MB must be a valid u64")?,
+            max_archive_entry_mb: std::env::var("MAX_ARCHIVE_ENTRY_MB")
+                .unwrap_or_else(|_| "512".to_string())
+                .parse()
+                .context("MAX_ARCHIVE_ENTRY_MB must be a valid u64")?,
+            max_triangles: std::env::var("MAX_TRIANGLES")
+                .unwrap_or_else(|_| "5000000".to_string())
+                .parse()
+                .context("MAX_TRIANGLES must be a valid usize")?,
+            max_boundary_edges: std::env::var("MAX_BOUNDARY_EDGES")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .context("MAX_BOUNDARY_EDGES must be a valid usize")?,
+            max_non_manifold_edges: std::env::var("MAX_NON_MANIFOLD_EDGES")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .context("MAX_NON_MANIFOLD_EDGES must be a valid usize")?,
             request_timeout_secs: std::env::var("REQUEST_TIMEOUT_SECS")
                 .unwrap_or_else(|_| "60".to_string())
                 .parse()
                 .context("REQUEST_TIMEOUT_SECS must be a valid u64")?,
+
+            download_allowed_hosts: std::env::var("DOWNLOAD_ALLOWED_HOSTS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|h| h.trim().to_string())
+                .filter(|h| !h.is_empty())
+                .collect(),
+            download_allow_private_networks: std::env::var("DOWNLOAD_ALLOW_PRIVATE_NETWORKS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("DOWNLOAD_ALLOW_PRIVATE_NETWORKS must be true or false")?,
+            download_connect_timeout_secs: std::env::var("DOWNLOAD_CONNECT_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .context("DOWNLOAD_CONNECT_TIMEOUT_SECS must be a valid u64")?,
+            download_read_timeout_secs: std::env::var("DOWNLOAD_READ_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .context("DOWNLOAD_READ_TIMEOUT_SECS must be a valid u64")?,
+            download_max_redirects: std::env::var("DOWNLOAD_MAX_REDIRECTS")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .context("DOWNLOAD_MAX_REDIRECTS must be a valid usize")?,
+
+            upload_service_url: std::env::var("UPLOAD_SERVICE_URL")
+                .unwrap_or_else(|_| "http://upload:8082".to_string()),
+            internal_service_token: std::env::var("INTERNAL_SERVICE_TOKEN")
+                .ok()
+                .filter(|t| !t.is_empty()),
+
+            max_concurrent_slices: std::env::var("MAX_CONCURRENT_SLICES")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .context("MAX_CONCURRENT_SLICES must be a valid usize")?,
+            max_queued_slices: std::env::var("MAX_QUEUED_SLICES")
+                .unwrap_or_else(|_| "8".to_string())
+                .parse()
+                .context("MAX_QUEUED_SLICES must be a valid usize")?,
+            slice_retry_after_secs: std::env::var("SLICE_RETRY_AFTER_SECS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .context("SLICE_RETRY_AFTER_SECS must be a valid u64")?,
+
+            slice_cache_max_mb: std::env::var("SLICE_CACHE_MAX_MB")
+                .unwrap_or_else(|_| "256".to_string())
+                .parse()
+                .context("SLICE_CACHE_MAX_MB must be a valid u64")?,
+
+            quote_job_ttl_secs: std::env::var("QUOTE_JOB_TTL_SECS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .context("QUOTE_JOB_TTL_SECS must be a valid u64")?,
+            max_unfinished_jobs: std::env::var("MAX_UNFINISHED_JOBS")
+                .unwrap_or_else(|_| "32".to_string())
+                .parse()
+                .context("MAX_UNFINISHED_JOBS must be a valid usize")?,
+            callback_allowed_hosts: std::env::var("CALLBACK_ALLOWED_HOSTS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|h| h.trim().to_string())
+                .filter(|h| !h.is_empty())
+                .collect(),
+            callback_allow_private_networks: std::env::var("CALLBACK_ALLOW_PRIVATE_NETWORKS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("CALLBACK_ALLOW_PRIVATE_NETWORKS must be true or false")?,
+
+            quote_valid_hours: std::env::var("QUOTE_VALID_HOURS")
+                .unwrap_or_else(|_| "24".to_string())
+                .parse()
+                .context("QUOTE_VALID_HOURS must be a valid u64")?,
+            quote_token_secret: std::env::var("QUOTE_TOKEN_SECRET")
+                .ok()
+                .filter(|s| !s.is_empty()),
+
+            artifact_store: std::env::var("ARTIFACT_STORE")
+                .unwrap_or_else(|_| "none".to_string())
+                .to_lowercase(),
+            artifact_local_dir: std::env::var("ARTIFACT_LOCAL_DIR")
+                .unwrap_or_else(|_| "/var/lib/pricing-fdm/artifacts".to_string()),
+            artifact_prefix: std::env::var("ARTIFACT_PREFIX")
+                .unwrap_or_else(|_| "pricing-fdm".to_string()),
+            artifact_pending_ttl_hours: std::env::var("ARTIFACT_PENDING_TTL_HOURS")
+                .unwrap_or_else(|_| "48".to_string())
+                .parse()
+                .context("ARTIFACT_PENDING_TTL_HOURS must be a valid u64")?,
+
+            calibration_path: std::env::var("CALIBRATION_PATH")
+                .unwrap_or_else(|_| "/var/lib/pricing-fdm/calibration.json".to_string()),
+            calibration_smoothing: std::env::var("CALIBRATION_SMOOTHING")
+                .unwrap_or_else(|_| "0.2".to_string())
+                .parse()
+                .context("CALIBRATION_SMOOTHING must be a valid f64")?,
+            calibration_min_samples: std::env::var("CALIBRATION_MIN_SAMPLES")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .context("CALIBRATION_MIN_SAMPLES must be a valid u32")?,
+            calibration_report_window_days: std::env::var("CALIBRATION_REPORT_WINDOW_DAYS")
+                .unwrap_or_else(|_| "90".to_string())
+                .parse()
+                .context("CALIBRATION_REPORT_WINDOW_DAYS must be a valid u64")?,
+
+            s3_endpoint: std::env::var("S3_ENDPOINT").unwrap_or_default(),
+            s3_bucket: std::env::var("S3_BUCKET").unwrap_or_else(|_| "rapidfab".to_string()),
+            s3_region: std::env::var("S3_REGION").unwrap_or_else(|_| "eu-central-1".to_string()),
+            s3_access_key_id: std::env::var("S3_ACCESS_KEY_ID").unwrap_or_default(),
+            s3_secret_access_key: std::env::var("S3_SECRET_ACCESS_KEY").unwrap_or_default(),
         };
 
-        Ok(config)
-    }
+        if config.orient_slice_candidates == 0 {
+            bail!("ORIENT_SLICE_CANDIDATES must be at least 1");
+        }
+        if !(config.calibration_smoothing > 0.0 && config.calibration_smoothing <= 1.0) {
+            bail!("CALIBRATION_SMOOTHING must be in (0, 1]");
+        }
+        if config.quote_token_secret.as_ref().is_some_and(|s| s.len() < 32) {
+            bail!("QUOTE_TOKEN_SECRET must be at least 32 bytes");
+        }
+        if config.artifact_store == "s3"
+            && (config.s3_endpoint.is_empty()
+                || config.s3_access_key_id.is_empty()
+                || config.s3_secret_access_key.is_empty())
+        {
+            bail!("ARTIFACT_STORE=s3 requires S3_ENDPOINT, S3_ACCESS_KEY_ID and S3_SECRET_ACCESS_KEY");
+        }
 
-    fn parse_env_f64(var_name: &str, default: f64) -> Result<f64> {
-        std::env::var(var_name)
-            .unwrap_or_else(|_| default.to_string())
-            .parse()
-            .with_context(|| format!("{} must be a valid f64", var_name))
+        Ok(config)
     }
 
-    /// Masked config for logging (hide nothing sensitive here, but keep pattern)
+    /// Masked config for logging (S3 credentials, the internal token and the token secret are left out)
     pub fn masked(&self) -> MaskedConfig {
         MaskedConfig {
             host: self.host.clone(),
             port: self.port,
+            slicer_backend: self.slicer_backend.clone(),
             orca_profiles_dir: self.orca_profiles_dir.clone(),
             orca_binary: self.orca_binary.clone(),
+            pricing_rules_path: self.pricing_rules_path.clone(),
+            upload_service_url: self.upload_service_url.clone(),
+            artifact_store: self.artifact_store.clone(),
+            calibration_path: self.calibration_path.clone(),
+            s3_endpoint: self.s3_endpoint.clone(),
+            s3_bucket: self.s3_bucket.clone(),
         }
     }
 }
@@ -135,6 +345,78 @@ impl Config {
 pub struct MaskedConfig {
     pub host: String,
     pub port: u16,
+    pub slicer_backend: String,
     pub orca_profiles_dir: String,
     pub orca_binary: String,
+    pub pricing_rules_path: String,
+    pub upload_service_url: String,
+    pub artifact_store: String,
+    pub calibration_path: String,
+    pub s3_endpoint: String,
+    pub s3_bucket: String,
+}
+
+#[cfg(test)]
+impl Config {
+    /// Defaults with the bundled profiles, without reading the environment
+    pub(crate) fn for_tests() -> Config {
+        let profiles_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/profiles").to_string();
+        let materials = MaterialCatalog::load(&profiles_dir).unwrap();
+        Config {
+            host: "127.0.0.1".to_string(),
+            port: 8083,
+            slicer_backend: "mock".to_string(),
+            slicer_mock_fixture: None,
+            machines: MachineRegistry::load(&profiles_dir, &materials).unwrap(),
+            materials,
+            default_currency: Currency::Pln,
+            exchange_rates: ExchangeRates::load(&Path::new(&profiles_dir).join(EXCHANGE_RATES_FILE))
+                .unwrap(),
+            pricing_rules_path: format!("{}/{}", profiles_dir, RULES_FILE),
+            pricing_rules_reload_secs: 30,
+            orca_profiles_dir: profiles_dir,
+            orca_binary: "orca-slicer".to_string(),
+            temp_dir: std::env::temp_dir().display().to_string(),
+            max_quantity: 1000,
+            nesting_spacing_mm: 5.0,
+            orient_slice_candidates: 1,
+            max_file_size_mb: 100,
+            max_archive_entry_mb: 512,
+            max_triangles: 5_000_000,
+            max_boundary_edges: 0,
+            max_non_manifold_edges: 0,
+            request_timeout_secs: 60,
+            download_allowed_hosts: Vec::new(),
+            download_allow_private_networks: false,
+            download_connect_timeout_secs: 5,
+            download_read_timeout_secs: 30,
+            download_max_redirects: 3,
+            upload_service_url: "http://127.0.0.1:8082".to_string(),
+            internal_service_token: None,
+            max_concurrent_slices: 2,
+            max_queued_slices: 8,
+            slice_retry_after_secs: 10,
+            slice_cache_dir: std::env::temp_dir().join("slice_cache").display().to_string(),
+            slice_cache_max_mb: 0,
+            quote_job_ttl_secs: 3600,
+            max_unfinished_jobs: 32,
+            callback_allowed_hosts: Vec::new(),
+            callback_allow_private_networks: false,
+            quote_valid_hours: 24,
+            quote_token_secret: None,
+            artifact_store: "none".to_string(),
+            artifact_local_dir: std::env::temp_dir().join("artifacts").display().to_string(),
+            artifact_prefix: "pricing-fdm".to_string(),
+            artifact_pending_ttl_hours: 48,
+            calibration_path: std::env::temp_dir().join("calibration.json").display().to_string(),
+            calibration_smoothing: 0.2,
+            calibration_min_samples: 3,
+            calibration_report_window_days: 90,
+            s3_endpoint: String::new(),
+            s3_bucket: "rapidfab".to_string(),
+            s3_region: "eu-central-1".to_string(),
+            s3_access_key_id: String::new(),
+            s3_secret_access_key: String::new(),
+        }
+    }
 }
@@ -0,0 +1,232 @@
+use crate::money::{Currency, Money};
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Exchange rate table inside `orca_profiles_dir`
+pub const EXCHANGE_RATES_FILE: &str = "exchange_rates.toml";
+
+/// Rate applied to a quote
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ExchangeRate {
+    pub from: Currency,
+    pub to: Currency,
+    /// Units of `to` per unit of `from`
+    pub rate: f64,
+    /// Date of the table the rate was taken from (absent when no conversion happens)
+    pub effective_date: Option<NaiveDate>,
+}
+
+impl ExchangeRate {
+    pub fn identity(currency: Currency) -> Self {
+        ExchangeRate {
+            from: currency,
+            to: currency,
+            rate: 1.0,
+            effective_date: None,
+        }
+    }
+
+    /// Convert a major-unit amount in `from` to minor units of `to`
+    pub fn to_minor(&self, amount: f64) -> Money {
+        Money::from_major(amount * self.rate)
+    }
+}
+
+/// Mid rates in PLN per unit of currency (the NBP table A convention), by effective date
+#[derive(Debug, Clone, Default)]
+pub struct ExchangeRates {
+    rates: HashMap<Currency, BTreeMap<NaiveDate, f64>>,
+}
+
+/// Manually maintained table
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManualTable {
+    rates: Vec<ManualRates>,
+}
+
+#[derive(Deserialize)]
+struct ManualRates {
+    effective_date: NaiveDate,
+    /// Currency code -> PLN per unit
+    #[serde(flatten)]
+    pln: HashMap<String, f64>,
+}
+
+/// NBP table A as returned by api.nbp.pl (one table, or an array for a date range)
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NbpExport {
+    Tables(Vec<NbpTable>),
+    Table(NbpTable),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NbpTable {
+    effective_date: NaiveDate,
+    rates: Vec<NbpRate>,
+}
+
+#[derive(Deserialize)]
+struct NbpRate {
+    code: String,
+    mid: f64,
+}
+
+impl ExchangeRates {
+    /// Load a manual TOML table, or an NBP table A export when the file ends in `.json`
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read exchange rates: {:?}", path))?;
+        let rates = if path.extension().is_some_and(|e| e == "json") {
+            Self::from_nbp_json(&content)
+        } else {
+            Self::from_toml(&content)
+        };
+        rates.with_context(|| format!("Invalid exchange rates: {:?}", path))
+    }
+
+    fn from_toml(content: &str) -> Result<Self> {
+        let table: ManualTable = toml::from_str(content)?;
+        let mut rates = ExchangeRates::default();
+        for day in table.rates {
+            for (code, pln) in day.pln {
+                rates.insert(code.parse()?, day.effective_date, pln)?;
+            }
+        }
+        Ok(rates)
+    }
+
+    fn from_nbp_json(content: &str) -> Result<Self> {
+        let tables = match serde_json::from_str(content)? {
+            NbpExport::Tables(tables) => tables,
+            NbpExport::Table(table) => vec![table],
+        };
+
+        let mut rates = ExchangeRates::default();
+        for table in tables {
+            // Table A lists ~30 currencies; keep the ones we quote in
+            for rate in table.rates {
+                if let Ok(currency) = rate.code.parse() {
+                    rates.insert(currency, table.effective_date, rate.mid)?;
+                }
+            }
+        }
+        Ok(rates)
+    }
+
+    fn insert(&mut self, currency: Currency, date: NaiveDate, pln: f64) -> Result<()> {
+        if currency == Currency::Pln {
+            bail!("PLN is the reference currency and has no rate");
+        }
+        if !(pln.is_finite() && pln > 0.0) {
+            bail!("Rate for {} on {} must be positive", currency, date);
+        }
+        self.rates.entry(currency).or_default().insert(date, pln);
+        Ok(())
+    }
+
+    /// Latest PLN rate effective on or before `on`
+    fn pln_per_unit(&self, currency: Currency, on: NaiveDate) -> Result<(f64, Option<NaiveDate>)> {
+        if currency == Currency::Pln {
+            return Ok((1.0, None));
+        }
+        self.rates
+            .get(&currency)
+            .and_then(|by_date| by_date.range(..=on).next_back())
+            .map(|(date, rate)| (*rate, Some(*date)))
+            .with_context(|| format!("No {} exchange rate effective on {}", currency, on))
+    }
+
+    /// Rate from one currency to another using the tables effective on `on`
+    pub fn rate(&self, from: Currency, to: Currency, on: NaiveDate) -> Result<ExchangeRate> {
+        if from == to {
+            return Ok(ExchangeRate::identity(from));
+        }
+
+        let (from_pln, from_date) = self.pln_per_unit(from, on)?;
+        let (to_pln, to_date) = self.pln_per_unit(to, on)?;
+
+        Ok(ExchangeRate {
+            from,
+            to,
+            rate: from_pln / to_pln,
+            // Report the older table when both legs need one
+            effective_date: from_date.into_iter().chain(to_date).min(),
+        })
+    }
+
+    /// Most recent effective date per currency
+    pub fn latest(&self) -> impl Iterator<Item = (Currency, NaiveDate)> + '_ {
+        self.rates
+            .iter()
+            .filter_map(|(currency, by_date)| Some((*currency, *by_date.keys().next_back()?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_manual_table_uses_latest_effective_rate() {
+        let rates = ExchangeRates::from_toml(
+            r#"
+            [[rates]]
+            effective_date = "2026-10-01"
+            USD = 3.60
+            EUR = 4.20
+
+            [[rates]]
+            effective_date = "2026-10-10"
+            USD = 3.70
+            "#,
+        )
+        .unwrap();
+
+        let usd = rates.rate(Currency::Usd, Currency::Pln, date("2026-10-09")).unwrap();
+        assert_eq!((usd.rate, usd.effective_date), (3.60, Some(date("2026-10-01"))));
+        let usd = rates.rate(Currency::Usd, Currency::Pln, date("2026-10-17")).unwrap();
+        assert_eq!(usd.rate, 3.70);
+        assert_eq!(usd.to_minor(10.0), Money(3700));
+
+        // Cross rate through PLN, dated by the older table
+        let eur = rates.rate(Currency::Usd, Currency::Eur, date("2026-10-17")).unwrap();
+        assert!((eur.rate - 3.70 / 4.20).abs() < 1e-12);
+        assert_eq!(eur.effective_date, Some(date("2026-10-01")));
+
+        assert!(rates.rate(Currency::Usd, Currency::Pln, date("2026-09-30")).is_err());
+        assert_eq!(rates.rate(Currency::Usd, Currency::Usd, date("2026-09-30")).unwrap().rate, 1.0);
+    }
+
+    #[test]
+    fn test_nbp_export() {
+        let rates = ExchangeRates::from_nbp_json(
+            r#"[{"table":"A","no":"201/A/NBP/2026","effectiveDate":"2026-10-16","rates":[
+                {"currency":"dolar amerykański","code":"USD","mid":3.6512},
+                {"currency":"euro","code":"EUR","mid":4.2487},
+                {"currency":"forint (Węgry)","code":"HUF","mid":0.010912}
+            ]}]"#,
+        )
+        .unwrap();
+
+        let usd = rates.rate(Currency::Usd, Currency::Pln, date("2026-10-17")).unwrap();
+        assert_eq!(usd.rate, 3.6512);
+        assert_eq!(rates.latest().count(), 2);
+    }
+
+    #[test]
+    fn test_bundled_rates_load() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("profiles").join(EXCHANGE_RATES_FILE);
+        let rates = ExchangeRates::load(&path).unwrap();
+        assert!(rates.latest().any(|(c, _)| c == Currency::Usd));
+    }
+}
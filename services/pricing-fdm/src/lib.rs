@@ -1,10 +1,29 @@
 pub mod app;
+pub mod artifacts;
+pub mod calibration;
 pub mod config;
+pub mod fx;
+pub mod machine;
+pub mod materials;
+pub mod mesh;
+pub mod metrics;
+pub mod money;
+pub mod quote_token;
+pub mod rules;
 pub mod slicer;
+pub mod upload;
 pub mod utils;
 
 // Re-export AppState for use in handlers
 #[derive(Clone)]
 pub struct AppState {
     pub config: config::Config,
+    pub rules: rules::RulesStore,
+    pub slicer: std::sync::Arc<dyn slicer::Slicer>,
+    pub slice_pool: slicer::SlicePool,
+    pub slice_cache: slicer::SliceCache,
+    pub jobs: app::jobs::JobStore,
+    /// `None` when `ARTIFACT_STORE=none`
+    pub artifacts: Option<artifacts::Artifacts>,
+    pub calibration: calibration::CalibrationStore,
 }
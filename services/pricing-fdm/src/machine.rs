@@ -0,0 +1,329 @@
+use crate::materials::{Material, MaterialCatalog};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Registry file name inside `orca_profiles_dir`
+pub const REGISTRY_FILE: &str = "machines.json";
+
+/// Layer heights a nozzle prints well, as a fraction of its diameter
+const MIN_LAYER_FRACTION: f64 = 0.25;
+const MAX_LAYER_FRACTION: f64 = 0.75;
+
+/// Build volume and nozzle of a printer, read from its Orca machine profile
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MachineProfile {
+    pub name: String,
+    pub bed_x_mm: f64,
+    pub bed_y_mm: f64,
+    pub max_height_mm: f64,
+    pub nozzle_diameter_mm: f64,
+}
+
+/// Subset of the Orca machine profile we read
+#[derive(Deserialize)]
+struct OrcaMachineProfile {
+    name: String,
+    /// Bed outline as "XxY" points, e.g. ["0x0", "200x0", "200x200", "0x200"]
+    printable_area: Vec<String>,
+    printable_height: String,
+    /// One entry per extruder
+    nozzle_diameter: Vec<String>,
+}
+
+impl MachineProfile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read machine profile: {:?}", path))?;
+        Self::from_orca_json(&content).with_context(|| format!("Invalid machine profile: {:?}", path))
+    }
+
+    fn from_orca_json(content: &str) -> Result<Self> {
+        let profile: OrcaMachineProfile = serde_json::from_str(content)?;
+
+        let mut min = [f64::INFINITY; 2];
+        let mut max = [f64::NEG_INFINITY; 2];
+        for point in &profile.printable_area {
+            let (x, y) = point
+                .split_once('x')
+                .with_context(|| format!("Invalid printable_area point: {}", point))?;
+            for (axis, value) in [x, y].iter().enumerate() {
+                let value: f64 = value
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("Invalid printable_area point: {}", point))?;
+                min[axis] = min[axis].min(value);
+                max[axis] = max[axis].max(value);
+            }
+        }
+
+        let machine = MachineProfile {
+            name: profile.name,
+            bed_x_mm: max[0] - min[0],
+            bed_y_mm: max[1] - min[1],
+            max_height_mm: profile
+                .printable_height
+                .parse()
+                .context("Invalid printable_height")?,
+            nozzle_diameter_mm: profile
+                .nozzle_diameter
+                .first()
+                .context("Missing nozzle_diameter")?
+                .parse()
+                .context("Invalid nozzle_diameter")?,
+        };
+
+        if !(machine.bed_x_mm > 0.0 && machine.bed_y_mm > 0.0 && machine.max_height_mm > 0.0) {
+            bail!("Machine build volume must be positive");
+        }
+        if !(machine.nozzle_diameter_mm.is_finite() && machine.nozzle_diameter_mm > 0.0) {
+            bail!("Nozzle diameter must be positive");
+        }
+
+        Ok(machine)
+    }
+
+    pub fn build_volume_mm(&self) -> [f64; 3] {
+        [self.bed_x_mm, self.bed_y_mm, self.max_height_mm]
+    }
+
+    /// Whether a part with this bounding box fits, allowing a 90° turn on the bed
+    pub fn fits(&self, size_mm: [f64; 3]) -> bool {
+        size_mm[2] <= self.max_height_mm && self.copies_per_plate([size_mm[0], size_mm[1]], 0.0) > 0
+    }
+
+    /// How many copies with the given XY footprint fit on the bed in a grid.
+    ///
+    /// Tries the footprint as-is and rotated 90°, keeping `spacing_mm` between copies.
+    pub fn copies_per_plate(&self, footprint_mm: [f64; 2], spacing_mm: f64) -> u32 {
+        let fit = |length: f64, bed: f64| -> u32 {
+            if length <= 0.0 || length > bed {
+                return 0;
+            }
+            ((bed + spacing_mm) / (length + spacing_mm)).floor() as u32
+        };
+
+        let [x, y] = footprint_mm;
+        let as_is = fit(x, self.bed_x_mm) * fit(y, self.bed_y_mm);
+        let rotated = fit(y, self.bed_x_mm) * fit(x, self.bed_y_mm);
+        as_is.max(rotated)
+    }
+}
+
+/// Registry entry as written in `machines.json`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MachineEntry {
+    id: String,
+    display_name: String,
+    /// Orca machine profile, relative to `orca_profiles_dir`
+    machine_profile: String,
+    /// Material ids this printer is set up for (enclosure, hardened nozzle, ...)
+    materials: Vec<String>,
+    enabled: bool,
+}
+
+/// A printer in the fleet
+#[derive(Debug, Clone)]
+pub struct Machine {
+    pub id: String,
+    pub display_name: String,
+    /// Absolute path of the Orca machine profile
+    pub profile_path: PathBuf,
+    pub materials: Vec<String>,
+    pub profile: MachineProfile,
+}
+
+impl Machine {
+    /// Set up for the material, and the nozzle can print the layer height
+    pub fn supports(&self, material: &Material, layer_thickness_um: u16) -> bool {
+        let layer_mm = layer_thickness_um as f64 / 1000.0;
+        let nozzle = self.profile.nozzle_diameter_mm;
+        self.materials.iter().any(|m| m.eq_ignore_ascii_case(&material.id))
+            && layer_mm >= nozzle * MIN_LAYER_FRACTION - 1e-9
+            && layer_mm <= nozzle * MAX_LAYER_FRACTION + 1e-9
+    }
+}
+
+/// Enabled printers loaded from `machines.json`, in file order
+#[derive(Debug, Clone)]
+pub struct MachineRegistry {
+    machines: Vec<Machine>,
+}
+
+impl MachineRegistry {
+    /// Load the registry and every referenced Orca profile from `orca_profiles_dir`.
+    ///
+    /// Material ids must exist in the catalog.
+    pub fn load(profiles_dir: &str, catalog: &MaterialCatalog) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct RegistryFile {
+            machines: Vec<MachineEntry>,
+        }
+
+        let path = Path::new(profiles_dir).join(REGISTRY_FILE);
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read machine registry: {:?}", path))?;
+        let file: RegistryFile = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid machine registry: {:?}", path))?;
+
+        let mut seen = HashSet::new();
+        let mut machines = Vec::new();
+        for entry in file.machines {
+            if entry.id.is_empty() {
+                bail!("Machine id cannot be empty");
+            }
+            if !seen.insert(entry.id.to_lowercase()) {
+                bail!("Duplicate machine id: {}", entry.id);
+            }
+            for material in &entry.materials {
+                if catalog.get(material).is_none() {
+                    bail!("Machine '{}' lists unknown material: {}", entry.id, material);
+                }
+            }
+            if !entry.enabled {
+                continue;
+            }
+
+            let profile_path = Path::new(profiles_dir).join(&entry.machine_profile);
+            machines.push(Machine {
+                profile: MachineProfile::load(&profile_path)?,
+                id: entry.id,
+                display_name: entry.display_name,
+                profile_path,
+                materials: entry.materials,
+            });
+        }
+
+        if machines.is_empty() {
+            bail!("Machine registry has no enabled machines");
+        }
+
+        Ok(MachineRegistry { machines })
+    }
+
+    pub fn all(&self) -> &[Machine] {
+        &self.machines
+    }
+
+    /// Look up a machine by id (case-insensitive)
+    pub fn get(&self, id: &str) -> Option<&Machine> {
+        self.machines.iter().find(|m| m.id.eq_ignore_ascii_case(id))
+    }
+
+    /// First machine in the registry, used where any printer will do (STEP import)
+    pub fn default_machine(&self) -> &Machine {
+        &self.machines[0]
+    }
+
+    /// Whether any machine can print the material at this layer height
+    pub fn supports(&self, material: &Material, layer_thickness_um: u16) -> bool {
+        self.machines.iter().any(|m| m.supports(material, layer_thickness_um))
+    }
+
+    /// Cheapest capable machine the part fits in; ties go to the earlier entry
+    pub fn select(
+        &self,
+        size_mm: [f64; 3],
+        material: &Material,
+        layer_thickness_um: u16,
+        hourly_rate: impl Fn(&Machine) -> f64,
+    ) -> Result<&Machine, TooLarge> {
+        let capable: Vec<&Machine> = self
+            .machines
+            .iter()
+            .filter(|m| m.supports(material, layer_thickness_um))
+            .collect();
+
+        capable
+            .iter()
+            .filter(|m| m.profile.fits(size_mm))
+            .min_by(|a, b| hourly_rate(a).total_cmp(&hourly_rate(b)))
+            .copied()
+            .ok_or_else(|| TooLarge {
+                dimensions_mm: size_mm,
+                material: material.id.clone(),
+                largest_mm: capable
+                    .iter()
+                    .map(|m| m.profile.build_volume_mm())
+                    .max_by(|a, b| (a[0] * a[1] * a[2]).total_cmp(&(b[0] * b[1] * b[2]))),
+            })
+    }
+}
+
+/// Part fits no machine that can print it
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "part measures {} mm and fits no printer for {material}{}",
+    format_mm(dimensions_mm),
+    largest_mm.map(|l| format!(" (largest build volume {} mm)", format_mm(&l))).unwrap_or_default()
+)]
+pub struct TooLarge {
+    pub dimensions_mm: [f64; 3],
+    pub material: String,
+    pub largest_mm: Option<[f64; 3]>,
+}
+
+fn format_mm(size: &[f64; 3]) -> String {
+    format!("{:.1} x {:.1} x {:.1}", size[0], size[1], size[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/profiles");
+
+    fn registry() -> (MachineRegistry, MaterialCatalog) {
+        let catalog = MaterialCatalog::load(PROFILES_DIR).unwrap();
+        (MachineRegistry::load(PROFILES_DIR, &catalog).unwrap(), catalog)
+    }
+
+    #[test]
+    fn test_bundled_profile_build_volume() {
+        let (registry, _) = registry();
+        let m = &registry.get("generic-200").unwrap().profile;
+        assert_eq!(m.build_volume_mm(), [200.0, 200.0, 200.0]);
+        assert_eq!(m.nozzle_diameter_mm, 0.4);
+    }
+
+    #[test]
+    fn test_copies_per_plate() {
+        let (registry, _) = registry();
+        let m = &registry.get("generic-200").unwrap().profile;
+
+        // (200 + 5) / (20 + 5) = 8.2 -> 8 per row
+        assert_eq!(m.copies_per_plate([20.0, 20.0], 5.0), 64);
+        // 150 wide only fits once, 30 deep fits 5 times (either orientation)
+        assert_eq!(m.copies_per_plate([150.0, 30.0], 5.0), 5);
+        assert_eq!(m.copies_per_plate([30.0, 150.0], 5.0), 5);
+        assert_eq!(m.copies_per_plate([250.0, 10.0], 5.0), 0);
+    }
+
+    #[test]
+    fn test_selects_cheapest_capable_machine() {
+        let (registry, catalog) = registry();
+        let pla = catalog.get("pla").unwrap();
+        let pa_cf = catalog.get("pa-cf").unwrap();
+        let rate = |m: &Machine| match m.id.as_str() {
+            "generic-200" => 10.0,
+            "enclosed-256" => 12.0,
+            _ => 16.0,
+        };
+
+        let small = [50.0, 50.0, 50.0];
+        assert_eq!(registry.select(small, pla, 200, rate).unwrap().id, "generic-200");
+        // Too tall for the 200 mm printer, lying flat is not tried here
+        assert_eq!(registry.select([50.0, 50.0, 240.0], pla, 200, rate).unwrap().id, "enclosed-256");
+        assert_eq!(registry.select([300.0, 50.0, 50.0], pla, 200, rate).unwrap().id, "large-350");
+
+        // 0.1 mm layers are too thin for the 0.6 mm nozzle of the large printer
+        let err = registry.select([300.0, 50.0, 50.0], pla, 100, rate).unwrap_err();
+        assert_eq!(err.largest_mm, Some([256.0, 256.0, 256.0]));
+        assert!(err.to_string().contains("300.0 x 50.0 x 50.0"));
+
+        assert!(!registry.get("generic-200").unwrap().supports(pa_cf, 200));
+        assert!(registry.supports(pa_cf, 200));
+    }
+}
@@ -1,8 +1,9 @@
 use pricing_fdm::*;
 
 use anyhow::Result;
-use axum::{routing::{get, post}, Router};
+use axum::{extract::State, routing::get, Router};
 use std::net::SocketAddr;
+use std::time::Duration;
 use tower_http::trace::TraceLayer;
 use tracing::info;
 
@@ -20,17 +21,60 @@ async fn main() -> Result<()> {
     // Load configuration
     let config = config::Config::from_env()?;
     info!("Loaded configuration: {:?}", config.masked());
+    for (currency, date) in config.exchange_rates.latest() {
+        info!("Latest {} exchange rate effective {}", currency, date);
+    }
+    for machine in config.machines.all() {
+        let [x, y, z] = machine.profile.build_volume_mm();
+        info!("Machine {}: {}x{}x{} mm, {} materials", machine.id, x, y, z, machine.materials.len());
+    }
+
+    // Load pricing rules; an invalid file stops startup
+    let rules = rules::RulesStore::load(&config.pricing_rules_path, &config)?;
+    rules.spawn_watcher(
+        config.clone(),
+        Duration::from_secs(config.pricing_rules_reload_secs),
+    );
+
+    // Sliced artifact storage; pending artifacts are swept hourly
+    let artifacts = artifacts::from_config(&config).await?;
+    if let Some(artifacts) = &artifacts {
+        info!("Storing sliced artifacts in {} storage", artifacts.store_name());
+        artifacts.spawn_sweeper(Duration::from_secs(3600));
+    }
+
+    // Correction factors from production reports
+    let calibration = calibration::CalibrationStore::load(&config.calibration_path, &config)?;
 
     // Create app state
+    let slicer = slicer::from_config(&config)?;
+    info!("Using {} slicer backend", slicer.name());
+
     let app_state = AppState {
         config: config.clone(),
+        rules,
+        slicer,
+        slice_pool: slicer::SlicePool::new(
+            config.max_concurrent_slices,
+            config.max_queued_slices,
+        ),
+        slice_cache: slicer::SliceCache::new(
+            &config.slice_cache_dir,
+            config.slice_cache_max_mb * 1024 * 1024,
+        ),
+        jobs: app::jobs::JobStore::new(
+            Duration::from_secs(config.quote_job_ttl_secs),
+            config.max_unfinished_jobs,
+        ),
+        artifacts,
+        calibration,
     };
 
     // Build router
     let app = Router::new()
         .route("/health", get(health))
         .route("/metrics", get(metrics))
-        .route("/internal/pricing/fdm/quote", post(app::handlers::quote))
+        .merge(app::router())
         .layer(TraceLayer::new_for_http())
         .with_state(app_state);
 
@@ -48,7 +92,17 @@ async fn health() -> &'static str {
     "OK"
 }
 
-async fn metrics() -> &'static str {
-    // TODO: Implement Prometheus metrics
-    "# Placeholder for metrics\n"
+/// Prometheus metrics endpoint
+async fn metrics(State(state): State<AppState>) -> String {
+    use prometheus::Encoder;
+
+    let stats = state.slice_pool.stats();
+    metrics::SLICES_ACTIVE.set(stats.active as i64);
+    metrics::SLICES_QUEUED.set(stats.queued as i64);
+
+    let encoder = prometheus::TextEncoder::new();
+    let metric_families = prometheus::gather();
+    let mut buffer = vec![];
+    encoder.encode(&metric_families, &mut buffer).unwrap();
+    String::from_utf8(buffer).unwrap()
 }
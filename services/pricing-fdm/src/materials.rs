@@ -0,0 +1,197 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Catalog file name inside `orca_profiles_dir`
+pub const CATALOG_FILE: &str = "materials.json";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Material {
+    pub id: String,
+    pub display_name: String,
+    pub density_g_cm3: f64,
+    pub filament_diameter_mm: f64,
+    pub cost_per_g_usd: f64,
+    /// Orca filament profile, relative to `orca_profiles_dir`
+    pub filament_profile: String,
+    /// Allowed layer heights in micrometers
+    pub layer_heights_um: Vec<u16>,
+    pub min_infill: u8,
+    pub max_infill: u8,
+    /// Stocked filament colors, for display; quotes do not depend on the color
+    #[serde(default)]
+    pub colors: Vec<String>,
+    pub enabled: bool,
+}
+
+impl Material {
+    pub fn supports_layer_height(&self, layer_thickness_um: u16) -> bool {
+        self.layer_heights_um.contains(&layer_thickness_um)
+    }
+
+    pub fn supports_infill(&self, infill: u8) -> bool {
+        (self.min_infill..=self.max_infill).contains(&infill)
+    }
+
+    /// Cross-section of the filament strand in cm²
+    pub fn filament_area_cm2(&self) -> f64 {
+        let radius_cm = self.filament_diameter_mm / 20.0;
+        std::f64::consts::PI * radius_cm * radius_cm
+    }
+}
+
+/// Data-driven list of materials loaded from `materials.json`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MaterialCatalog {
+    materials: Vec<Material>,
+}
+
+impl MaterialCatalog {
+    /// Load and validate the catalog from `orca_profiles_dir`.
+    ///
+    /// Every enabled material must point at an existing filament profile.
+    pub fn load(profiles_dir: &str) -> Result<Self> {
+        let path = Path::new(profiles_dir).join(CATALOG_FILE);
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read material catalog: {:?}", path))?;
+        let catalog: MaterialCatalog = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid material catalog: {:?}", path))?;
+
+        catalog.validate()?;
+
+        for material in catalog.enabled() {
+            catalog.filament_profile_path(material, profiles_dir)?;
+        }
+
+        Ok(catalog)
+    }
+
+    pub fn from_materials(materials: Vec<Material>) -> Result<Self> {
+        let catalog = MaterialCatalog { materials };
+        catalog.validate()?;
+        Ok(catalog)
+    }
+
+    /// Look up a material by id (case-insensitive), including disabled ones
+    pub fn get(&self, id: &str) -> Option<&Material> {
+        self.materials
+            .iter()
+            .find(|m| m.id.eq_ignore_ascii_case(id))
+    }
+
+    pub fn enabled(&self) -> impl Iterator<Item = &Material> {
+        self.materials.iter().filter(|m| m.enabled)
+    }
+
+    /// Resolve the Orca filament profile for a material, failing if it is missing
+    pub fn filament_profile_path(&self, material: &Material, profiles_dir: &str) -> Result<PathBuf> {
+        let path = Path::new(profiles_dir).join(&material.filament_profile);
+        if !path.is_file() {
+            bail!(
+                "Filament profile for material '{}' not found: {:?}",
+                material.id,
+                path
+            );
+        }
+        Ok(path)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.materials.is_empty() {
+            bail!("Material catalog is empty");
+        }
+
+        let mut seen = HashSet::new();
+        for m in &self.materials {
+            if m.id.is_empty() {
+                bail!("Material id cannot be empty");
+            }
+            if !seen.insert(m.id.to_lowercase()) {
+                bail!("Duplicate material id: {}", m.id);
+            }
+            if m.density_g_cm3 <= 0.0 || m.filament_diameter_mm <= 0.0 {
+                bail!("Material '{}' must have positive density and diameter", m.id);
+            }
+            if m.cost_per_g_usd < 0.0 {
+                bail!("Material '{}' has negative cost per gram", m.id);
+            }
+            if m.layer_heights_um.is_empty() {
+                bail!("Material '{}' must allow at least one layer height", m.id);
+            }
+            if m.min_infill > m.max_infill || m.max_infill > 100 {
+                bail!(
+                    "Material '{}' has invalid infill range {}-{}",
+                    m.id,
+                    m.min_infill,
+                    m.max_infill
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn material(id: &str) -> Material {
+        Material {
+            id: id.to_string(),
+            display_name: id.to_uppercase(),
+            density_g_cm3: 1.24,
+            filament_diameter_mm: 1.75,
+            cost_per_g_usd: 0.02,
+            filament_profile: format!("filament_{}.json", id),
+            layer_heights_um: vec![100, 200, 300],
+            min_infill: 10,
+            max_infill: 100,
+            colors: vec!["black".to_string()],
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_lookup_is_case_insensitive() {
+        let catalog = MaterialCatalog::from_materials(vec![material("pla")]).unwrap();
+        assert!(catalog.get("PLA").is_some());
+        assert!(catalog.get("abs").is_none());
+    }
+
+    #[test]
+    fn test_rejects_duplicate_ids() {
+        let result = MaterialCatalog::from_materials(vec![material("pla"), material("PLA")]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_infill_range() {
+        let mut m = material("tpu");
+        m.min_infill = 50;
+        m.max_infill = 20;
+        assert!(MaterialCatalog::from_materials(vec![m]).is_err());
+    }
+
+    #[test]
+    fn test_missing_filament_profile_is_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = MaterialCatalog::from_materials(vec![material("pa-cf")]).unwrap();
+        let m = catalog.get("pa-cf").unwrap();
+
+        let profiles_dir = dir.path().to_str().unwrap();
+        assert!(catalog.filament_profile_path(m, profiles_dir).is_err());
+
+        std::fs::write(dir.path().join("filament_pa-cf.json"), "{}").unwrap();
+        assert!(catalog.filament_profile_path(m, profiles_dir).is_ok());
+    }
+
+    #[test]
+    fn test_bundled_catalog_loads() {
+        let profiles_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/profiles");
+        let catalog = MaterialCatalog::load(profiles_dir).unwrap();
+        assert!(catalog.get("pla").is_some_and(|m| m.enabled));
+        assert!(catalog.enabled().all(|m| !m.colors.is_empty()));
+    }
+}
@@ -0,0 +1,503 @@
+//! Design-for-manufacturing checks on the uploaded mesh.
+//!
+//! Locations are bounding boxes in the model's own coordinates (as uploaded),
+//! so they can be shown on the customer's part before any rotation.
+
+use super::orient::{self, Rotation};
+use super::{cross, dot, length, sub, BoundingBox, Mesh};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Walls thinner than this many nozzle widths get a warning (two perimeters)
+const THIN_WALL_NOZZLES: f64 = 2.0;
+/// Thin regions smaller than this are ignored (sharp edges, chamfers)
+const MIN_THIN_AREA_MM2: f64 = 1.0;
+/// Triangles ray-cast for wall thickness; larger meshes are sampled evenly
+const MAX_THICKNESS_SAMPLES: usize = 20_000;
+/// Spatial grid resolution cap per axis
+const MAX_GRID_CELLS: f64 = 64.0;
+/// Shells smaller than this many nozzle widths in every direction
+const SMALL_FEATURE_NOZZLES: f64 = 5.0;
+/// Overhangs smaller than this are bridged or ignored by the slicer
+const MIN_OVERHANG_AREA_MM2: f64 = 25.0;
+/// Support above this share of the filament weight is worth redesigning for
+const HIGH_SUPPORT_PERCENT: f64 = 30.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    /// Likely to fail or not match the model
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DfmCheck {
+    ThinWall,
+    SmallFeature,
+    Overhang,
+    OpenMesh,
+    NonManifold,
+    InvertedNormals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DfmWarning {
+    pub check: DfmCheck,
+    pub severity: Severity,
+    pub message: String,
+    /// Affected region in model coordinates
+    pub location: Option<BoundingBox>,
+}
+
+/// Inputs the checks compare the mesh against
+pub struct DfmLimits {
+    pub nozzle_diameter_mm: f64,
+    /// Orientation the part is printed in, for overhangs
+    pub rotation: Rotation,
+    /// Support share of filament weight from the slice
+    pub support_percent: f64,
+}
+
+/// Run every check, most severe first
+pub fn check(mesh: &Mesh, limits: &DfmLimits) -> Vec<DfmWarning> {
+    let shells = shells(mesh);
+    let mut warnings = Vec::new();
+    warnings.extend(check_topology(mesh, &shells));
+    warnings.extend(check_thin_walls(mesh, limits.nozzle_diameter_mm));
+    warnings.extend(check_small_features(&shells, limits.nozzle_diameter_mm));
+    warnings.extend(check_overhangs(mesh, limits));
+    warnings.sort_by_key(|w| std::cmp::Reverse(w.severity));
+    warnings
+}
+
+/// Grows a bounding box around the points of a problem region
+#[derive(Default)]
+struct Region {
+    bounds: Option<BoundingBox>,
+}
+
+impl Region {
+    fn add(&mut self, point: [f64; 3]) {
+        let bounds = self.bounds.get_or_insert(BoundingBox {
+            min: point,
+            max: point,
+        });
+        for (axis, value) in point.into_iter().enumerate() {
+            bounds.min[axis] = bounds.min[axis].min(value);
+            bounds.max[axis] = bounds.max[axis].max(value);
+        }
+    }
+}
+
+/// Connected component of the mesh, with what the checks need to know about it
+struct Shell {
+    triangles: Vec<usize>,
+    /// Signed volume in mm³, negative when the shell is inside out
+    volume: f64,
+    bounds: BoundingBox,
+}
+
+/// Split the mesh into shells once, for all checks
+fn shells(mesh: &Mesh) -> Vec<Shell> {
+    mesh.shells()
+        .into_iter()
+        .filter_map(|triangles| {
+            let mut region = Region::default();
+            let mut volume = 0.0;
+            for &index in &triangles {
+                let [a, b, c] = mesh.corners(&mesh.triangles[index]);
+                volume += dot(a, cross(b, c)) / 6.0;
+                [a, b, c].into_iter().for_each(|v| region.add(v));
+            }
+            Some(Shell {
+                triangles,
+                volume,
+                bounds: region.bounds?,
+            })
+        })
+        .collect()
+}
+
+/// Holes, non-manifold edges and inside-out shells
+fn check_topology(mesh: &Mesh, shells: &[Shell]) -> Vec<DfmWarning> {
+    // Undirected edge -> (triangles using it, of which traverse it low -> high)
+    let mut edges: HashMap<(u32, u32), (u32, u32)> = HashMap::with_capacity(mesh.triangles.len() * 3 / 2);
+    for tri in &mesh.triangles {
+        for i in 0..3 {
+            let (a, b) = (tri[i], tri[(i + 1) % 3]);
+            let entry = edges.entry((a.min(b), a.max(b))).or_insert((0, 0));
+            entry.0 += 1;
+            entry.1 += (a < b) as u32;
+        }
+    }
+
+    let (mut open, mut non_manifold, mut flipped) = (Region::default(), Region::default(), Region::default());
+    let (mut open_count, mut non_manifold_count, mut flipped_count) = (0, 0, 0);
+    for (&(a, b), &(uses, forward)) in &edges {
+        let (region, count) = match uses {
+            1 => (&mut open, &mut open_count),
+            2 if forward != 1 => (&mut flipped, &mut flipped_count),
+            2 => continue,
+            _ => (&mut non_manifold, &mut non_manifold_count),
+        };
+        region.add(mesh.vertices[a as usize]);
+        region.add(mesh.vertices[b as usize]);
+        *count += 1;
+    }
+
+    let mut warnings = Vec::new();
+    if open_count > 0 {
+        warnings.push(DfmWarning {
+            check: DfmCheck::OpenMesh,
+            severity: Severity::Warning,
+            message: format!(
+                "Surface has holes ({} open edges); the slicer will try to close them",
+                open_count
+            ),
+            location: open.bounds,
+        });
+    }
+    if non_manifold_count > 0 {
+        warnings.push(DfmWarning {
+            check: DfmCheck::NonManifold,
+            severity: Severity::Warning,
+            message: format!(
+                "{} edges are shared by more than two faces; overlapping bodies may print merged",
+                non_manifold_count
+            ),
+            location: non_manifold.bounds,
+        });
+    }
+
+    // Neighbouring faces wound in opposite directions, or whole shells turned inside out
+    for shell in inside_out_shells(shells) {
+        flipped_count += shell.triangles.len();
+        flipped.add(shell.bounds.min);
+        flipped.add(shell.bounds.max);
+    }
+    if flipped_count > 0 {
+        warnings.push(DfmWarning {
+            check: DfmCheck::InvertedNormals,
+            severity: Severity::Warning,
+            message: "Some faces point inwards; the slicer may treat these regions as empty".to_string(),
+            location: flipped.bounds,
+        });
+    }
+
+    warnings
+}
+
+/// Shells with negative volume that are not cavities inside another shell
+fn inside_out_shells(shells: &[Shell]) -> impl Iterator<Item = &Shell> {
+    let contains = |outer: &BoundingBox, inner: &BoundingBox| {
+        (0..3).all(|i| outer.min[i] <= inner.min[i] && inner.max[i] <= outer.max[i])
+    };
+    shells.iter().filter(move |shell| {
+        shell.volume < 0.0
+            && !shells
+                .iter()
+                .any(|outer| outer.volume > 0.0 && contains(&outer.bounds, &shell.bounds))
+    })
+}
+
+/// Wall thickness measured by casting a ray inwards from each face
+fn check_thin_walls(mesh: &Mesh, nozzle_mm: f64) -> Vec<DfmWarning> {
+    let limit = nozzle_mm * THIN_WALL_NOZZLES;
+    let grid = Grid::build(mesh, limit);
+    let stride = mesh.triangles.len().div_ceil(MAX_THICKNESS_SAMPLES).max(1);
+
+    let mut region = Region::default();
+    let mut thin_area = 0.0;
+    let mut thinnest = f64::INFINITY;
+    for (index, tri) in mesh.triangles.iter().enumerate().step_by(stride) {
+        let [a, b, c] = mesh.corners(tri);
+        let normal = cross(sub(b, a), sub(c, a));
+        let double_area = length(normal);
+        if double_area == 0.0 {
+            continue;
+        }
+        let inward = normal.map(|n| -n / double_area);
+        let centroid = [0, 1, 2].map(|i| (a[i] + b[i] + c[i]) / 3.0);
+
+        if let Some(thickness) = grid.nearest_hit(mesh, centroid, inward, limit, index) {
+            thin_area += double_area / 2.0 * stride as f64;
+            thinnest = thinnest.min(thickness);
+            [a, b, c].into_iter().for_each(|v| region.add(v));
+        }
+    }
+
+    if thin_area < MIN_THIN_AREA_MM2 {
+        return Vec::new();
+    }
+    let severity = if thinnest < nozzle_mm {
+        Severity::Error
+    } else {
+        Severity::Warning
+    };
+    vec![DfmWarning {
+        check: DfmCheck::ThinWall,
+        severity,
+        message: format!(
+            "Walls down to {:.2} mm thick over {:.1} mm²; the {:.1} mm nozzle needs at least {:.2} mm",
+            thinnest, thin_area, nozzle_mm, limit
+        ),
+        location: region.bounds,
+    }]
+}
+
+/// Separate bodies too small to print reliably, as one warning however many there are
+fn check_small_features(shells: &[Shell], nozzle_mm: f64) -> Vec<DfmWarning> {
+    let limit = nozzle_mm * SMALL_FEATURE_NOZZLES;
+    let mut region = Region::default();
+    let mut count = 0;
+    let mut smallest = f64::INFINITY;
+    for shell in shells {
+        let largest = shell.bounds.size().into_iter().fold(0.0, f64::max);
+        if largest < limit {
+            count += 1;
+            smallest = smallest.min(largest);
+            region.add(shell.bounds.min);
+            region.add(shell.bounds.max);
+        }
+    }
+
+    let message = match count {
+        0 => return Vec::new(),
+        1 => format!(
+            "Feature of {:.2} mm is smaller than {:.1} mm and may not print or detach",
+            smallest, limit
+        ),
+        _ => format!(
+            "{} features down to {:.2} mm are smaller than {:.1} mm and may not print or detach",
+            count, smallest, limit
+        ),
+    };
+    vec![DfmWarning {
+        check: DfmCheck::SmallFeature,
+        severity: Severity::Warning,
+        message,
+        location: region.bounds,
+    }]
+}
+
+/// Downward-facing area in the printed orientation that needs support, as scored when the
+/// orientation was chosen
+fn check_overhangs(mesh: &Mesh, limits: &DfmLimits) -> Vec<DfmWarning> {
+    let (score, overhangs) = orient::score(mesh, &limits.rotation);
+    let area = score.overhang_area_cm2 * 100.0;
+
+    let mut region = Region::default();
+    for &index in &overhangs {
+        mesh.corners(&mesh.triangles[index]).into_iter().for_each(|v| region.add(v));
+    }
+
+    if area < MIN_OVERHANG_AREA_MM2 {
+        return Vec::new();
+    }
+    let (severity, extra) = if limits.support_percent > HIGH_SUPPORT_PERCENT {
+        (
+            Severity::Warning,
+            format!(", and support is {:.0}% of the filament", limits.support_percent),
+        )
+    } else {
+        (Severity::Info, String::new())
+    };
+    vec![DfmWarning {
+        check: DfmCheck::Overhang,
+        severity,
+        message: format!(
+            "{:.1} cm² of overhangs need support in the quoted orientation{}; supported surfaces are left rough",
+            area / 100.0,
+            extra
+        ),
+        location: region.bounds,
+    }]
+}
+
+/// Uniform grid of triangle indices for short ray casts
+struct Grid {
+    origin: [f64; 3],
+    cell_mm: f64,
+    cells: HashMap<[i64; 3], Vec<u32>>,
+}
+
+impl Grid {
+    /// Cells are at least `reach_mm` wide, so a ray of that length crosses at most two per axis
+    fn build(mesh: &Mesh, reach_mm: f64) -> Grid {
+        let bounds = mesh.bounding_box();
+        let largest = bounds.size().into_iter().fold(0.0, f64::max);
+        let mut grid = Grid {
+            origin: bounds.min,
+            cell_mm: reach_mm.max(largest / MAX_GRID_CELLS).max(1e-6),
+            cells: HashMap::new(),
+        };
+
+        for (index, tri) in mesh.triangles.iter().enumerate() {
+            let corners = mesh.corners(tri);
+            let lo = grid.cell(corners.iter().fold(corners[0], |m, v| [0, 1, 2].map(|i| m[i].min(v[i]))));
+            let hi = grid.cell(corners.iter().fold(corners[0], |m, v| [0, 1, 2].map(|i| m[i].max(v[i]))));
+            for x in lo[0]..=hi[0] {
+                for y in lo[1]..=hi[1] {
+                    for z in lo[2]..=hi[2] {
+                        grid.cells.entry([x, y, z]).or_default().push(index as u32);
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    fn cell(&self, p: [f64; 3]) -> [i64; 3] {
+        [0, 1, 2].map(|i| ((p[i] - self.origin[i]) / self.cell_mm).floor() as i64)
+    }
+
+    /// Distance to the nearest face within `reach_mm` along `dir`, skipping face `skip`
+    fn nearest_hit(&self, mesh: &Mesh, from: [f64; 3], dir: [f64; 3], reach_mm: f64, skip: usize) -> Option<f64> {
+        let to = [0, 1, 2].map(|i| from[i] + dir[i] * reach_mm);
+        let (lo, hi) = (self.cell(from), self.cell(to));
+        let mut nearest: Option<f64> = None;
+        for x in lo[0].min(hi[0])..=lo[0].max(hi[0]) {
+            for y in lo[1].min(hi[1])..=lo[1].max(hi[1]) {
+                for z in lo[2].min(hi[2])..=lo[2].max(hi[2]) {
+                    for &index in self.cells.get(&[x, y, z]).into_iter().flatten() {
+                        if index as usize == skip {
+                            continue;
+                        }
+                        let corners = mesh.corners(&mesh.triangles[index as usize]);
+                        if let Some(t) = ray_triangle(from, dir, corners) {
+                            if t <= reach_mm && nearest.is_none_or(|n| t < n) {
+                                nearest = Some(t);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        nearest
+    }
+}
+
+/// Möller–Trumbore intersection; distance along `dir` when the ray hits the triangle
+fn ray_triangle(origin: [f64; 3], dir: [f64; 3], [a, b, c]: [[f64; 3]; 3]) -> Option<f64> {
+    const EPSILON: f64 = 1e-9;
+    let ab = sub(b, a);
+    let ac = sub(c, a);
+    let p = cross(dir, ac);
+    let det = dot(ab, p);
+    if det.abs() < EPSILON {
+        return None;
+    }
+    let inv = 1.0 / det;
+    let s = sub(origin, a);
+    let u = dot(s, p) * inv;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = cross(s, ab);
+    let v = dot(dir, q) * inv;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = dot(ac, q) * inv;
+    (t > 1e-6).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::tests::{build, cube, cuboid};
+
+    fn limits() -> DfmLimits {
+        DfmLimits {
+            nozzle_diameter_mm: 0.4,
+            rotation: Rotation::IDENTITY,
+            support_percent: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_clean_cube_has_no_warnings() {
+        assert!(check(&build(cube(20.0, [0.0; 3])), &limits()).is_empty());
+    }
+
+    #[test]
+    fn test_thin_wall_is_located() {
+        // 0.3 mm fin next to a solid block
+        let mut triangles = cuboid([0.0, 0.0, 0.0], [20.0, 20.0, 10.0]);
+        triangles.extend(cuboid([30.0, 0.0, 0.0], [30.3, 20.0, 10.0]));
+        let warnings = check(&build(triangles), &limits());
+
+        let thin = warnings.iter().find(|w| w.check == DfmCheck::ThinWall).unwrap();
+        assert_eq!(thin.severity, Severity::Error);
+        assert!(thin.message.contains("0.30 mm"), "{}", thin.message);
+        let location = thin.location.unwrap();
+        assert!(location.min[0] >= 30.0 && location.max[0] <= 30.3 + 1e-9);
+        // Errors come first
+        assert_eq!(warnings[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_small_feature_and_overhang() {
+        // 1 mm speck beside a bridge-like table top held up by one leg
+        let mut triangles = cuboid([0.0, 0.0, 0.0], [5.0, 5.0, 20.0]);
+        triangles.extend(cuboid([0.0, 0.0, 20.0], [30.0, 30.0, 25.0]));
+        triangles.extend(cube(1.0, [50.0, 50.0, 0.0]));
+        let warnings = check(&build(triangles), &limits());
+
+        let speck = warnings.iter().find(|w| w.check == DfmCheck::SmallFeature).unwrap();
+        assert_eq!(speck.location.unwrap().min, [50.0, 50.0, 0.0]);
+
+        let overhang = warnings.iter().find(|w| w.check == DfmCheck::Overhang).unwrap();
+        assert_eq!(overhang.severity, Severity::Info);
+        assert_eq!(overhang.location.unwrap().min[2], 20.0);
+
+        // Flipped onto the table top, nothing overhangs
+        let flipped = DfmLimits {
+            rotation: Rotation::face_down([0.0, 0.0, 1.0]),
+            ..limits()
+        };
+        let warnings = check(&build(cuboid([0.0, 0.0, 20.0], [30.0, 30.0, 25.0])), &flipped);
+        assert!(warnings.iter().all(|w| w.check != DfmCheck::Overhang));
+    }
+
+    #[test]
+    fn test_small_features_are_reported_once() {
+        let mut triangles = cube(20.0, [0.0; 3]);
+        for i in 0..50 {
+            triangles.extend(cube(1.0, [30.0 + 2.0 * i as f64, 0.0, 0.0]));
+        }
+        let warnings = check(&build(triangles), &limits());
+
+        let specks: Vec<_> = warnings.iter().filter(|w| w.check == DfmCheck::SmallFeature).collect();
+        assert_eq!(specks.len(), 1);
+        assert!(specks[0].message.starts_with("50 features"), "{}", specks[0].message);
+        let location = specks[0].location.unwrap();
+        assert_eq!((location.min[0], location.max[0]), (30.0, 129.0));
+    }
+
+    #[test]
+    fn test_topology_problems() {
+        // Inside-out cube: every face reversed
+        let inverted: Vec<_> = cube(10.0, [0.0; 3]).into_iter().map(|[a, b, c]| [a, c, b]).collect();
+        let warnings = check(&build(inverted), &limits());
+        assert!(warnings.iter().any(|w| w.check == DfmCheck::InvertedNormals));
+
+        // One flipped face and one missing face
+        let mut triangles = cube(10.0, [0.0; 3]);
+        let [a, b, c] = triangles[0];
+        triangles[0] = [a, c, b];
+        triangles.pop();
+        let checks: Vec<DfmCheck> = check(&build(triangles), &limits()).iter().map(|w| w.check).collect();
+        assert!(checks.contains(&DfmCheck::InvertedNormals));
+        assert!(checks.contains(&DfmCheck::OpenMesh));
+
+        // A hollow box's inner wall faces inwards on purpose
+        let mut hollow = cube(20.0, [0.0; 3]);
+        hollow.extend(cube(10.0, [5.0; 3]).into_iter().map(|[a, b, c]| [a, c, b]));
+        let warnings = check(&build(hollow), &limits());
+        assert!(warnings.iter().all(|w| w.check != DfmCheck::InvertedNormals));
+    }
+}
@@ -0,0 +1,194 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+
+/// Bytes read from the start of a file for format detection
+const SNIFF_LEN: usize = 1024;
+
+/// Model file formats accepted for quoting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ModelFormat {
+    #[serde(rename = "stl")]
+    Stl,
+    #[serde(rename = "3mf")]
+    ThreeMf,
+    #[serde(rename = "obj")]
+    Obj,
+    #[serde(rename = "step")]
+    Step,
+}
+
+impl ModelFormat {
+    /// File extension Orca uses to pick its importer
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ModelFormat::Stl => "stl",
+            ModelFormat::ThreeMf => "3mf",
+            ModelFormat::Obj => "obj",
+            ModelFormat::Step => "step",
+        }
+    }
+
+    /// STEP is a B-rep format; it has to be tessellated by Orca before mesh analysis
+    pub fn is_mesh(&self) -> bool {
+        !matches!(self, ModelFormat::Step)
+    }
+
+    /// Detect the format from the leading bytes of a file and its total length
+    pub fn detect(head: &[u8], len: u64) -> Result<ModelFormat> {
+        if head.starts_with(b"PK\x03\x04") {
+            return Ok(ModelFormat::ThreeMf);
+        }
+
+        // Binary STL has no magic, but its size is fully determined by the triangle count.
+        // Check it first because the 80-byte header may itself start with "solid".
+        if head.len() >= 84 {
+            let count = u32::from_le_bytes([head[80], head[81], head[82], head[83]]) as u64;
+            if len == 84 + count * 50 {
+                return Ok(ModelFormat::Stl);
+            }
+        }
+
+        if head.starts_with(b"glTF") {
+            bail!("glTF/GLB models are not supported, please upload STL, 3MF, OBJ or STEP");
+        }
+
+        let text = head
+            .strip_prefix(b"\xEF\xBB\xBF")
+            .unwrap_or(head)
+            .trim_ascii_start();
+
+        if text.starts_with(b"ISO-10303-21") {
+            return Ok(ModelFormat::Step);
+        }
+        if text.starts_with(b"solid") {
+            return Ok(ModelFormat::Stl);
+        }
+        if text.starts_with(b"ply") {
+            bail!("PLY models are not supported, please upload STL, 3MF, OBJ or STEP");
+        }
+        if text.starts_with(b"<") {
+            if contains(text, b"<amf") {
+                bail!("AMF models are not supported, please upload STL, 3MF, OBJ or STEP");
+            }
+            bail!("XML files are not supported, please upload STL, 3MF, OBJ or STEP");
+        }
+        if text.starts_with(b"{") {
+            bail!("JSON files (including glTF) are not supported, please upload STL, 3MF, OBJ or STEP");
+        }
+        if is_obj(text, (head.len() as u64) < len) {
+            return Ok(ModelFormat::Obj);
+        }
+
+        bail!("Unrecognized model format, please upload STL, 3MF, OBJ or STEP");
+    }
+
+    /// Detect the format of a file on disk
+    pub async fn detect_file(path: &Path) -> Result<ModelFormat> {
+        let mut file = tokio::fs::File::open(path)
+            .await
+            .with_context(|| format!("Failed to open model: {:?}", path))?;
+        let len = file.metadata().await?.len();
+
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        (&mut file).take(SNIFF_LEN as u64).read_to_end(&mut head).await?;
+
+        ModelFormat::detect(&head, len)
+    }
+}
+
+/// OBJ has no header; accept text whose first statement is an OBJ keyword.
+///
+/// `truncated` is set when `text` is only the sniffed start of the file; its last line is then
+/// cut short and ignored.
+fn is_obj(text: &[u8], truncated: bool) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "v", "vt", "vn", "vp", "f", "l", "o", "g", "s", "mtllib", "usemtl",
+    ];
+
+    // The sniffed prefix may end inside a multi-byte character
+    let (text, partial) = match std::str::from_utf8(text) {
+        Ok(s) => (s, truncated),
+        Err(e) => (std::str::from_utf8(&text[..e.valid_up_to()]).unwrap(), true),
+    };
+    let text = match text.rfind('\n') {
+        Some(end) if partial => &text[..end],
+        None if partial => "",
+        _ => text,
+    };
+
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .and_then(|line| line.split_whitespace().next())
+        .is_some_and(|keyword| KEYWORDS.contains(&keyword))
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(data: &[u8]) -> Result<ModelFormat> {
+        ModelFormat::detect(data, data.len() as u64)
+    }
+
+    #[test]
+    fn test_detects_supported_formats() {
+        let mut binary_stl = b"solid exported by CAD".to_vec();
+        binary_stl.resize(80, 0);
+        binary_stl.extend_from_slice(&1u32.to_le_bytes());
+        binary_stl.extend_from_slice(&[0u8; 50]);
+
+        assert_eq!(detect(&binary_stl).unwrap(), ModelFormat::Stl);
+        assert_eq!(detect(b"solid cube\nendsolid cube\n").unwrap(), ModelFormat::Stl);
+        assert_eq!(detect(b"PK\x03\x04rest").unwrap(), ModelFormat::ThreeMf);
+        assert_eq!(
+            detect(b"ISO-10303-21;\nHEADER;\n").unwrap(),
+            ModelFormat::Step
+        );
+        assert_eq!(
+            detect(b"# Blender v3.6\nmtllib cube.mtl\no Cube\nv 1 1 1\n").unwrap(),
+            ModelFormat::Obj
+        );
+    }
+
+    #[test]
+    fn test_obj_sniff_ending_inside_a_character() {
+        let mut obj = "# Modèle exporté\nv 0 0 0\n# ".to_string();
+        while obj.len() <= SNIFF_LEN {
+            obj.push('é');
+        }
+        obj.push_str("\nv 1 0 0\nv 0 1 0\nf 1 2 3\n");
+        let head = &obj.as_bytes()[..SNIFF_LEN];
+        assert!(std::str::from_utf8(head).is_err());
+
+        assert_eq!(
+            ModelFormat::detect(head, obj.len() as u64).unwrap(),
+            ModelFormat::Obj
+        );
+
+        // A line cut short by the sniff is not trusted ("vt" seen as "v")
+        let cut = format!("{}\nvt 0 0", "#".repeat(SNIFF_LEN - 3));
+        assert!(ModelFormat::detect(&cut.as_bytes()[..SNIFF_LEN - 1], 4096).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_formats_have_clear_errors() {
+        let ply = detect(b"ply\nformat ascii 1.0\n").unwrap_err();
+        assert!(ply.to_string().starts_with("PLY models are not supported"));
+
+        let amf = detect(b"<?xml version=\"1.0\"?>\n<amf unit=\"millimeter\">").unwrap_err();
+        assert!(amf.to_string().starts_with("AMF models are not supported"));
+
+        let glb = detect(b"glTF\x02\x00\x00\x00").unwrap_err();
+        assert!(glb.to_string().starts_with("glTF/GLB models are not supported"));
+
+        let unknown = detect(b"\x00\x01\x02garbage").unwrap_err();
+        assert!(unknown.to_string().starts_with("Unrecognized model format"));
+    }
+}
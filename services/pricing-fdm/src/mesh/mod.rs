@@ -0,0 +1,392 @@
+pub mod dfm;
+mod format;
+mod obj;
+pub mod orient;
+mod stl;
+mod threemf;
+
+pub use format::ModelFormat;
+
+use crate::config::Config;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Indexed triangle mesh in millimeters
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub vertices: Vec<[f64; 3]>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BoundingBox {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+impl BoundingBox {
+    /// Size along X, Y and Z in millimeters
+    pub fn size(&self) -> [f64; 3] {
+        [
+            self.max[0] - self.min[0],
+            self.max[1] - self.min[1],
+            self.max[2] - self.min[2],
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MeshAnalysis {
+    pub volume_cm3: f64,
+    pub surface_area_cm2: f64,
+    pub bounding_box: BoundingBox,
+    pub triangle_count: usize,
+    pub shell_count: usize,
+    /// Edges used by a single triangle (holes in the surface)
+    pub boundary_edges: usize,
+    /// Edges shared by more than two triangles
+    pub non_manifold_edges: usize,
+    pub is_watertight: bool,
+}
+
+/// How far a model may expand while parsing; a small 3MF can otherwise unpack into gigabytes
+#[derive(Debug, Clone, Copy)]
+pub struct ParseLimits {
+    /// Largest any one 3MF zip entry may decompress to
+    pub max_entry_bytes: u64,
+    /// Most triangles a 3MF may instantiate through its build items and components
+    pub max_triangles: usize,
+}
+
+impl ParseLimits {
+    pub fn from_config(config: &Config) -> Self {
+        ParseLimits {
+            max_entry_bytes: config.max_archive_entry_mb * 1024 * 1024,
+            max_triangles: config.max_triangles,
+        }
+    }
+}
+
+/// Load an STL (ASCII or binary), 3MF or OBJ mesh from disk
+pub fn load(path: &Path, limits: ParseLimits) -> Result<Mesh> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read model: {:?}", path))?;
+    parse(&data, limits)
+}
+
+/// Load and analyze a model on the blocking thread pool
+pub async fn analyze_file(path: &Path, limits: ParseLimits) -> Result<MeshAnalysis> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || load(&path, limits).map(|mesh| mesh.analyze()))
+        .await
+        .context("Mesh analysis task panicked")?
+}
+
+/// Load a model on the blocking thread pool
+pub async fn load_file(path: &Path, limits: ParseLimits) -> Result<Mesh> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || load(&path, limits))
+        .await
+        .context("Mesh loading task panicked")?
+}
+
+/// Write a mesh as binary STL
+pub fn write_stl(mesh: &Mesh, path: &Path) -> Result<()> {
+    std::fs::write(path, stl::write_binary(mesh))
+        .with_context(|| format!("Failed to write STL: {:?}", path))
+}
+
+/// Parse mesh bytes, detecting the format from their content
+pub fn parse(data: &[u8], limits: ParseLimits) -> Result<Mesh> {
+    match ModelFormat::detect(data, data.len() as u64)? {
+        ModelFormat::Stl => stl::parse(data),
+        ModelFormat::ThreeMf => threemf::parse(data, limits),
+        ModelFormat::Obj => obj::parse(data),
+        ModelFormat::Step => bail!("STEP models must be converted to a mesh before analysis"),
+    }
+}
+
+impl Mesh {
+    pub fn analyze(&self) -> MeshAnalysis {
+        let mut signed_volume = 0.0;
+        let mut surface_area = 0.0;
+
+        for tri in &self.triangles {
+            let [a, b, c] = self.corners(tri);
+            let ab = sub(b, a);
+            let ac = sub(c, a);
+
+            signed_volume += dot(a, cross(b, c)) / 6.0;
+            surface_area += length(cross(ab, ac)) / 2.0;
+        }
+
+        let (boundary_edges, non_manifold_edges) = self.edge_stats();
+        let triangle_count = self.triangles.len();
+
+        MeshAnalysis {
+            // mm³ -> cm³, mm² -> cm²
+            volume_cm3: signed_volume.abs() / 1000.0,
+            surface_area_cm2: surface_area / 100.0,
+            bounding_box: self.bounding_box(),
+            triangle_count,
+            shell_count: self.shell_count(),
+            boundary_edges,
+            non_manifold_edges,
+            is_watertight: triangle_count > 0 && boundary_edges == 0 && non_manifold_edges == 0,
+        }
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        if self.triangles.is_empty() {
+            return BoundingBox {
+                min: [0.0; 3],
+                max: [0.0; 3],
+            };
+        }
+
+        let mut min = [f64::INFINITY; 3];
+        let mut max = [f64::NEG_INFINITY; 3];
+
+        for tri in &self.triangles {
+            for &idx in tri {
+                let v = self.vertices[idx as usize];
+                for axis in 0..3 {
+                    min[axis] = min[axis].min(v[axis]);
+                    max[axis] = max[axis].max(v[axis]);
+                }
+            }
+        }
+
+        BoundingBox { min, max }
+    }
+
+    fn corners(&self, tri: &[u32; 3]) -> [[f64; 3]; 3] {
+        [
+            self.vertices[tri[0] as usize],
+            self.vertices[tri[1] as usize],
+            self.vertices[tri[2] as usize],
+        ]
+    }
+
+    /// Count boundary (1 face) and non-manifold (>2 faces) edges
+    fn edge_stats(&self) -> (usize, usize) {
+        let mut edges: HashMap<(u32, u32), u32> = HashMap::with_capacity(self.triangles.len() * 3 / 2);
+
+        for tri in &self.triangles {
+            for i in 0..3 {
+                let a = tri[i];
+                let b = tri[(i + 1) % 3];
+                *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+
+        let boundary = edges.values().filter(|&&n| n == 1).count();
+        let non_manifold = edges.values().filter(|&&n| n > 2).count();
+        (boundary, non_manifold)
+    }
+
+    /// Number of connected components (shells) in the mesh
+    fn shell_count(&self) -> usize {
+        self.shells().len()
+    }
+
+    /// Triangle indices of each connected component, in order of first triangle
+    fn shells(&self) -> Vec<Vec<usize>> {
+        let mut parent: Vec<u32> = (0..self.vertices.len() as u32).collect();
+
+        fn find(parent: &mut [u32], mut x: u32) -> u32 {
+            while parent[x as usize] != x {
+                parent[x as usize] = parent[parent[x as usize] as usize];
+                x = parent[x as usize];
+            }
+            x
+        }
+
+        for tri in &self.triangles {
+            let root = find(&mut parent, tri[0]);
+            for &v in &tri[1..] {
+                let other = find(&mut parent, v);
+                if other != root {
+                    parent[other as usize] = root;
+                }
+            }
+        }
+
+        let mut shells: Vec<Vec<usize>> = Vec::new();
+        let mut by_root: HashMap<u32, usize> = HashMap::new();
+        for (index, tri) in self.triangles.iter().enumerate() {
+            let root = find(&mut parent, tri[0]);
+            let shell = *by_root.entry(root).or_insert_with(|| {
+                shells.push(Vec::new());
+                shells.len() - 1
+            });
+            shells[shell].push(index);
+        }
+        shells
+    }
+}
+
+/// Builds an indexed mesh from triangle soup, welding identical vertices
+#[derive(Default)]
+pub(crate) struct MeshBuilder {
+    mesh: Mesh,
+    index: HashMap<[u64; 3], u32>,
+}
+
+impl MeshBuilder {
+    /// Add a triangle; fails on NaN or infinite coordinates, which would poison every
+    /// volume and bounding box computed from the mesh
+    pub fn add_triangle(&mut self, corners: [[f64; 3]; 3]) -> Result<()> {
+        if let Some(v) = corners.iter().find(|v| v.iter().any(|c| !c.is_finite())) {
+            bail!("Vertex has a non-finite coordinate: {:?}", v);
+        }
+
+        let tri = [
+            self.vertex(corners[0]),
+            self.vertex(corners[1]),
+            self.vertex(corners[2]),
+        ];
+
+        // Skip degenerate triangles collapsed onto an edge or point
+        if tri[0] == tri[1] || tri[1] == tri[2] || tri[0] == tri[2] {
+            return Ok(());
+        }
+
+        self.mesh.triangles.push(tri);
+        Ok(())
+    }
+
+    pub fn finish(self) -> Mesh {
+        self.mesh
+    }
+
+    fn vertex(&mut self, v: [f64; 3]) -> u32 {
+        // +0.0 normalizes -0.0 so both weld to the same vertex
+        let key = [
+            (v[0] + 0.0).to_bits(),
+            (v[1] + 0.0).to_bits(),
+            (v[2] + 0.0).to_bits(),
+        ];
+
+        if let Some(&idx) = self.index.get(&key) {
+            return idx;
+        }
+
+        let idx = self.mesh.vertices.len() as u32;
+        self.mesh.vertices.push(v);
+        self.index.insert(key, idx);
+        idx
+    }
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn length(a: [f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Axis-aligned cube with outward-facing triangles
+    pub fn cube(size: f64, offset: [f64; 3]) -> Vec<[[f64; 3]; 3]> {
+        let p = |x: f64, y: f64, z: f64| {
+            [
+                offset[0] + x * size,
+                offset[1] + y * size,
+                offset[2] + z * size,
+            ]
+        };
+        let quads = [
+            [p(0., 0., 0.), p(0., 1., 0.), p(1., 1., 0.), p(1., 0., 0.)], // bottom
+            [p(0., 0., 1.), p(1., 0., 1.), p(1., 1., 1.), p(0., 1., 1.)], // top
+            [p(0., 0., 0.), p(1., 0., 0.), p(1., 0., 1.), p(0., 0., 1.)], // front
+            [p(0., 1., 0.), p(0., 1., 1.), p(1., 1., 1.), p(1., 1., 0.)], // back
+            [p(0., 0., 0.), p(0., 0., 1.), p(0., 1., 1.), p(0., 1., 0.)], // left
+            [p(1., 0., 0.), p(1., 1., 0.), p(1., 1., 1.), p(1., 0., 1.)], // right
+        ];
+
+        quads
+            .iter()
+            .flat_map(|q| [[q[0], q[1], q[2]], [q[0], q[2], q[3]]])
+            .collect()
+    }
+
+    /// Axis-aligned box from `min` to `max` with outward-facing triangles
+    pub fn cuboid(min: [f64; 3], max: [f64; 3]) -> Vec<[[f64; 3]; 3]> {
+        cube(1.0, [0.0; 3])
+            .into_iter()
+            .map(|tri| tri.map(|v| [0, 1, 2].map(|i| min[i] + v[i] * (max[i] - min[i]))))
+            .collect()
+    }
+
+    pub fn build(triangles: Vec<[[f64; 3]; 3]>) -> Mesh {
+        let mut builder = MeshBuilder::default();
+        for tri in triangles {
+            builder.add_triangle(tri).unwrap();
+        }
+        builder.finish()
+    }
+
+    #[test]
+    fn test_cube_analysis() {
+        let mesh = build(cube(10.0, [0.0; 3]));
+        let analysis = mesh.analyze();
+
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!(analysis.triangle_count, 12);
+        assert!((analysis.volume_cm3 - 1.0).abs() < 1e-9);
+        assert!((analysis.surface_area_cm2 - 6.0).abs() < 1e-9);
+        assert_eq!(analysis.bounding_box.size(), [10.0, 10.0, 10.0]);
+        assert_eq!(analysis.shell_count, 1);
+        assert!(analysis.is_watertight);
+    }
+
+    #[test]
+    fn test_open_mesh_is_not_watertight() {
+        let mut triangles = cube(10.0, [0.0; 3]);
+        triangles.truncate(10); // drop the right face
+        let analysis = build(triangles).analyze();
+
+        assert_eq!(analysis.boundary_edges, 4);
+        assert!(!analysis.is_watertight);
+    }
+
+    #[test]
+    fn test_two_shells() {
+        let mut triangles = cube(10.0, [0.0; 3]);
+        triangles.extend(cube(5.0, [20.0, 0.0, 0.0]));
+        let analysis = build(triangles).analyze();
+
+        assert_eq!(analysis.shell_count, 2);
+        assert!((analysis.volume_cm3 - 1.125).abs() < 1e-9);
+        assert_eq!(analysis.bounding_box.size(), [25.0, 10.0, 10.0]);
+    }
+
+    #[test]
+    fn test_non_manifold_edge() {
+        let mut triangles = cube(10.0, [0.0; 3]);
+        // Fin sharing the cube's bottom-front edge
+        triangles.push([[0.0, 0.0, 0.0], [10.0, 0.0, 0.0], [5.0, -5.0, 0.0]]);
+        let analysis = build(triangles).analyze();
+
+        assert_eq!(analysis.non_manifold_edges, 1);
+        assert!(!analysis.is_watertight);
+    }
+}
@@ -0,0 +1,101 @@
+use super::{Mesh, MeshBuilder};
+use anyhow::{bail, Context, Result};
+
+/// Parse Wavefront OBJ geometry (vertices and faces; units assumed to be millimeters)
+pub fn parse(data: &[u8]) -> Result<Mesh> {
+    let text = std::str::from_utf8(data).context("OBJ file is not valid UTF-8")?;
+    let mut vertices: Vec<[f64; 3]> = Vec::new();
+    let mut builder = MeshBuilder::default();
+
+    for (line_no, line) in text.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let mut v = [0.0; 3];
+                for value in v.iter_mut() {
+                    *value = tokens
+                        .next()
+                        .and_then(|t| t.parse::<f64>().ok())
+                        .with_context(|| format!("Invalid vertex on line {}", line_no + 1))?;
+                }
+                vertices.push(v);
+            }
+            Some("f") => {
+                let corners = tokens
+                    .map(|t| resolve_index(t, vertices.len()).map(|i| vertices[i]))
+                    .collect::<Option<Vec<_>>>()
+                    .with_context(|| format!("Invalid face on line {}", line_no + 1))?;
+                if corners.len() < 3 {
+                    bail!(
+                        "Face on line {} has {} vertices, expected at least 3",
+                        line_no + 1,
+                        corners.len()
+                    );
+                }
+
+                // Fan-triangulate polygons (OBJ faces are expected to be convex)
+                for i in 1..corners.len() - 1 {
+                    builder.add_triangle([corners[0], corners[i], corners[i + 1]])?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(builder.finish())
+}
+
+/// Resolve a face vertex reference (`v`, `v/vt`, `v//vn` or `v/vt/vn`) to a 0-based index.
+///
+/// Negative indices count back from the most recent vertex.
+fn resolve_index(token: &str, vertex_count: usize) -> Option<usize> {
+    let index: i64 = token.split('/').next()?.parse().ok()?;
+    let resolved = match index {
+        i if i > 0 => i - 1,
+        i if i < 0 => vertex_count as i64 + i,
+        _ => return None,
+    };
+    (0..vertex_count as i64)
+        .contains(&resolved)
+        .then_some(resolved as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUBE: &str = "\
+# unit cube, quads, mixed index styles
+o Cube
+v 0 0 0
+v 10 0 0
+v 10 10 0
+v 0 10 0
+v 0 0 10
+v 10 0 10
+v 10 10 10
+v 0 10 10
+vn 0 0 1
+f 1 4 3 2
+f 5/1 6/1 7/1 8/1
+f 1//1 2//1 6//1 5//1
+f -5 -1 -2 -6
+f 1 5 8 4
+f 2 3 7 6
+";
+
+    #[test]
+    fn test_quad_cube() {
+        let analysis = parse(CUBE.as_bytes()).unwrap().analyze();
+
+        assert_eq!(analysis.triangle_count, 12);
+        assert!((analysis.volume_cm3 - 1.0).abs() < 1e-9);
+        assert!(analysis.is_watertight);
+    }
+
+    #[test]
+    fn test_out_of_range_index_is_rejected() {
+        let err = parse(b"v 0 0 0\nv 1 0 0\nf 1 2 3\n").unwrap_err();
+        assert!(err.to_string().contains("Invalid face on line 3"));
+    }
+}
@@ -0,0 +1,253 @@
+use super::{cross, dot, length, sub, Mesh};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Faces closer to horizontal than this (degrees from the build plate) need support
+const OVERHANG_ANGLE_DEG: f64 = 45.0;
+/// Faces within this distance of the bed rest on it
+const BED_CONTACT_MM: f64 = 0.05;
+/// Largest flat regions tried as the bottom face, on top of the six axis directions
+const FLAT_FACE_CANDIDATES: usize = 6;
+/// Support-equivalent cost of 1 mm of height (more layers to print)
+const HEIGHT_WEIGHT_CM3_PER_MM: f64 = 0.02;
+
+/// Row-major rotation applied to model coordinates (`v' = R · v`)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Rotation(pub [[f64; 3]; 3]);
+
+impl Rotation {
+    pub const IDENTITY: Rotation = Rotation([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+
+    /// Rotation that turns the unit direction `down` to point at -Z (onto the bed)
+    pub fn face_down(down: [f64; 3]) -> Self {
+        let target = [0.0, 0.0, -1.0];
+        let axis = cross(down, target);
+        let sin = length(axis);
+        let cos = dot(down, target);
+
+        if sin < 1e-9 {
+            return if cos > 0.0 {
+                Rotation::IDENTITY
+            } else {
+                // Upside down: half turn about X
+                Rotation([[1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, -1.0]])
+            };
+        }
+
+        // Rodrigues: R = I + K + K² (1 - cos) / sin²
+        let [x, y, z] = axis;
+        let k = [[0.0, -z, y], [z, 0.0, -x], [-y, x, 0.0]];
+        let factor = (1.0 - cos) / (sin * sin);
+        let mut r = Rotation::IDENTITY.0;
+        for (i, row) in r.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                let k2: f64 = (0..3).map(|n| k[i][n] * k[n][j]).sum();
+                // Round away float noise so axis-aligned turns come out as exact 0/±1
+                *value = ((*value + k[i][j] + k2 * factor) * 1e12).round() / 1e12 + 0.0;
+            }
+        }
+        Rotation(r)
+    }
+
+    pub fn apply(&self, v: [f64; 3]) -> [f64; 3] {
+        let r = &self.0;
+        [dot(r[0], v), dot(r[1], v), dot(r[2], v)]
+    }
+}
+
+/// Mesh-only estimate of how expensive an orientation is to print
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct OrientationScore {
+    /// Overhang area projected onto the bed times its height above it
+    pub support_volume_cm3: f64,
+    /// Downward-facing area steeper than the overhang angle, not resting on the bed
+    pub overhang_area_cm2: f64,
+    /// Bounding box after rotation
+    pub size_mm: [f64; 3],
+}
+
+impl OrientationScore {
+    pub fn height_mm(&self) -> f64 {
+        self.size_mm[2]
+    }
+
+    /// Lower is cheaper: support volume, plus a small weight for height
+    pub fn cost(&self) -> f64 {
+        self.support_volume_cm3 + self.height_mm() * HEIGHT_WEIGHT_CM3_PER_MM
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Orientation {
+    pub rotation: Rotation,
+    pub score: OrientationScore,
+}
+
+/// Candidate orientations, cheapest first.
+///
+/// Tries each axis direction and the largest flat regions as the bottom face. Ties keep
+/// the earlier candidate, so a model already lying well stays as uploaded.
+pub fn candidates(mesh: &Mesh) -> Vec<Orientation> {
+    let mut directions: Vec<[f64; 3]> = vec![
+        [0.0, 0.0, -1.0],
+        [0.0, 0.0, 1.0],
+        [-1.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [0.0, -1.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+    for normal in flat_regions(mesh).into_iter().take(FLAT_FACE_CANDIDATES) {
+        if directions.iter().all(|d| dot(*d, normal) < 1.0 - 1e-6) {
+            directions.push(normal);
+        }
+    }
+
+    let mut orientations: Vec<Orientation> = directions
+        .into_iter()
+        .map(|down| {
+            let rotation = Rotation::face_down(down);
+            Orientation {
+                rotation,
+                score: score(mesh, &rotation).0,
+            }
+        })
+        .collect();
+    orientations.sort_by(|a, b| a.score.cost().total_cmp(&b.score.cost()));
+    orientations
+}
+
+/// Score the mesh as it would sit on the bed after `rotation`, returning the indices of the
+/// triangles that need support along with it
+pub fn score(mesh: &Mesh, rotation: &Rotation) -> (OrientationScore, Vec<usize>) {
+    let vertices: Vec<[f64; 3]> = mesh.vertices.iter().map(|v| rotation.apply(*v)).collect();
+
+    let mut min = [f64::INFINITY; 3];
+    let mut max = [f64::NEG_INFINITY; 3];
+    for tri in &mesh.triangles {
+        for &idx in tri {
+            let v = vertices[idx as usize];
+            for axis in 0..3 {
+                min[axis] = min[axis].min(v[axis]);
+                max[axis] = max[axis].max(v[axis]);
+            }
+        }
+    }
+    if mesh.triangles.is_empty() {
+        (min, max) = ([0.0; 3], [0.0; 3]);
+    }
+
+    let overhang_z = -OVERHANG_ANGLE_DEG.to_radians().cos();
+    let mut support_mm3 = 0.0;
+    let mut overhang_mm2 = 0.0;
+    let mut overhangs = Vec::new();
+    for (index, tri) in mesh.triangles.iter().enumerate() {
+        let [a, b, c] = tri.map(|i| vertices[i as usize]);
+        let normal = cross(sub(b, a), sub(c, a));
+        let double_area = length(normal);
+        if double_area == 0.0 || normal[2] / double_area >= overhang_z {
+            continue;
+        }
+        if [a, b, c].iter().all(|v| v[2] - min[2] < BED_CONTACT_MM) {
+            continue;
+        }
+
+        let area = double_area / 2.0;
+        let centroid_z = (a[2] + b[2] + c[2]) / 3.0 - min[2];
+        overhang_mm2 += area;
+        overhangs.push(index);
+        // Projected (XY) area is |normal.z| / 2
+        support_mm3 += normal[2].abs() / 2.0 * centroid_z;
+    }
+
+    let score = OrientationScore {
+        support_volume_cm3: support_mm3 / 1000.0,
+        overhang_area_cm2: overhang_mm2 / 100.0,
+        size_mm: [max[0] - min[0], max[1] - min[1], max[2] - min[2]],
+    };
+    (score, overhangs)
+}
+
+/// Outward normals of coplanar-facing regions, largest total area first
+fn flat_regions(mesh: &Mesh) -> Vec<[f64; 3]> {
+    let mut regions: HashMap<[i32; 3], ([f64; 3], f64)> = HashMap::new();
+    for tri in &mesh.triangles {
+        let [a, b, c] = mesh.corners(tri);
+        let normal = cross(sub(b, a), sub(c, a));
+        let double_area = length(normal);
+        if double_area == 0.0 {
+            continue;
+        }
+        let unit = normal.map(|n| n / double_area);
+        let key = unit.map(|n| (n * 1000.0).round() as i32);
+        let region = regions.entry(key).or_insert((unit, 0.0));
+        region.1 += double_area / 2.0;
+    }
+
+    let mut regions: Vec<([f64; 3], f64)> = regions.into_values().collect();
+    regions.sort_by(|a, b| b.1.total_cmp(&a.1));
+    regions.into_iter().map(|(normal, _)| normal).collect()
+}
+
+impl Mesh {
+    /// Copy of the mesh with `rotation` applied to every vertex
+    pub fn rotated(&self, rotation: &Rotation) -> Mesh {
+        Mesh {
+            vertices: self.vertices.iter().map(|v| rotation.apply(*v)).collect(),
+            triangles: self.triangles.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::tests::{build, cube, cuboid};
+
+    #[test]
+    fn test_face_down_rotations() {
+        for down in [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.6, 0.0, 0.8]] {
+            let r = Rotation::face_down(down);
+            let turned = r.apply(down);
+            assert!((turned[2] + 1.0).abs() < 1e-9, "{:?} -> {:?}", down, turned);
+            // Rows stay orthonormal
+            for i in 0..3 {
+                assert!((dot(r.0[i], r.0[i]) - 1.0).abs() < 1e-9);
+            }
+        }
+        assert_eq!(Rotation::face_down([0.0, 0.0, -1.0]), Rotation::IDENTITY);
+        assert_eq!(
+            Rotation::face_down([1.0, 0.0, 0.0]).0,
+            [[0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [-1.0, 0.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn test_cube_keeps_uploaded_orientation() {
+        let mesh = build(cube(20.0, [0.0; 3]));
+        let best = candidates(&mesh)[0];
+
+        assert_eq!(best.rotation, Rotation::IDENTITY);
+        assert_eq!(best.score.support_volume_cm3, 0.0);
+        assert!((mesh.rotated(&best.rotation).analyze().volume_cm3 - 8.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_mushroom_prints_cap_down() {
+        // 10 mm stem, 30 mm tall, under a 40 x 40 x 5 mm cap
+        let mut triangles = cuboid([15.0, 15.0, 0.0], [25.0, 25.0, 30.0]);
+        triangles.extend(cuboid([0.0, 0.0, 30.0], [40.0, 40.0, 35.0]));
+        let mesh = build(triangles);
+
+        // Upright, the whole cap underside needs 30 mm of support
+        let (upright, overhangs) = score(&mesh, &Rotation::IDENTITY);
+        assert!((upright.support_volume_cm3 - 48.0).abs() < 1e-9);
+        assert_eq!(overhangs.len(), 2);
+        assert!((upright.overhang_area_cm2 - 16.0).abs() < 1e-9);
+
+        let best = candidates(&mesh)[0];
+        assert_eq!(best.rotation, Rotation::face_down([0.0, 0.0, 1.0]));
+        assert!(best.score.support_volume_cm3 < 1.0);
+        assert_eq!(best.score.size_mm, [40.0, 40.0, 35.0]);
+    }
+}
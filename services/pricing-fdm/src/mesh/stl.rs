@@ -0,0 +1,207 @@
+use super::{Mesh, MeshBuilder};
+use anyhow::{bail, Context, Result};
+
+const HEADER_LEN: usize = 80;
+const TRIANGLE_LEN: usize = 50;
+
+/// Parse ASCII or binary STL
+pub fn parse(data: &[u8]) -> Result<Mesh> {
+    // Binary STLs may also start with "solid", so trust the size check first
+    if is_binary(data) {
+        parse_binary(data)
+    } else if data.trim_ascii_start().starts_with(b"solid") {
+        parse_ascii(data)
+    } else {
+        bail!("Not a valid STL file");
+    }
+}
+
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() < HEADER_LEN + 4 {
+        return false;
+    }
+    let count = triangle_count(data) as usize;
+    data.len() == HEADER_LEN + 4 + count * TRIANGLE_LEN
+}
+
+fn triangle_count(data: &[u8]) -> u32 {
+    u32::from_le_bytes([
+        data[HEADER_LEN],
+        data[HEADER_LEN + 1],
+        data[HEADER_LEN + 2],
+        data[HEADER_LEN + 3],
+    ])
+}
+
+fn parse_binary(data: &[u8]) -> Result<Mesh> {
+    let count = triangle_count(data) as usize;
+    let mut builder = MeshBuilder::default();
+
+    for i in 0..count {
+        // Skip the 12-byte normal, read 3 vertices, ignore 2-byte attribute
+        let offset = HEADER_LEN + 4 + i * TRIANGLE_LEN + 12;
+        let mut corners = [[0.0; 3]; 3];
+        for (v, corner) in corners.iter_mut().enumerate() {
+            for (axis, value) in corner.iter_mut().enumerate() {
+                let start = offset + (v * 3 + axis) * 4;
+                let bytes = [data[start], data[start + 1], data[start + 2], data[start + 3]];
+                *value = f32::from_le_bytes(bytes) as f64;
+            }
+        }
+        builder.add_triangle(corners)?;
+    }
+
+    Ok(builder.finish())
+}
+
+/// Serialize as binary STL, with facet normals computed from the winding
+pub fn write_binary(mesh: &Mesh) -> Vec<u8> {
+    let mut out = vec![0u8; HEADER_LEN];
+    let header = b"pricing-fdm";
+    out[..header.len()].copy_from_slice(header);
+    out.extend_from_slice(&(mesh.triangles.len() as u32).to_le_bytes());
+    out.reserve(mesh.triangles.len() * TRIANGLE_LEN);
+
+    for tri in &mesh.triangles {
+        let corners = mesh.corners(tri);
+        let normal = super::cross(super::sub(corners[1], corners[0]), super::sub(corners[2], corners[0]));
+        let len = super::length(normal);
+        let normal = if len > 0.0 { normal.map(|n| n / len) } else { normal };
+        for v in std::iter::once(normal).chain(corners) {
+            for value in v {
+                out.extend_from_slice(&(value as f32).to_le_bytes());
+            }
+        }
+        out.extend_from_slice(&[0u8; 2]);
+    }
+    out
+}
+
+fn parse_ascii(data: &[u8]) -> Result<Mesh> {
+    let text = std::str::from_utf8(data).context("ASCII STL is not valid UTF-8")?;
+    let mut builder = MeshBuilder::default();
+    let mut corners: Vec<[f64; 3]> = Vec::with_capacity(3);
+
+    for (line_no, line) in text.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("vertex") => {
+                let mut v = [0.0; 3];
+                for value in v.iter_mut() {
+                    *value = tokens
+                        .next()
+                        .and_then(|t| t.parse::<f64>().ok())
+                        .with_context(|| format!("Invalid vertex on line {}", line_no + 1))?;
+                }
+                corners.push(v);
+            }
+            Some("endfacet") => {
+                if corners.len() != 3 {
+                    bail!(
+                        "Facet ending on line {} has {} vertices, expected 3",
+                        line_no + 1,
+                        corners.len()
+                    );
+                }
+                builder.add_triangle([corners[0], corners[1], corners[2]])?;
+                corners.clear();
+            }
+            _ => {}
+        }
+    }
+
+    Ok(builder.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::tests::cube;
+
+    fn ascii_stl(triangles: &[[[f64; 3]; 3]]) -> String {
+        let mut out = String::from("solid cube\n");
+        for tri in triangles {
+            out.push_str("  facet normal 0 0 0\n    outer loop\n");
+            for v in tri {
+                out.push_str(&format!("      vertex {} {} {}\n", v[0], v[1], v[2]));
+            }
+            out.push_str("    endloop\n  endfacet\n");
+        }
+        out.push_str("endsolid cube\n");
+        out
+    }
+
+    fn binary_stl(triangles: &[[[f64; 3]; 3]], header: &[u8]) -> Vec<u8> {
+        let mut out = vec![0u8; HEADER_LEN];
+        out[..header.len()].copy_from_slice(header);
+        out.extend_from_slice(&(triangles.len() as u32).to_le_bytes());
+        for tri in triangles {
+            out.extend_from_slice(&[0u8; 12]);
+            for v in tri {
+                for value in v {
+                    out.extend_from_slice(&(*value as f32).to_le_bytes());
+                }
+            }
+            out.extend_from_slice(&[0u8; 2]);
+        }
+        out
+    }
+
+    #[test]
+    fn test_parse_ascii() {
+        let mesh = parse(ascii_stl(&cube(10.0, [0.0; 3])).as_bytes()).unwrap();
+        let analysis = mesh.analyze();
+
+        assert_eq!(analysis.triangle_count, 12);
+        assert!((analysis.volume_cm3 - 1.0).abs() < 1e-9);
+        assert!(analysis.is_watertight);
+    }
+
+    #[test]
+    fn test_parse_binary() {
+        let mesh = parse(&binary_stl(&cube(20.0, [0.0; 3]), b"binary")).unwrap();
+        let analysis = mesh.analyze();
+
+        assert_eq!(mesh.vertices.len(), 8);
+        assert!((analysis.volume_cm3 - 8.0).abs() < 1e-6);
+        assert!(analysis.is_watertight);
+    }
+
+    #[test]
+    fn test_binary_with_solid_header() {
+        // Some exporters write "solid" into the binary header
+        let mesh = parse(&binary_stl(&cube(10.0, [0.0; 3]), b"solid exported")).unwrap();
+        assert_eq!(mesh.triangles.len(), 12);
+    }
+
+    #[test]
+    fn test_write_binary_round_trip() {
+        let mesh = parse(&binary_stl(&cube(20.0, [5.0; 3]), b"binary")).unwrap();
+        let written = write_binary(&mesh);
+
+        assert_eq!(written.len(), HEADER_LEN + 4 + 12 * TRIANGLE_LEN);
+        let reparsed = parse(&written).unwrap();
+        assert!((reparsed.analyze().volume_cm3 - 8.0).abs() < 1e-6);
+        assert_eq!(reparsed.bounding_box().min, [5.0; 3]);
+    }
+
+    #[test]
+    fn test_rejects_non_finite_coordinates() {
+        let mut tris = cube(10.0, [0.0; 3]);
+        tris[3][1][2] = f64::NAN;
+
+        let err = parse(&binary_stl(&tris, b"binary")).unwrap_err();
+        assert!(err.to_string().contains("non-finite"), "{:#}", err);
+    }
+
+    #[test]
+    fn test_rejects_garbage() {
+        assert!(parse(b"definitely not a mesh").is_err());
+    }
+
+    #[test]
+    fn test_rejects_incomplete_facet() {
+        let stl = "solid x\nfacet normal 0 0 0\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nendloop\nendfacet\nendsolid x\n";
+        assert!(parse(stl.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,516 @@
+use super::{Mesh, MeshBuilder, ParseLimits};
+use anyhow::{bail, Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+
+const DEFAULT_MODEL_PATH: &str = "3D/3dmodel.model";
+const MAX_COMPONENT_DEPTH: usize = 16;
+
+/// Affine transform in 3MF order: m00 m01 m02 m10 m11 m12 m20 m21 m22 m30 m31 m32
+type Transform = [f64; 12];
+
+const IDENTITY: Transform = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+
+enum Object {
+    Mesh {
+        vertices: Vec<[f64; 3]>,
+        triangles: Vec<[u32; 3]>,
+    },
+    Components(Vec<ObjectRef>),
+}
+
+struct ObjectRef {
+    object_id: String,
+    /// Model part the object lives in (production extension `p:path`)
+    path: Option<String>,
+    transform: Transform,
+}
+
+struct ModelPart {
+    /// Scale to millimeters from the model's `unit` attribute
+    scale: f64,
+    objects: HashMap<String, Object>,
+    build: Vec<ObjectRef>,
+}
+
+/// Zip archive of a 3MF package, with caps on how far it may expand
+struct Package<'a> {
+    archive: zip::ZipArchive<Cursor<&'a [u8]>>,
+    limits: ParseLimits,
+    /// Triangles instantiated so far, across all build items and components
+    triangles: usize,
+}
+
+impl Package<'_> {
+    /// Read an entry as text, refusing it once it decompresses past the cap (zip bombs)
+    fn read_entry(&mut self, path: &str) -> Result<String> {
+        let entry = self
+            .archive
+            .by_name(path)
+            .with_context(|| format!("3MF entry not found: {}", path))?;
+        if entry.size() > self.limits.max_entry_bytes {
+            bail!(
+                "3MF entry {} is {} bytes uncompressed, limit is {}",
+                path,
+                entry.size(),
+                self.limits.max_entry_bytes
+            );
+        }
+
+        // The declared size is untrusted; stop reading one byte past the cap
+        let mut content = Vec::new();
+        entry
+            .take(self.limits.max_entry_bytes.saturating_add(1))
+            .read_to_end(&mut content)?;
+        if content.len() as u64 > self.limits.max_entry_bytes {
+            bail!(
+                "3MF entry {} exceeds {} bytes uncompressed",
+                path,
+                self.limits.max_entry_bytes
+            );
+        }
+        String::from_utf8(content).with_context(|| format!("3MF entry {} is not UTF-8", path))
+    }
+}
+
+/// Parse a 3MF package, flattening all build items into one mesh.
+///
+/// Fails if any entry read decompresses to more than `limits.max_entry_bytes`, or if the build
+/// instantiates more than `limits.max_triangles` triangles (components can fan out
+/// exponentially within the depth cap).
+pub fn parse(data: &[u8], limits: ParseLimits) -> Result<Mesh> {
+    let archive = zip::ZipArchive::new(Cursor::new(data)).context("Invalid 3MF archive")?;
+    let mut package = Package {
+        archive,
+        limits,
+        triangles: 0,
+    };
+
+    let root_path = root_model_path(&mut package).unwrap_or_else(|| DEFAULT_MODEL_PATH.to_string());
+    let mut parts: HashMap<String, ModelPart> = HashMap::new();
+    load_part(&mut package, &mut parts, &root_path)?;
+
+    let mut builder = MeshBuilder::default();
+    let build = std::mem::take(&mut parts.get_mut(&root_path).expect("root part loaded").build);
+    if build.is_empty() {
+        bail!("3MF model has no build items");
+    }
+
+    for item in &build {
+        instantiate(&mut package, &mut parts, &root_path, item, IDENTITY, 0, &mut builder)?;
+    }
+
+    Ok(builder.finish())
+}
+
+fn instantiate(
+    package: &mut Package<'_>,
+    parts: &mut HashMap<String, ModelPart>,
+    current_path: &str,
+    item: &ObjectRef,
+    parent: Transform,
+    depth: usize,
+    builder: &mut MeshBuilder,
+) -> Result<()> {
+    if depth > MAX_COMPONENT_DEPTH {
+        bail!("3MF component nesting too deep");
+    }
+
+    let path = item
+        .path
+        .as_deref()
+        .map(|p| p.trim_start_matches('/').to_string())
+        .unwrap_or_else(|| current_path.to_string());
+    load_part(package, parts, &path)?;
+
+    let transform = compose(item.transform, parent);
+    let part = &parts[&path];
+    let scale = part.scale;
+
+    match part.objects.get(&item.object_id) {
+        Some(Object::Mesh {
+            vertices,
+            triangles,
+        }) => {
+            package.triangles += triangles.len();
+            if package.triangles > package.limits.max_triangles {
+                bail!(
+                    "3MF build instantiates more than {} triangles",
+                    package.limits.max_triangles
+                );
+            }
+            for tri in triangles {
+                let mut corners = [[0.0; 3]; 3];
+                for (corner, &idx) in corners.iter_mut().zip(tri) {
+                    let v = vertices
+                        .get(idx as usize)
+                        .with_context(|| format!("Triangle references missing vertex {}", idx))?;
+                    *corner = apply(&transform, [v[0] * scale, v[1] * scale, v[2] * scale]);
+                }
+                builder.add_triangle(corners)?;
+            }
+        }
+        Some(Object::Components(components)) => {
+            let children: Vec<ObjectRef> = components
+                .iter()
+                .map(|c| ObjectRef {
+                    object_id: c.object_id.clone(),
+                    path: c.path.clone(),
+                    transform: c.transform,
+                })
+                .collect();
+            for child in &children {
+                instantiate(package, parts, &path, child, transform, depth + 1, builder)?;
+            }
+        }
+        None => bail!("3MF build references unknown object {}", item.object_id),
+    }
+
+    Ok(())
+}
+
+/// Find the root model part from `_rels/.rels`
+fn root_model_path(package: &mut Package<'_>) -> Option<String> {
+    let rels = package.read_entry("_rels/.rels").ok()?;
+    let mut reader = Reader::from_str(&rels);
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Empty(e)) | Ok(Event::Start(e)) if e.local_name().as_ref() == b"Relationship" => {
+                let rel_type = attr(&e, b"Type").ok()?;
+                if rel_type.is_some_and(|t| t.ends_with("/3dmodel")) {
+                    return attr(&e, b"Target")
+                        .ok()?
+                        .map(|t| t.trim_start_matches('/').to_string());
+                }
+            }
+            Ok(Event::Eof) | Err(_) => return None,
+            _ => {}
+        }
+    }
+}
+
+fn load_part(
+    package: &mut Package<'_>,
+    parts: &mut HashMap<String, ModelPart>,
+    path: &str,
+) -> Result<()> {
+    if parts.contains_key(path) {
+        return Ok(());
+    }
+    let xml = package.read_entry(path)?;
+    let part = parse_model(&xml).with_context(|| format!("Invalid 3MF model part: {}", path))?;
+    parts.insert(path.to_string(), part);
+    Ok(())
+}
+
+fn parse_model(xml: &str) -> Result<ModelPart> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut part = ModelPart {
+        scale: 1.0,
+        objects: HashMap::new(),
+        build: Vec::new(),
+    };
+
+    let mut current_id: Option<String> = None;
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+    let mut components = Vec::new();
+
+    loop {
+        let event = reader.read_event()?;
+        match &event {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"model" => {
+                    part.scale = unit_scale(attr(e, b"unit")?.as_deref().unwrap_or("millimeter"))?;
+                }
+                b"object" => {
+                    current_id = Some(required_attr(e, b"id")?);
+                    vertices.clear();
+                    triangles.clear();
+                    components.clear();
+                }
+                b"vertex" => {
+                    vertices.push([
+                        required_attr(e, b"x")?.parse::<f64>()?,
+                        required_attr(e, b"y")?.parse::<f64>()?,
+                        required_attr(e, b"z")?.parse::<f64>()?,
+                    ]);
+                }
+                b"triangle" => {
+                    triangles.push([
+                        required_attr(e, b"v1")?.parse::<u32>()?,
+                        required_attr(e, b"v2")?.parse::<u32>()?,
+                        required_attr(e, b"v3")?.parse::<u32>()?,
+                    ]);
+                }
+                b"component" => components.push(object_ref(e)?),
+                b"item" => part.build.push(object_ref(e)?),
+                _ => {}
+            },
+            Event::End(e) if e.local_name().as_ref() == b"object" => {
+                let id = current_id.take().context("Unbalanced <object> element")?;
+                let object = if components.is_empty() {
+                    Object::Mesh {
+                        vertices: std::mem::take(&mut vertices),
+                        triangles: std::mem::take(&mut triangles),
+                    }
+                } else {
+                    Object::Components(std::mem::take(&mut components))
+                };
+                part.objects.insert(id, object);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(part)
+}
+
+fn object_ref(e: &BytesStart) -> Result<ObjectRef> {
+    Ok(ObjectRef {
+        object_id: required_attr(e, b"objectid")?,
+        path: attr(e, b"path")?,
+        transform: match attr(e, b"transform")? {
+            Some(t) => parse_transform(&t)?,
+            None => IDENTITY,
+        },
+    })
+}
+
+fn attr(e: &BytesStart, name: &[u8]) -> Result<Option<String>> {
+    for a in e.attributes() {
+        let a = a?;
+        if a.key.local_name().as_ref() == name {
+            return Ok(Some(a.unescape_value()?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+fn required_attr(e: &BytesStart, name: &[u8]) -> Result<String> {
+    attr(e, name)?.with_context(|| {
+        format!(
+            "<{}> is missing attribute '{}'",
+            String::from_utf8_lossy(e.local_name().as_ref()),
+            String::from_utf8_lossy(name)
+        )
+    })
+}
+
+fn parse_transform(value: &str) -> Result<Transform> {
+    let numbers: Vec<f64> = value
+        .split_whitespace()
+        .map(|n| n.parse::<f64>())
+        .collect::<std::result::Result<_, _>>()
+        .with_context(|| format!("Invalid transform: {}", value))?;
+    numbers
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Transform must have 12 values: {}", value))
+}
+
+fn unit_scale(unit: &str) -> Result<f64> {
+    Ok(match unit {
+        "micron" => 0.001,
+        "millimeter" => 1.0,
+        "centimeter" => 10.0,
+        "inch" => 25.4,
+        "foot" => 304.8,
+        "meter" => 1000.0,
+        other => bail!("Unsupported 3MF unit: {}", other),
+    })
+}
+
+fn apply(t: &Transform, p: [f64; 3]) -> [f64; 3] {
+    [
+        p[0] * t[0] + p[1] * t[3] + p[2] * t[6] + t[9],
+        p[0] * t[1] + p[1] * t[4] + p[2] * t[7] + t[10],
+        p[0] * t[2] + p[1] * t[5] + p[2] * t[8] + t[11],
+    ]
+}
+
+/// Transform that applies `first`, then `second`
+fn compose(first: Transform, second: Transform) -> Transform {
+    let mut out = [0.0; 12];
+    for row in 0..4 {
+        for col in 0..3 {
+            let mut value = (0..3)
+                .map(|k| first[row * 3 + k] * second[k * 3 + col])
+                .sum::<f64>();
+            if row == 3 {
+                value += second[9 + col];
+            }
+            out[row * 3 + col] = value;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const MAX_ENTRY: u64 = 1 << 20;
+    const LIMITS: ParseLimits = ParseLimits {
+        max_entry_bytes: MAX_ENTRY,
+        max_triangles: 10_000,
+    };
+
+    fn package(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut buf = Cursor::new(Vec::new());
+        {
+            let mut zip = zip::ZipWriter::new(&mut buf);
+            for (name, content) in files {
+                zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+                zip.write_all(content.as_bytes()).unwrap();
+            }
+            zip.finish().unwrap();
+        }
+        buf.into_inner()
+    }
+
+    const TETRA_OBJECT: &str = r#"
+        <object id="1" type="model">
+          <mesh>
+            <vertices>
+              <vertex x="0" y="0" z="0"/>
+              <vertex x="10" y="0" z="0"/>
+              <vertex x="0" y="10" z="0"/>
+              <vertex x="0" y="0" z="10"/>
+            </vertices>
+            <triangles>
+              <triangle v1="0" v2="2" v3="1"/>
+              <triangle v1="0" v2="1" v3="3"/>
+              <triangle v1="0" v2="3" v3="2"/>
+              <triangle v1="1" v2="2" v3="3"/>
+            </triangles>
+          </mesh>
+        </object>"#;
+
+    #[test]
+    fn test_parse_mesh_with_build_transforms() {
+        let model = format!(
+            r#"<?xml version="1.0"?>
+            <model unit="millimeter" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">
+              <resources>{}</resources>
+              <build>
+                <item objectid="1"/>
+                <item objectid="1" transform="2 0 0 0 2 0 0 0 2 50 0 0"/>
+              </build>
+            </model>"#,
+            TETRA_OBJECT
+        );
+        let data = package(&[("3D/3dmodel.model", &model)]);
+
+        let analysis = parse(&data, LIMITS).unwrap().analyze();
+
+        // 1000/6 mm³ for the unit tetra plus 8x for the scaled copy
+        assert!((analysis.volume_cm3 - 9.0 * 1000.0 / 6.0 / 1000.0).abs() < 1e-9);
+        assert_eq!(analysis.shell_count, 2);
+        assert_eq!(analysis.bounding_box.size(), [70.0, 20.0, 20.0]);
+        assert!(analysis.is_watertight);
+    }
+
+    #[test]
+    fn test_components_in_separate_part() {
+        let rels = r#"<?xml version="1.0"?>
+            <Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+              <Relationship Target="/3D/3dmodel.model" Id="rel0" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel"/>
+            </Relationships>"#;
+        let root = r#"<?xml version="1.0"?>
+            <model unit="centimeter" xmlns:p="http://schemas.microsoft.com/3dmanufacturing/production/2015/06">
+              <resources>
+                <object id="2" type="model">
+                  <components>
+                    <component p:path="/3D/Objects/object_1.model" objectid="1" transform="1 0 0 0 1 0 0 0 1 0 0 5"/>
+                  </components>
+                </object>
+              </resources>
+              <build><item objectid="2"/></build>
+            </model>"#;
+        let object = format!(
+            r#"<?xml version="1.0"?><model unit="millimeter"><resources>{}</resources><build/></model>"#,
+            TETRA_OBJECT
+        );
+        let data = package(&[
+            ("_rels/.rels", rels),
+            ("3D/3dmodel.model", root),
+            ("3D/Objects/object_1.model", &object),
+        ]);
+
+        let mesh = parse(&data, LIMITS).unwrap();
+        let bbox = mesh.bounding_box();
+
+        // Object part is in millimeters, component translation in the root part is applied
+        assert_eq!(bbox.size(), [10.0, 10.0, 10.0]);
+        assert_eq!(bbox.min[2], 5.0);
+    }
+
+    #[test]
+    fn test_rejects_entries_that_decompress_past_the_cap() {
+        // Compresses to a few KB
+        let model = format!(
+            r#"<?xml version="1.0"?><model unit="millimeter"><resources>{}</resources><build><item objectid="1"/></build></model>{}"#,
+            TETRA_OBJECT,
+            " ".repeat(2 * MAX_ENTRY as usize)
+        );
+        let data = package(&[("3D/3dmodel.model", &model)]);
+        assert!(data.len() < 64 * 1024);
+
+        let err = parse(&data, LIMITS).unwrap_err();
+        assert!(format!("{:#}", err).contains("limit is 1048576"), "{:#}", err);
+        let limits = ParseLimits {
+            max_entry_bytes: 4 * MAX_ENTRY,
+            ..LIMITS
+        };
+        assert!(parse(&data, limits).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_component_fan_out_past_the_triangle_cap() {
+        // Each level repeats the one below 10 times: 4 * 10^5 triangles within the depth cap
+        let mut objects = TETRA_OBJECT.to_string();
+        for id in 2..=6 {
+            let components = format!(r#"<component objectid="{}"/>"#, id - 1).repeat(10);
+            objects.push_str(&format!(
+                r#"<object id="{}" type="model"><components>{}</components></object>"#,
+                id, components
+            ));
+        }
+        let model = format!(
+            r#"<?xml version="1.0"?><model unit="millimeter"><resources>{}</resources><build><item objectid="6"/></build></model>"#,
+            objects
+        );
+        let data = package(&[("3D/3dmodel.model", &model)]);
+
+        let err = parse(&data, LIMITS).unwrap_err();
+        assert!(format!("{:#}", err).contains("more than 10000 triangles"), "{:#}", err);
+    }
+
+    #[test]
+    fn test_compose_applies_first_then_second() {
+        let scale: Transform = [2.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0];
+        let shift: Transform = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 10.0, 0.0, 0.0];
+
+        assert_eq!(apply(&compose(scale, shift), [1.0, 1.0, 1.0]), [12.0, 2.0, 2.0]);
+        assert_eq!(apply(&compose(shift, scale), [1.0, 1.0, 1.0]), [22.0, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn test_benchy_fixture() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/fixtures/3DBenchy.3mf");
+        let limits = ParseLimits::from_config(&crate::config::Config::for_tests());
+        let analysis = crate::mesh::load(std::path::Path::new(path), limits).unwrap().analyze();
+
+        let size = analysis.bounding_box.size();
+        assert!((size[0] - 60.0).abs() < 1.0, "unexpected bbox {:?}", size);
+        assert!(analysis.volume_cm3 > 10.0 && analysis.volume_cm3 < 20.0);
+        assert!(analysis.triangle_count > 100_000);
+    }
+}
@@ -0,0 +1,104 @@
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge, HistogramOpts,
+    HistogramVec, IntCounterVec, IntGauge, Opts,
+};
+
+/// Download times, from a local MinIO to a slow remote host
+const DOWNLOAD_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Orca runs take seconds to minutes
+const SLICE_BUCKETS: &[f64] = &[0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+/// Net quote totals in major units of the quote currency
+const TOTAL_BUCKETS: &[f64] = &[
+    10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0, 25000.0, 100000.0,
+];
+
+pub static DOWNLOAD_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        HistogramOpts::new(
+            "pricing_fdm_download_duration_seconds",
+            "Model download duration"
+        )
+        .buckets(DOWNLOAD_BUCKETS.to_vec()),
+        &["outcome"]
+    )
+    .unwrap()
+});
+
+pub static SLICE_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        HistogramOpts::new(
+            "pricing_fdm_slice_duration_seconds",
+            "Slicer run duration (cache misses only)"
+        )
+        .buckets(SLICE_BUCKETS.to_vec()),
+        &["backend", "outcome"]
+    )
+    .unwrap()
+});
+
+pub static QUOTE_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        HistogramOpts::new(
+            "pricing_fdm_quote_duration_seconds",
+            "End-to-end quote duration, including queueing for a slot"
+        )
+        .buckets(SLICE_BUCKETS.to_vec()),
+        &["outcome"]
+    )
+    .unwrap()
+});
+
+pub static QUOTES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        Opts::new("pricing_fdm_quotes_total", "Quote requests by material and outcome"),
+        &["material", "outcome"]
+    )
+    .unwrap()
+});
+
+pub static SLICE_FAILURES: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        Opts::new(
+            "pricing_fdm_slice_failures_total",
+            "Slicing failures by reason (timeout, slicer_error, parse_error)"
+        ),
+        &["backend", "reason"]
+    )
+    .unwrap()
+});
+
+/// Set from the slice pool on every scrape
+pub static SLICES_ACTIVE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("pricing_fdm_slices_active", "Slices currently running").unwrap()
+});
+
+/// Set from the slice pool on every scrape
+pub static SLICES_QUEUED: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("pricing_fdm_slices_queued", "Quotes waiting for a slicing slot").unwrap()
+});
+
+pub static QUOTE_TOTALS: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        HistogramOpts::new(
+            "pricing_fdm_quote_total_net",
+            "Net quote totals in major units of the quote currency"
+        )
+        .buckets(TOTAL_BUCKETS.to_vec()),
+        &["currency", "material"]
+    )
+    .unwrap()
+});
+
+pub static CALIBRATION_REPORTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        Opts::new(
+            "pricing_fdm_calibration_reports_total",
+            "Production reports by machine and outcome (recorded, duplicate, rejected)"
+        ),
+        &["machine", "outcome"]
+    )
+    .unwrap()
+});
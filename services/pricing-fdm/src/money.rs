@@ -0,0 +1,147 @@
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Sub};
+use std::str::FromStr;
+
+/// Currency that rates and rules are written in
+pub const BASE_CURRENCY: Currency = Currency::Usd;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Currency {
+    Pln,
+    Eur,
+    Usd,
+}
+
+impl Currency {
+    pub const ALL: [Currency; 3] = [Currency::Pln, Currency::Eur, Currency::Usd];
+
+    /// ISO 4217 code
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::Pln => "PLN",
+            Currency::Eur => "EUR",
+            Currency::Usd => "USD",
+        }
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Currency::ALL.into_iter().find(|c| c.code().eq_ignore_ascii_case(s.trim())) {
+            Some(c) => Ok(c),
+            None => bail!("Unsupported currency: {}", s),
+        }
+    }
+}
+
+/// Amount in minor units (grosze, cents) of a currency carried alongside it.
+///
+/// Rates and hours are fractional, so amounts are rounded once with `from_major`
+/// and all later arithmetic stays in integers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Money(pub i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    /// Round a major-unit amount to the nearest minor unit (half away from zero)
+    pub fn from_major(amount: f64) -> Money {
+        Money((amount * 100.0).round() as i64)
+    }
+
+    pub fn to_major(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+
+    pub fn times(self, quantity: u32) -> Money {
+        Money(self.0 * quantity as i64)
+    }
+
+    /// Multiply by a factor, rounding to the nearest minor unit
+    pub fn scale(self, factor: f64) -> Money {
+        Money((self.0 as f64 * factor).round() as i64)
+    }
+
+    pub fn percent(self, percent: f64) -> Money {
+        self.scale(percent / 100.0)
+    }
+
+    /// Split into `parts` equal shares, rounding half up
+    pub fn div_round(self, parts: u32) -> Money {
+        let parts = parts as i64;
+        Money((2 * self.0 + parts).div_euclid(2 * parts))
+    }
+
+    /// Split into `parts` equal shares, rounding up so the shares cover the amount
+    pub fn div_ceil(self, parts: u32) -> Money {
+        let parts = parts as i64;
+        Money(-(-self.0).div_euclid(parts))
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, rhs: Money) -> Money {
+        Money(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Money) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, rhs: Money) -> Money {
+        Money(self.0 - rhs.0)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(Money::from_major(12.345), Money(1235));
+        assert_eq!(Money::from_major(0.1 + 0.2), Money(30));
+        assert_eq!(Money(1000).percent(23.0), Money(230));
+        assert_eq!(Money(1001).scale(1.3), Money(1301));
+
+        assert_eq!(Money(1000).div_round(3), Money(333));
+        assert_eq!(Money(1000).div_round(8), Money(125));
+        assert_eq!(Money(1001).div_round(2), Money(501));
+        assert_eq!(Money(1000).div_ceil(3), Money(334));
+        assert_eq!(Money(1500).div_ceil(3), Money(500));
+    }
+
+    #[test]
+    fn test_currency_codes() {
+        assert_eq!("pln".parse::<Currency>().unwrap(), Currency::Pln);
+        assert_eq!(serde_json::to_string(&Currency::Eur).unwrap(), "\"EUR\"");
+        assert!("GBP".parse::<Currency>().is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use crate::app::dto::{FinishingRequest, QuoteResponse};
+use crate::materials::Material;
+use crate::money::{Currency, Money};
+use crate::rules::FinishingUnit;
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{errors::ErrorKind, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What a quote token vouches for: the priced file, the parameters, the breakdown and the expiry.
+///
+/// Signed as an HS256 JWT with `QUOTE_TOKEN_SECRET`. Amounts are in minor units of `currency`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuoteClaims {
+    pub quote_id: Uuid,
+    /// Issued at and expiry, Unix seconds
+    pub iat: i64,
+    pub exp: i64,
+
+    // What was priced
+    pub model_sha256: String,
+    pub file_id: Option<Uuid>,
+    pub material: String,
+    pub infill: u8,
+    pub layer_thickness: u16,
+    pub quantity: u32,
+    pub customer_tier: String,
+    pub rush: String,
+    pub machine_id: String,
+    pub finishing: Vec<FinishingRequest>,
+    /// Sliced project production should print, when one was stored
+    pub artifact_sha256: Option<String>,
+    /// Slicer estimates per copy before calibration; production reports are measured against these
+    pub sliced_print_time_hours: f64,
+    pub sliced_filament_weight_g: f64,
+
+    // Price breakdown
+    pub currency: Currency,
+    pub unit_price_minor: Money,
+    pub total_net_minor: Money,
+    pub vat_minor: Money,
+    pub total_gross_minor: Money,
+    pub material_cost_minor: Money,
+    pub machine_cost_minor: Money,
+    pub support_removal_minor: Money,
+    pub base_fee_minor: Money,
+    pub surcharges_minor: Money,
+    pub finishing_minor: Money,
+    pub discount_minor: Money,
+    pub rush_surcharge_minor: Money,
+    pub minimum_order_adjustment_minor: Money,
+    pub lead_time_days: u8,
+    pub pricing_rules_version: String,
+}
+
+impl QuoteClaims {
+    pub fn new(quote: &QuoteResponse, material: &Material, infill: u8, layer_thickness: u16) -> Self {
+        QuoteClaims {
+            quote_id: quote.quote_id,
+            iat: quote.created_at.timestamp(),
+            exp: quote.expires_at.timestamp(),
+            model_sha256: quote.model_sha256.clone(),
+            file_id: quote.file_id,
+            material: material.id.clone(),
+            infill,
+            layer_thickness,
+            quantity: quote.quantity,
+            customer_tier: quote.customer_tier.clone(),
+            rush: quote.rush.clone(),
+            machine_id: quote.machine.id.clone(),
+            finishing: quote
+                .finishing
+                .iter()
+                .map(|f| FinishingRequest {
+                    id: f.id.clone(),
+                    count: (f.unit == FinishingUnit::PerInsert).then_some(f.units_per_part as u32),
+                })
+                .collect(),
+            artifact_sha256: quote.artifact.as_ref().map(|a| a.sha256.clone()),
+            sliced_print_time_hours: quote.calibration.sliced_print_time_hours,
+            sliced_filament_weight_g: quote.calibration.sliced_filament_weight_g,
+            currency: quote.currency,
+            unit_price_minor: quote.unit_price_minor,
+            total_net_minor: quote.total_net_minor,
+            vat_minor: quote.vat_minor,
+            total_gross_minor: quote.total_gross_minor,
+            material_cost_minor: quote.material_cost_minor,
+            machine_cost_minor: quote.machine_cost_minor,
+            support_removal_minor: quote.support_removal_minor,
+            base_fee_minor: quote.base_fee_minor,
+            surcharges_minor: quote.surcharges.iter().map(|s| s.amount_minor).sum(),
+            finishing_minor: quote.finishing.iter().map(|f| f.amount_minor).sum(),
+            discount_minor: quote.discount_minor,
+            rush_surcharge_minor: quote.rush_surcharge_minor,
+            minimum_order_adjustment_minor: quote.minimum_order_adjustment_minor,
+            lead_time_days: quote.lead_time_days,
+            pricing_rules_version: quote.pricing_rules_version.clone(),
+        }
+    }
+
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(self.exp, 0)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TokenError {
+    #[error("Quote token expired")]
+    Expired,
+    #[error("Invalid quote token: {0}")]
+    Invalid(String),
+}
+
+pub fn sign(claims: &QuoteClaims, secret: &str) -> anyhow::Result<String> {
+    Ok(jsonwebtoken::encode(
+        &Header::new(Algorithm::HS256),
+        claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )?)
+}
+
+/// Check the signature and expiry, returning the signed claims.
+///
+/// Callers should take prices from the claims, never from a relayed copy of the quote.
+pub fn verify(token: &str, secret: &str) -> Result<QuoteClaims, TokenError> {
+    decode(token, secret, true)
+}
+
+/// Check the signature only, for quotes that may have expired since (production reports)
+pub fn verify_signature(token: &str, secret: &str) -> Result<QuoteClaims, TokenError> {
+    decode(token, secret, false)
+}
+
+fn decode(token: &str, secret: &str, validate_exp: bool) -> Result<QuoteClaims, TokenError> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.leeway = 0;
+    validation.validate_exp = validate_exp;
+    validation.set_required_spec_claims(&["exp", "iat"]);
+
+    jsonwebtoken::decode::<QuoteClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .map_err(|e| match e.kind() {
+        ErrorKind::ExpiredSignature => TokenError::Expired,
+        _ => TokenError::Invalid(e.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    fn claims(exp: i64) -> QuoteClaims {
+        QuoteClaims {
+            quote_id: Uuid::new_v4(),
+            iat: Utc::now().timestamp(),
+            exp,
+            model_sha256: "9f86d08".to_string(),
+            file_id: None,
+            material: "pla".to_string(),
+            infill: 20,
+            layer_thickness: 200,
+            quantity: 10,
+            customer_tier: "standard".to_string(),
+            rush: "standard".to_string(),
+            machine_id: "generic-200".to_string(),
+            finishing: vec![FinishingRequest {
+                id: "heat_set_inserts".to_string(),
+                count: Some(4),
+            }],
+            artifact_sha256: None,
+            sliced_print_time_hours: 2.5,
+            sliced_filament_weight_g: 40.0,
+            currency: Currency::Pln,
+            unit_price_minor: Money(1500),
+            total_net_minor: Money(15000),
+            vat_minor: Money(3450),
+            total_gross_minor: Money(18450),
+            material_cost_minor: Money(4000),
+            machine_cost_minor: Money(8000),
+            support_removal_minor: Money::ZERO,
+            base_fee_minor: Money(2000),
+            surcharges_minor: Money::ZERO,
+            finishing_minor: Money(720),
+            discount_minor: Money::ZERO,
+            rush_surcharge_minor: Money::ZERO,
+            minimum_order_adjustment_minor: Money::ZERO,
+            lead_time_days: 5,
+            pricing_rules_version: "2026.10.1".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let claims = claims(Utc::now().timestamp() + 3600);
+        let token = sign(&claims, SECRET).unwrap();
+        assert_eq!(verify(&token, SECRET).unwrap(), claims);
+    }
+
+    #[test]
+    fn test_rejects_tampering_and_wrong_secret() {
+        let token = sign(&claims(Utc::now().timestamp() + 3600), SECRET).unwrap();
+
+        // Swap the payload for one with a lower price, keeping the signature
+        let mut cheaper = claims(Utc::now().timestamp() + 3600);
+        cheaper.total_gross_minor = Money(100);
+        let forged = sign(&cheaper, "another-secret-another-secret-xx").unwrap();
+        let parts: Vec<&str> = token.split('.').collect();
+        let forged_parts: Vec<&str> = forged.split('.').collect();
+        let tampered = format!("{}.{}.{}", parts[0], forged_parts[1], parts[2]);
+
+        assert!(matches!(verify(&tampered, SECRET), Err(TokenError::Invalid(_))));
+        assert!(matches!(verify(&forged, SECRET), Err(TokenError::Invalid(_))));
+        assert!(matches!(verify("not-a-token", SECRET), Err(TokenError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_rejects_expired_token() {
+        let token = sign(&claims(Utc::now().timestamp() - 1), SECRET).unwrap();
+        assert!(matches!(verify(&token, SECRET), Err(TokenError::Expired)));
+        assert!(verify_signature(&token, SECRET).is_ok());
+        assert!(matches!(
+            verify_signature(&token, "another-secret-another-secret-xx"),
+            Err(TokenError::Invalid(_))
+        ));
+    }
+}
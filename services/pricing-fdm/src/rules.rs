@@ -0,0 +1,456 @@
+use crate::config::Config;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{error, info};
+
+/// Rules file name inside `orca_profiles_dir`
+pub const RULES_FILE: &str = "pricing.toml";
+
+/// Tier used when a request does not name one
+pub const DEFAULT_TIER: &str = "standard";
+
+/// Versioned pricing rules loaded from `pricing.toml`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PricingRules {
+    /// Recorded in every quote
+    pub version: String,
+    pub base_fee_usd: f64,
+    /// Hourly rate for machines without their own
+    pub machine_rate_usd_per_hour: f64,
+    pub support_removal_usd_per_g: f64,
+    /// Heat-up and bed clearing per build plate
+    pub plate_setup_minutes: f64,
+    /// Orders below this total are raised to it
+    pub minimum_order_usd: f64,
+    /// VAT added to the net price (Polish standard rate)
+    pub vat_percent: f64,
+    /// Keyed by machine id from machines.json
+    #[serde(default)]
+    pub machines: HashMap<String, MachineRules>,
+    /// Keyed by material id; a material's rate wins over the machine's
+    #[serde(default)]
+    pub materials: HashMap<String, MaterialRules>,
+    pub customer_tiers: HashMap<String, CustomerTier>,
+    pub rush_tiers: HashMap<String, RushTier>,
+    #[serde(default)]
+    pub surcharges: Vec<Surcharge>,
+    #[serde(default)]
+    pub quantity_discounts: Vec<QuantityBreak>,
+    /// Post-processing add-ons a request can pick, keyed by option id
+    #[serde(default)]
+    pub finishing: HashMap<String, FinishingRules>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MachineRules {
+    pub machine_rate_usd_per_hour: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaterialRules {
+    pub machine_rate_usd_per_hour: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomerTier {
+    pub margin_multiplier: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RushTier {
+    pub surcharge_percent: f64,
+    /// Subtracted from the estimated lead time (never below one day)
+    pub lead_time_days_saved: u8,
+}
+
+/// Part property a surcharge is triggered by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SurchargeMetric {
+    MaxDimensionMm,
+    VolumeCm3,
+    TriangleCount,
+    ShellCount,
+    /// Support material as a percentage of total filament weight
+    SupportPercent,
+}
+
+/// Size/complexity surcharge, applied when the metric exceeds `threshold`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Surcharge {
+    pub name: String,
+    pub metric: SurchargeMetric,
+    pub threshold: f64,
+    /// Percentage of the subtotal
+    pub percent: Option<f64>,
+    /// Flat amount per order
+    pub flat_usd: Option<f64>,
+}
+
+/// What a finishing option is charged per
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinishingUnit {
+    /// Per cm² of model surface (mesh analysis)
+    PerCm2,
+    PerPart,
+    /// Per insert; the request gives the count per part
+    PerInsert,
+}
+
+/// Post-processing add-on, priced per copy and added before margin
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FinishingRules {
+    pub display_name: String,
+    pub unit: FinishingUnit,
+    pub price_usd: f64,
+    /// Added to the lead time when the option is chosen
+    pub lead_time_days: u8,
+    /// Material ids the option is offered for; empty means every material
+    #[serde(default)]
+    pub materials: Vec<String>,
+}
+
+impl FinishingRules {
+    pub fn supports(&self, material_id: &str) -> bool {
+        self.materials.is_empty() || self.materials.iter().any(|m| m.eq_ignore_ascii_case(material_id))
+    }
+}
+
+/// Discount applied from `min_quantity` copies upwards
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuantityBreak {
+    pub min_quantity: u32,
+    pub discount_percent: f64,
+}
+
+impl PricingRules {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read pricing rules: {:?}", path))?;
+        Self::parse(&content).with_context(|| format!("Invalid pricing rules: {:?}", path))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut rules: PricingRules = toml::from_str(content)?;
+        rules.quantity_discounts.sort_by_key(|b| b.min_quantity);
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    /// Hourly rate: the material's override, else the machine's rate, else the default
+    pub fn machine_rate(&self, machine_id: &str, material_id: &str) -> f64 {
+        let machine_rate = self
+            .machines
+            .iter()
+            .find(|(id, _)| id.eq_ignore_ascii_case(machine_id))
+            .map(|(_, m)| m.machine_rate_usd_per_hour);
+        self.materials
+            .iter()
+            .find(|(id, _)| id.eq_ignore_ascii_case(material_id))
+            .and_then(|(_, m)| m.machine_rate_usd_per_hour)
+            .or(machine_rate)
+            .unwrap_or(self.machine_rate_usd_per_hour)
+    }
+
+    pub fn customer_tier(&self, tier: &str) -> Option<&CustomerTier> {
+        self.customer_tiers.get(&tier.to_lowercase())
+    }
+
+    pub fn rush_tier(&self, tier: &str) -> Option<&RushTier> {
+        self.rush_tiers.get(&tier.to_lowercase())
+    }
+
+    pub fn finishing(&self, id: &str) -> Option<&FinishingRules> {
+        self.finishing.get(&id.to_lowercase())
+    }
+
+    /// Largest discount whose quantity break is reached
+    pub fn quantity_discount(&self, quantity: u32) -> f64 {
+        self.quantity_discounts
+            .iter()
+            .rev()
+            .find(|b| quantity >= b.min_quantity)
+            .map_or(0.0, |b| b.discount_percent)
+    }
+
+    /// Check that overrides refer to catalog materials and registered machines
+    pub fn check_references(&self, config: &Config) -> Result<()> {
+        for id in self.materials.keys() {
+            if config.materials.get(id).is_none() {
+                bail!("Pricing rules reference unknown material: {}", id);
+            }
+        }
+        for id in self.machines.keys() {
+            if config.machines.get(id).is_none() {
+                bail!("Pricing rules reference unknown machine: {}", id);
+            }
+        }
+        for (id, option) in &self.finishing {
+            for material in &option.materials {
+                if config.materials.get(material).is_none() {
+                    bail!("Finishing option {} references unknown material: {}", id, material);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.version.trim().is_empty() {
+            bail!("version cannot be empty");
+        }
+
+        for (name, value) in [
+            ("base_fee_usd", self.base_fee_usd),
+            ("machine_rate_usd_per_hour", self.machine_rate_usd_per_hour),
+            ("support_removal_usd_per_g", self.support_removal_usd_per_g),
+            ("plate_setup_minutes", self.plate_setup_minutes),
+            ("minimum_order_usd", self.minimum_order_usd),
+        ] {
+            if !non_negative(value) {
+                bail!("{} must be a non-negative number", name);
+            }
+        }
+
+        if !(0.0..100.0).contains(&self.vat_percent) {
+            bail!("vat_percent must be between 0 and 100");
+        }
+
+        for (id, machine) in &self.machines {
+            if !non_negative(machine.machine_rate_usd_per_hour) {
+                bail!("machines.{}.machine_rate_usd_per_hour must be non-negative", id);
+            }
+        }
+
+        for (id, material) in &self.materials {
+            if material.machine_rate_usd_per_hour.is_some_and(|r| !non_negative(r)) {
+                bail!("materials.{}.machine_rate_usd_per_hour must be non-negative", id);
+            }
+        }
+
+        if !self.customer_tiers.contains_key(DEFAULT_TIER) {
+            bail!("customer_tiers must define a \"{}\" tier", DEFAULT_TIER);
+        }
+        for (id, tier) in &self.customer_tiers {
+            if !non_negative(tier.margin_multiplier - 1.0) {
+                bail!("customer_tiers.{}.margin_multiplier must be at least 1.0", id);
+            }
+        }
+
+        if !self.rush_tiers.contains_key(DEFAULT_TIER) {
+            bail!("rush_tiers must define a \"{}\" tier", DEFAULT_TIER);
+        }
+        for (id, tier) in &self.rush_tiers {
+            if !non_negative(tier.surcharge_percent) {
+                bail!("rush_tiers.{}.surcharge_percent must be non-negative", id);
+            }
+        }
+
+        for s in &self.surcharges {
+            match (s.percent, s.flat_usd) {
+                (Some(v), None) | (None, Some(v)) if non_negative(v) => {}
+                _ => bail!(
+                    "Surcharge '{}' must set exactly one non-negative percent or flat_usd",
+                    s.name
+                ),
+            }
+        }
+
+        let mut previous = 0;
+        for b in &self.quantity_discounts {
+            if b.min_quantity == 0 || b.min_quantity == previous {
+                bail!("Quantity breaks must have distinct, positive min_quantity values");
+            }
+            if !(0.0..100.0).contains(&b.discount_percent) {
+                bail!("Quantity break discount must be between 0 and 100 percent");
+            }
+            previous = b.min_quantity;
+        }
+
+        for (id, option) in &self.finishing {
+            if id.to_lowercase() != *id {
+                bail!("Finishing option id must be lowercase: {}", id);
+            }
+            if !non_negative(option.price_usd) {
+                bail!("finishing.{}.price_usd must be non-negative", id);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Current pricing rules, swapped in place when the file changes
+#[derive(Clone)]
+pub struct RulesStore {
+    path: Option<PathBuf>,
+    current: Arc<RwLock<Arc<PricingRules>>>,
+    modified: Arc<RwLock<Option<SystemTime>>>,
+}
+
+impl RulesStore {
+    /// Load and validate the rules file; errors here should stop startup
+    pub fn load(path: impl Into<PathBuf>, config: &Config) -> Result<Self> {
+        let path = path.into();
+        let rules = PricingRules::load(&path)?;
+        rules.check_references(config)?;
+        info!("Loaded pricing rules version {}", rules.version);
+
+        Ok(RulesStore {
+            modified: Arc::new(RwLock::new(modified_time(&path))),
+            current: Arc::new(RwLock::new(Arc::new(rules))),
+            path: Some(path),
+        })
+    }
+
+    /// Fixed rules that are never reloaded
+    pub fn from_rules(rules: PricingRules) -> Self {
+        RulesStore {
+            path: None,
+            current: Arc::new(RwLock::new(Arc::new(rules))),
+            modified: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    pub fn current(&self) -> Arc<PricingRules> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Reload when the file's mtime changed. Invalid files are rejected and
+    /// the previous rules stay active.
+    pub fn reload_if_changed(&self, config: &Config) -> Result<bool> {
+        let Some(path) = &self.path else {
+            return Ok(false);
+        };
+
+        let modified = modified_time(path);
+        if modified == *self.modified.read().unwrap() {
+            return Ok(false);
+        }
+        // Record the attempt so a broken file is reported once, not on every poll
+        *self.modified.write().unwrap() = modified;
+
+        let rules = PricingRules::load(path)?;
+        rules.check_references(config)?;
+        info!("Reloaded pricing rules version {}", rules.version);
+        *self.current.write().unwrap() = Arc::new(rules);
+        Ok(true)
+    }
+
+    /// Poll the rules file for changes in the background
+    pub fn spawn_watcher(&self, config: Config, interval: Duration) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = store.reload_if_changed(&config) {
+                    error!("Keeping previous pricing rules: {:#}", e);
+                }
+            }
+        });
+    }
+}
+
+/// Also rejects NaN and infinity, which TOML allows
+fn non_negative(value: f64) -> bool {
+    value.is_finite() && value >= 0.0
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn bundled_path() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("profiles").join(RULES_FILE)
+    }
+
+    pub fn bundled() -> PricingRules {
+        PricingRules::load(&bundled_path()).unwrap()
+    }
+
+    #[test]
+    fn test_bundled_rules_load() {
+        let rules = bundled();
+        rules.check_references(&Config::for_tests()).unwrap();
+
+        assert_eq!(rules.machine_rate("enclosed-256", "pc"), 12.0);
+        assert_eq!(rules.machine_rate("Large-350", "pla"), 16.0);
+        // Material override wins over the machine's rate
+        assert_eq!(rules.machine_rate("generic-200", "TPU"), 13.0);
+        assert_eq!(rules.machine_rate("retired", "pla"), rules.machine_rate_usd_per_hour);
+        assert_eq!(rules.quantity_discount(9), 0.0);
+        assert_eq!(rules.quantity_discount(60), 10.0);
+
+        let smoothing = rules.finishing("Vapor_Smoothing").unwrap();
+        assert_eq!(smoothing.unit, FinishingUnit::PerPart);
+        assert!(smoothing.supports("ABS"));
+        assert!(!smoothing.supports("pla"));
+        assert!(rules.finishing("sanding").unwrap().supports("pla"));
+    }
+
+    #[test]
+    fn test_rejects_invalid_rules() {
+        let base = std::fs::read_to_string(bundled_path()).unwrap();
+
+        let no_default_tier = base.replace("[customer_tiers.standard]", "[customer_tiers.retail]");
+        assert!(PricingRules::parse(&no_default_tier).is_err());
+
+        let both = base.replace("threshold = 150\npercent = 10\n", "threshold = 150\npercent = 10\nflat_usd = 1.0\n");
+        assert!(PricingRules::parse(&both).is_err());
+
+        let typo = base.replace("minimum_order_usd", "minimum_order");
+        assert!(PricingRules::parse(&typo).is_err());
+
+        let unknown_unit = base.replace("unit = \"per_insert\"", "unit = \"per_hole\"");
+        assert!(PricingRules::parse(&unknown_unit).is_err());
+    }
+
+    #[test]
+    fn test_reload_keeps_previous_rules_when_invalid() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(RULES_FILE);
+        let base = std::fs::read_to_string(bundled_path()).unwrap();
+        std::fs::write(&path, &base).unwrap();
+        let config = Config::for_tests();
+        let store = RulesStore::load(&path, &config).unwrap();
+
+        let later = SystemTime::now() + Duration::from_secs(5);
+        let write = |content: &str, mtime: SystemTime| {
+            std::fs::write(&path, content).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(mtime)
+                .unwrap();
+        };
+
+        write(&base.replace("2026.10.1", "2026.10.2"), later);
+        assert!(store.reload_if_changed(&config).unwrap());
+        assert_eq!(store.current().version, "2026.10.2");
+
+        write("version = \"broken\"", later + Duration::from_secs(5));
+        assert!(store.reload_if_changed(&config).is_err());
+        assert_eq!(store.current().version, "2026.10.2");
+        assert!(!store.reload_if_changed(&config).unwrap());
+    }
+}
@@ -0,0 +1,274 @@
+use super::{SliceMetrics, SliceSettings};
+use crate::config::Config;
+use crate::utils::hash;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Bump when `SliceMetrics` or metric extraction changes so stale entries are ignored
+const CACHE_FORMAT_VERSION: u32 = 5;
+
+/// On-disk cache of slicing results, keyed by model hash and slicing inputs.
+///
+/// Entries are small JSON files, plus the sliced project when artifacts are stored.
+/// When the directory grows past `max_bytes` the least recently used files are removed.
+#[derive(Clone)]
+pub struct SliceCache {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl SliceCache {
+    /// `max_bytes == 0` disables the cache
+    pub fn new(dir: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        SliceCache {
+            dir: dir.into(),
+            max_bytes,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_bytes > 0
+    }
+
+    /// Cache key covering the backend, model, machine, process parameters and profile versions.
+    ///
+    /// Profile versions are content hashes, so editing a profile invalidates old entries.
+    pub async fn key(
+        &self,
+        backend: &str,
+        model_sha256: &str,
+        settings: &SliceSettings<'_>,
+        config: &Config,
+    ) -> Result<String> {
+        let profiles_dir = Path::new(&config.orca_profiles_dir);
+        let filament_profile = config
+            .materials
+            .filament_profile_path(settings.material, &config.orca_profiles_dir)?;
+
+        let mut profile_hashes = Vec::new();
+        for path in [
+            settings.machine.profile_path.clone(),
+            profiles_dir.join("process_standard.json"),
+            filament_profile,
+        ] {
+            let content = tokio::fs::read(&path)
+                .await
+                .with_context(|| format!("Failed to read profile: {:?}", path))?;
+            profile_hashes.push(hash::sha256_hex(&content));
+        }
+
+        let input = format!(
+            "v{}|{}|{}|{}|{}|{}|{}|{}",
+            CACHE_FORMAT_VERSION,
+            backend,
+            model_sha256,
+            settings.machine.id.to_lowercase(),
+            settings.material.id.to_lowercase(),
+            settings.infill,
+            settings.layer_thickness_um,
+            profile_hashes.join("|")
+        );
+        Ok(hash::sha256_hex(input.as_bytes()))
+    }
+
+    pub async fn get(&self, key: &str) -> Option<SliceMetrics> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let path = self.entry_path(key);
+        let content = tokio::fs::read(&path).await.ok()?;
+        let metrics = match serde_json::from_slice(&content) {
+            Ok(m) => m,
+            Err(e) => {
+                warn!("Dropping corrupt cache entry {:?}: {}", path, e);
+                let _ = tokio::fs::remove_file(&path).await;
+                return None;
+            }
+        };
+
+        touch(&path).await;
+        debug!("Slice cache hit: {}", key);
+        Some(metrics)
+    }
+
+    /// Copy the cached sliced project for `key` to `dest`, if there is one
+    pub async fn get_project(&self, key: &str, dest: &Path) -> Option<PathBuf> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let path = self.project_path(key);
+        tokio::fs::copy(&path, dest).await.ok()?;
+        touch(&path).await;
+        Some(dest.to_path_buf())
+    }
+
+    pub async fn put(&self, key: &str, metrics: &SliceMetrics) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .context("Failed to create slice cache directory")?;
+
+        // Write to a temp file and rename, so readers never see partial entries
+        let tmp_path = self.dir.join(format!(".{}.tmp", Uuid::new_v4()));
+        tokio::fs::write(&tmp_path, serde_json::to_vec(metrics)?).await?;
+        tokio::fs::rename(&tmp_path, self.entry_path(key)).await?;
+
+        self.evict().await
+    }
+
+    /// Keep a copy of the sliced project next to the metrics of `key`
+    pub async fn put_project(&self, key: &str, project: &Path) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .context("Failed to create slice cache directory")?;
+
+        let tmp_path = self.dir.join(format!(".{}.tmp", Uuid::new_v4()));
+        tokio::fs::copy(project, &tmp_path).await?;
+        tokio::fs::rename(&tmp_path, self.project_path(key)).await?;
+
+        self.evict().await
+    }
+
+    /// Remove least recently used entries until the cache fits in `max_bytes`
+    async fn evict(&self) -> Result<()> {
+        let mut entries = Vec::new();
+        let mut total: u64 = 0;
+
+        let mut dir = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            let path = entry.path();
+            if !matches!(path.extension().and_then(|e| e.to_str()), Some("json" | "3mf")) {
+                continue;
+            }
+            let meta = entry.metadata().await?;
+            total += meta.len();
+            entries.push((meta.modified().unwrap_or(SystemTime::UNIX_EPOCH), meta.len(), path));
+        }
+
+        if total <= self.max_bytes {
+            return Ok(());
+        }
+
+        entries.sort_by_key(|(modified, _, _)| *modified);
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            if tokio::fs::remove_file(&path).await.is_ok() {
+                total = total.saturating_sub(len);
+                debug!("Evicted slice cache entry {:?}", path);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    fn project_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.3mf", key))
+    }
+}
+
+/// Refresh mtime so eviction treats the file as recently used
+async fn touch(path: &Path) {
+    let path = path.to_path_buf();
+    let _ = tokio::task::spawn_blocking(move || {
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|f| f.set_modified(SystemTime::now()))
+    })
+    .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(hours: f64) -> SliceMetrics {
+        SliceMetrics {
+            print_time_hours: hours,
+            filament_weight_g: 10.0,
+            filament_length_mm: 3300.0,
+            filament_volume_cm3: 8.0,
+            features: Vec::new(),
+            plates: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = SliceCache::new(dir.path(), 1024 * 1024);
+
+        assert!(cache.get("abc").await.is_none());
+        cache.put("abc", &metrics(1.5)).await.unwrap();
+
+        let hit = cache.get("abc").await.unwrap();
+        assert_eq!(hit.print_time_hours, 1.5);
+    }
+
+    #[tokio::test]
+    async fn test_project_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = SliceCache::new(dir.path().join("cache"), 1024 * 1024);
+        let project = dir.path().join("model.sliced.3mf");
+        std::fs::write(&project, b"PK sliced").unwrap();
+
+        let dest = dir.path().join("copy.3mf");
+        assert!(cache.get_project("abc", &dest).await.is_none());
+        cache.put_project("abc", &project).await.unwrap();
+
+        assert_eq!(cache.get_project("abc", &dest).await, Some(dest.clone()));
+        assert_eq!(std::fs::read(&dest).unwrap(), b"PK sliced");
+    }
+
+    #[tokio::test]
+    async fn test_disabled_cache_stores_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = SliceCache::new(dir.path().join("cache"), 0);
+
+        cache.put("abc", &metrics(1.0)).await.unwrap();
+        assert!(cache.get("abc").await.is_none());
+        assert!(!dir.path().join("cache").exists());
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry_len = serde_json::to_vec(&metrics(1.0)).unwrap().len() as u64;
+        let cache = SliceCache::new(dir.path(), entry_len * 2);
+
+        cache.put("first", &metrics(1.0)).await.unwrap();
+        cache.put("second", &metrics(1.0)).await.unwrap();
+
+        // Make "first" the most recently used, then overflow the cache
+        let old = SystemTime::now() - std::time::Duration::from_secs(60);
+        std::fs::File::options()
+            .write(true)
+            .open(dir.path().join("second.json"))
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        cache.put("third", &metrics(1.0)).await.unwrap();
+
+        assert!(cache.get("first").await.is_some());
+        assert!(cache.get("second").await.is_none());
+        assert!(cache.get("third").await.is_some());
+    }
+}
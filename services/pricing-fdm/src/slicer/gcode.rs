@@ -0,0 +1,285 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Feature name for moves before the first `; FEATURE:` marker (start G-code)
+const UNTAGGED_FEATURE: &str = "Custom";
+
+/// Per-feature totals from one pass over the G-code
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeatureStats {
+    /// Filament pushed into the nozzle (net of retractions)
+    pub filament_mm: f64,
+    /// Naive move time (distance / feedrate, no acceleration)
+    pub time_secs: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GcodeAnalysis {
+    pub features: BTreeMap<String, FeatureStats>,
+    /// Distinct Z heights that received extrusion
+    pub layer_count: u32,
+    /// Highest Z with extrusion (part height on the plate)
+    pub max_z_mm: f64,
+    /// Non-extruding XYZ moves
+    pub travel_mm: f64,
+    pub travel_time_secs: f64,
+}
+
+impl GcodeAnalysis {
+    /// Total naive time, used to scale per-feature times to the slicer's estimate
+    pub fn total_time_secs(&self) -> f64 {
+        self.features.values().map(|f| f.time_secs).sum::<f64>() + self.travel_time_secs
+    }
+}
+
+struct Machine {
+    pos: [f64; 4],
+    absolute_xyz: bool,
+    absolute_e: bool,
+    /// mm/min
+    feedrate: f64,
+}
+
+/// Walk the G-code, aggregating extrusion and time by Orca's `; FEATURE:` blocks.
+///
+/// Handles G0/G1 moves, G2/G3 arcs (I/J form), G4 dwells, G90/G91, M82/M83 and G92.
+/// PrusaSlicer-style `;TYPE:` markers are accepted as well.
+pub fn analyze(gcode: &str) -> GcodeAnalysis {
+    let mut analysis = GcodeAnalysis::default();
+    let mut machine = Machine {
+        pos: [0.0; 4],
+        absolute_xyz: true,
+        absolute_e: true,
+        feedrate: 0.0,
+    };
+    let mut feature = UNTAGGED_FEATURE.to_string();
+    let mut layer_heights: BTreeSet<i64> = BTreeSet::new();
+
+    for raw in gcode.lines() {
+        let line = raw.trim();
+        if let Some(comment) = line.strip_prefix(';') {
+            let comment = comment.trim_start();
+            if let Some(name) = comment
+                .strip_prefix("FEATURE:")
+                .or_else(|| comment.strip_prefix("TYPE:"))
+            {
+                feature = name.trim().to_string();
+            }
+            continue;
+        }
+
+        let code = line.split(';').next().unwrap_or("").trim();
+        let mut words = code.split_whitespace();
+        let Some(command) = words.next() else {
+            continue;
+        };
+        let params = words.filter_map(|w| {
+            let mut chars = w.chars();
+            let letter = chars.next()?.to_ascii_uppercase();
+            Some((letter, chars.as_str().parse::<f64>().ok()?))
+        });
+
+        let command = command.to_ascii_uppercase();
+        match command.as_str() {
+            "G0" | "G1" | "G2" | "G3" => {
+                let arc = match command.as_str() {
+                    "G2" => Some(true),
+                    "G3" => Some(false),
+                    _ => None,
+                };
+                let start = machine.pos;
+                let mut target = start;
+                let mut center_offset = [0.0; 2];
+
+                for (letter, value) in params {
+                    match letter {
+                        'X' | 'Y' | 'Z' => {
+                            let axis = (letter as u8 - b'X') as usize;
+                            target[axis] = if machine.absolute_xyz { value } else { start[axis] + value };
+                        }
+                        'E' => target[3] = if machine.absolute_e { value } else { start[3] + value },
+                        'F' => machine.feedrate = value,
+                        'I' => center_offset[0] = value,
+                        'J' => center_offset[1] = value,
+                        _ => {}
+                    }
+                }
+
+                let xy = match arc {
+                    Some(clockwise) => arc_length(start, target, center_offset, clockwise),
+                    None => (target[0] - start[0]).hypot(target[1] - start[1]),
+                };
+                let dz = target[2] - start[2];
+                let de = target[3] - start[3];
+                let distance = xy.hypot(dz);
+                let time = move_time(distance.max(de.abs()), machine.feedrate);
+
+                // Retractions count against the current feature even while moving (wipe), so
+                // its filament stays net of them
+                if de != 0.0 {
+                    analysis.features.entry(feature.clone()).or_default().filament_mm += de;
+                }
+
+                // Extruding while only Z moves (vase mode, spiral lifts) is still printing
+                if de > 0.0 && distance > 0.0 {
+                    let stats = analysis.features.entry(feature.clone()).or_default();
+                    stats.time_secs += time;
+
+                    // Quantize to microns so float noise doesn't split layers
+                    layer_heights.insert((target[2] * 1000.0).round() as i64);
+                    analysis.max_z_mm = analysis.max_z_mm.max(target[2]);
+                } else if distance > 0.0 {
+                    analysis.travel_mm += distance;
+                    analysis.travel_time_secs += time;
+                } else {
+                    // Retract/unretract in place
+                    analysis.features.entry(feature.clone()).or_default().time_secs += time;
+                }
+
+                machine.pos = target;
+            }
+            "G4" => {
+                let mut dwell = 0.0;
+                for (letter, value) in params {
+                    match letter {
+                        'P' => dwell = value / 1000.0,
+                        'S' => dwell = value,
+                        _ => {}
+                    }
+                }
+                analysis.features.entry(feature.clone()).or_default().time_secs += dwell;
+            }
+            "G90" => {
+                machine.absolute_xyz = true;
+                machine.absolute_e = true;
+            }
+            "G91" => {
+                machine.absolute_xyz = false;
+                machine.absolute_e = false;
+            }
+            "M82" => machine.absolute_e = true,
+            "M83" => machine.absolute_e = false,
+            "G92" => {
+                for (letter, value) in params {
+                    match letter {
+                        'X' | 'Y' | 'Z' => machine.pos[(letter as u8 - b'X') as usize] = value,
+                        'E' => machine.pos[3] = value,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    analysis.layer_count = layer_heights.len() as u32;
+    analysis
+}
+
+fn move_time(distance: f64, feedrate_mm_min: f64) -> f64 {
+    if feedrate_mm_min > 0.0 {
+        distance / (feedrate_mm_min / 60.0)
+    } else {
+        0.0
+    }
+}
+
+/// XY length of a G2 (clockwise) or G3 arc with center at start + (I, J)
+fn arc_length(start: [f64; 4], end: [f64; 4], offset: [f64; 2], clockwise: bool) -> f64 {
+    let center = [start[0] + offset[0], start[1] + offset[1]];
+    let radius = offset[0].hypot(offset[1]);
+    let a0 = (start[1] - center[1]).atan2(start[0] - center[0]);
+    let a1 = (end[1] - center[1]).atan2(end[0] - center[0]);
+
+    let mut sweep = if clockwise { a0 - a1 } else { a1 - a0 };
+    if sweep <= 0.0 {
+        sweep += std::f64::consts::TAU;
+    }
+    radius * sweep
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GCODE: &str = "\
+G90
+M83
+G1 Z0.2 F600
+; FEATURE: Outer wall
+G1 X10 Y0 E1.0 F1200
+G1 X10 Y10 E1.0
+G1 E-0.8 F2400 ; retract
+G0 X20 Y10 F6000
+G1 E0.8 F2400
+; FEATURE: Support
+G1 X30 Y10 E0.5 F1200
+;LAYER_CHANGE
+G1 Z0.4 F600
+; FEATURE: Outer wall
+G1 X30 Y20 E1.0 F1200
+G92 E0
+G4 P500
+";
+
+    #[test]
+    fn test_feature_breakdown() {
+        let analysis = analyze(GCODE);
+        let wall = &analysis.features["Outer wall"];
+        let support = &analysis.features["Support"];
+
+        // 3 x 1.0 extruded, retraction and unretraction cancel
+        assert!((wall.filament_mm - 3.0).abs() < 1e-9);
+        assert!((support.filament_mm - 0.5).abs() < 1e-9);
+        // 30mm of walls at 20mm/s, two 0.8mm E moves at 40mm/s, 0.5s dwell
+        assert!((wall.time_secs - (1.5 + 0.04 + 0.5)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_layers_height_and_travel() {
+        let analysis = analyze(GCODE);
+
+        assert_eq!(analysis.layer_count, 2);
+        assert!((analysis.max_z_mm - 0.4).abs() < 1e-9);
+        // Initial Z lift, 10mm travel, Z hop to the second layer
+        assert!((analysis.travel_mm - (0.2 + 10.0 + 0.2)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_absolute_extrusion_and_g92_reset() {
+        let gcode = "M82\nG1 X10 E2 F600\nG92 E0\nG1 X20 E2\nG1 X30 E1.5\n";
+        let analysis = analyze(gcode);
+
+        // 2 + 2 extruded, then 0.5 retracted while moving (travel, but still net filament)
+        assert!((analysis.features[UNTAGGED_FEATURE].filament_mm - 3.5).abs() < 1e-9);
+        assert!((analysis.travel_mm - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_wipe_retraction_is_net_of_the_unretract() {
+        let gcode = "M83\n; FEATURE: Outer wall\nG1 X10 E1 F600\nG1 X15 E-0.8\nG1 E0.8\n";
+        let analysis = analyze(gcode);
+
+        assert!((analysis.features["Outer wall"].filament_mm - 1.0).abs() < 1e-9);
+        assert!((analysis.travel_mm - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_z_only_extrusion_is_printing() {
+        let gcode = "M83\n; FEATURE: Outer wall\nG1 X10 E1 F600\nG1 Z0.5 E0.1\n";
+        let analysis = analyze(gcode);
+
+        let wall = &analysis.features["Outer wall"];
+        assert!((wall.filament_mm - 1.1).abs() < 1e-9);
+        // 10.5mm at 10mm/s, none of it travel
+        assert!((wall.time_secs - 1.05).abs() < 1e-9);
+        assert_eq!(analysis.travel_mm, 0.0);
+        assert!((analysis.max_z_mm - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_arc_length() {
+        // Counter-clockwise half circle of radius 10
+        let length = arc_length([10.0, 0.0, 0.0, 0.0], [-10.0, 0.0, 0.0, 0.0], [-10.0, 0.0], false);
+        assert!((length - std::f64::consts::PI * 10.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,161 @@
+use super::{
+    FeatureMetrics, PlateMetrics, SliceError, SliceMetrics, SliceOutput, SliceSettings, Slicer,
+};
+use crate::config::Config;
+use crate::materials::Material;
+use crate::mesh;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::Path;
+
+/// Wall and top/bottom shell thickness assumed by the mesh-derived estimate
+const SHELL_THICKNESS_MM: f64 = 1.2;
+/// Sustained volumetric flow of a 0.4mm nozzle
+const VOLUMETRIC_FLOW_MM3_PER_S: f64 = 8.0;
+/// Travel, retraction and Z move overhead per layer
+const LAYER_OVERHEAD_SECS: f64 = 3.0;
+
+/// Deterministic slicer for tests and CI boxes without Orca.
+///
+/// Returns either canned metrics from a JSON fixture or an estimate derived
+/// from the mesh volume, surface area and height. Produces no sliced project.
+pub struct MockSlicer {
+    canned: Option<SliceMetrics>,
+}
+
+impl MockSlicer {
+    pub fn mesh_derived() -> Self {
+        MockSlicer { canned: None }
+    }
+
+    pub fn canned(metrics: SliceMetrics) -> Self {
+        MockSlicer {
+            canned: Some(metrics),
+        }
+    }
+
+    /// Load canned metrics from a `SliceMetrics` JSON file
+    pub fn from_fixture(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read slicer fixture: {:?}", path))?;
+        let metrics = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid slicer fixture: {:?}", path))?;
+        Ok(Self::canned(metrics))
+    }
+}
+
+#[async_trait]
+impl Slicer for MockSlicer {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn slice(
+        &self,
+        model_path: &Path,
+        settings: &SliceSettings<'_>,
+        config: &Config,
+    ) -> Result<SliceOutput, SliceError> {
+        if let Some(metrics) = &self.canned {
+            return Ok(SliceOutput {
+                metrics: metrics.clone(),
+                project_path: None,
+            });
+        }
+
+        let limits = mesh::ParseLimits::from_config(config);
+        let analysis = mesh::analyze_file(model_path, limits).await?;
+        let object = model_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Ok(SliceOutput {
+            metrics: estimate(
+                &analysis,
+                settings.material,
+                settings.infill,
+                settings.layer_height_mm(),
+                object,
+            ),
+            project_path: None,
+        })
+    }
+}
+
+fn estimate(
+    analysis: &mesh::MeshAnalysis,
+    material: &Material,
+    infill: u8,
+    layer_height: f32,
+    object: String,
+) -> SliceMetrics {
+    let volume_mm3 = analysis.volume_cm3 * 1000.0;
+    let shell_mm3 = (analysis.surface_area_cm2 * 100.0 * SHELL_THICKNESS_MM).min(volume_mm3);
+    let infill_mm3 = (volume_mm3 - shell_mm3) * infill as f64 / 100.0;
+
+    let height_mm = analysis.bounding_box.size()[2];
+    let layer_count = (height_mm / layer_height as f64).ceil() as u32;
+    let overhead_secs = layer_count as f64 * LAYER_OVERHEAD_SECS;
+
+    let feature = |name: &str, mm3: f64, secs: f64| FeatureMetrics {
+        feature: name.to_string(),
+        print_time_hours: secs / 3600.0,
+        filament_length_mm: mm3 / 1000.0 / material.filament_area_cm2() * 10.0,
+        filament_weight_g: mm3 / 1000.0 * material.density_g_cm3,
+    };
+    let features = vec![
+        feature("Outer wall", shell_mm3, shell_mm3 / VOLUMETRIC_FLOW_MM3_PER_S),
+        feature("Sparse infill", infill_mm3, infill_mm3 / VOLUMETRIC_FLOW_MM3_PER_S),
+        feature("Travel", 0.0, overhead_secs),
+    ];
+
+    let plate = PlateMetrics {
+        plate: 1,
+        print_time_hours: features.iter().map(|f| f.print_time_hours).sum(),
+        filament_weight_g: features.iter().map(|f| f.filament_weight_g).sum(),
+        filament_length_mm: features.iter().map(|f| f.filament_length_mm).sum(),
+        filament_volume_cm3: (shell_mm3 + infill_mm3) / 1000.0,
+        objects: vec![object],
+        layer_count,
+        max_z_mm: height_mm,
+        travel_mm: 0.0,
+        features,
+    };
+
+    SliceMetrics::from_plates(vec![plate])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::{tests::cube, MeshBuilder};
+
+    #[test]
+    fn test_estimate_is_deterministic_and_scales_with_infill() {
+        let mut builder = MeshBuilder::default();
+        for tri in cube(20.0, [0.0; 3]) {
+            builder.add_triangle(tri).unwrap();
+        }
+        let analysis = builder.finish().analyze();
+        let pla = crate::materials::MaterialCatalog::load(concat!(env!("CARGO_MANIFEST_DIR"), "/profiles"))
+            .unwrap()
+            .get("pla")
+            .unwrap()
+            .clone();
+
+        let sparse = estimate(&analysis, &pla, 20, 0.2, "cube.stl".to_string());
+        let solid = estimate(&analysis, &pla, 100, 0.2, "cube.stl".to_string());
+
+        assert_eq!(sparse.plates[0].layer_count, 100);
+        assert!(sparse.filament_weight_g < solid.filament_weight_g);
+        assert!(sparse.print_time_hours < solid.print_time_hours);
+        // 100% infill extrudes the whole part volume
+        assert!((solid.filament_volume_cm3 - 8.0).abs() < 1e-9);
+        assert_eq!(
+            estimate(&analysis, &pla, 20, 0.2, "cube.stl".to_string()).print_time_hours,
+            sparse.print_time_hours
+        );
+    }
+}
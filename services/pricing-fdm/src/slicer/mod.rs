@@ -1,23 +1,219 @@
+mod cache;
+mod gcode;
+mod mock;
 mod orca;
 mod parser;
+mod pool;
+mod process;
+
+pub use cache::SliceCache;
+pub use mock::MockSlicer;
+pub use orca::OrcaSlicer;
+pub use pool::{PoolStats, QueueFull, QueueTicket, SlicePool, SliceSlot};
 
 use crate::config::Config;
-use anyhow::Result;
-use std::path::Path;
+use crate::machine::Machine;
+use crate::materials::Material;
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
+/// Slicing results summed over all plates
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SliceMetrics {
     pub print_time_hours: f64,
     pub filament_weight_g: f64,
     pub filament_length_mm: f64,
-    pub volume_cm3: f64,
+    /// Volume of extruded filament (not the part volume, see `mesh::MeshAnalysis`)
+    pub filament_volume_cm3: f64,
+    /// Per-feature breakdown merged over all plates
+    pub features: Vec<FeatureMetrics>,
+    pub plates: Vec<PlateMetrics>,
+}
+
+/// Slicing results for one build plate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlateMetrics {
+    /// 1-based plate index as numbered by Orca
+    pub plate: u32,
+    pub print_time_hours: f64,
+    pub filament_weight_g: f64,
+    pub filament_length_mm: f64,
+    pub filament_volume_cm3: f64,
+    /// Objects arranged on this plate (from slice_info.config)
+    pub objects: Vec<String>,
+    pub layer_count: u32,
+    pub max_z_mm: f64,
+    pub travel_mm: f64,
+    pub features: Vec<FeatureMetrics>,
+}
+
+/// Time and filament spent on one G-code feature (Orca `; FEATURE:` block).
+///
+/// Values are scaled so they add up to the slicer's own totals; `Travel` holds
+/// non-extruding moves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureMetrics {
+    pub feature: String,
+    pub print_time_hours: f64,
+    pub filament_length_mm: f64,
+    pub filament_weight_g: f64,
+}
+
+impl FeatureMetrics {
+    /// Support, support interface and support transition
+    pub fn is_support(&self) -> bool {
+        self.feature.to_ascii_lowercase().starts_with("support")
+    }
+}
+
+impl SliceMetrics {
+    pub fn from_plates(plates: Vec<PlateMetrics>) -> Self {
+        let mut features: Vec<FeatureMetrics> = Vec::new();
+        for feature in plates.iter().flat_map(|p| &p.features) {
+            match features.iter_mut().find(|f| f.feature == feature.feature) {
+                Some(total) => {
+                    total.print_time_hours += feature.print_time_hours;
+                    total.filament_length_mm += feature.filament_length_mm;
+                    total.filament_weight_g += feature.filament_weight_g;
+                }
+                None => features.push(feature.clone()),
+            }
+        }
+
+        SliceMetrics {
+            print_time_hours: plates.iter().map(|p| p.print_time_hours).sum(),
+            filament_weight_g: plates.iter().map(|p| p.filament_weight_g).sum(),
+            filament_length_mm: plates.iter().map(|p| p.filament_length_mm).sum(),
+            filament_volume_cm3: plates.iter().map(|p| p.filament_volume_cm3).sum(),
+            features,
+            plates,
+        }
+    }
+
+    /// Copy with times scaled by `time_factor` and filament amounts by `filament_factor`
+    pub fn scaled(&self, time_factor: f64, filament_factor: f64) -> SliceMetrics {
+        let scale_feature = |f: &FeatureMetrics| FeatureMetrics {
+            feature: f.feature.clone(),
+            print_time_hours: f.print_time_hours * time_factor,
+            filament_length_mm: f.filament_length_mm * filament_factor,
+            filament_weight_g: f.filament_weight_g * filament_factor,
+        };
+        let plates = self
+            .plates
+            .iter()
+            .map(|p| PlateMetrics {
+                print_time_hours: p.print_time_hours * time_factor,
+                filament_weight_g: p.filament_weight_g * filament_factor,
+                filament_length_mm: p.filament_length_mm * filament_factor,
+                filament_volume_cm3: p.filament_volume_cm3 * filament_factor,
+                features: p.features.iter().map(scale_feature).collect(),
+                ..p.clone()
+            })
+            .collect();
+
+        SliceMetrics {
+            print_time_hours: self.print_time_hours * time_factor,
+            filament_weight_g: self.filament_weight_g * filament_factor,
+            filament_length_mm: self.filament_length_mm * filament_factor,
+            filament_volume_cm3: self.filament_volume_cm3 * filament_factor,
+            features: self.features.iter().map(scale_feature).collect(),
+            plates,
+        }
+    }
+
+    /// Filament used for support structures, which have to be removed by hand
+    pub fn support_weight_g(&self) -> f64 {
+        self.features
+            .iter()
+            .filter(|f| f.is_support())
+            .map(|f| f.filament_weight_g)
+            .sum()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SliceError {
+    /// Slicer exceeded its timeout and was killed
+    #[error("Slicing timed out after {0}s")]
+    Timeout(u64),
+    #[error(transparent)]
+    Failed(#[from] anyhow::Error),
+    /// Slicer finished but its output could not be read
+    #[error("Failed to read slicer output: {0}")]
+    Parse(anyhow::Error),
+}
+
+impl SliceError {
+    /// `reason` label for the slice failure counter
+    pub fn reason(&self) -> &'static str {
+        match self {
+            SliceError::Timeout(_) => "timeout",
+            SliceError::Failed(_) => "slicer_error",
+            SliceError::Parse(_) => "parse_error",
+        }
+    }
+}
+
+/// Result of one slicer run
+#[derive(Debug, Clone)]
+pub struct SliceOutput {
+    pub metrics: SliceMetrics,
+    /// Sliced project (3MF with embedded G-code) next to the model; the caller removes it.
+    /// `None` for backends that do not produce one.
+    pub project_path: Option<PathBuf>,
+}
+
+/// What to slice a model with
+#[derive(Debug, Clone, Copy)]
+pub struct SliceSettings<'a> {
+    pub material: &'a Material,
+    pub machine: &'a Machine,
+    /// Percentage
+    pub infill: u8,
+    pub layer_thickness_um: u16,
+    /// How long the slicer may run: what is left of the quote's `request_timeout_secs`
+    pub timeout: Duration,
+}
+
+impl SliceSettings<'_> {
+    pub fn layer_height_mm(&self) -> f32 {
+        self.layer_thickness_um as f32 / 1000.0
+    }
+}
+
+/// Slicing backend.
+///
+/// Selected with `SLICER_BACKEND`; new slicers (PrusaSlicer, CuraEngine) plug in here.
+#[async_trait]
+pub trait Slicer: Send + Sync {
+    /// Backend name, also part of the slice cache key
+    fn name(&self) -> &'static str;
+
+    async fn slice(
+        &self,
+        model_path: &Path,
+        settings: &SliceSettings<'_>,
+        config: &Config,
+    ) -> Result<SliceOutput, SliceError>;
+
+    /// Tessellate a STEP model into a 3MF next to it, returning the 3MF path
+    async fn convert_step(&self, _step_path: &Path, _config: &Config) -> Result<PathBuf, SliceError> {
+        Err(anyhow!("STEP import is not supported by the {} slicer backend", self.name()).into())
+    }
 }
 
-pub async fn slice_model(
-    stl_path: &Path,
-    material: &str,
-    infill: u8,
-    layer_height: f32,
-    config: &Config,
-) -> Result<SliceMetrics> {
-    orca::slice(stl_path, material, infill, layer_height, config).await
+/// Build the backend configured by `SLICER_BACKEND`
+pub fn from_config(config: &Config) -> Result<Arc<dyn Slicer>> {
+    match config.slicer_backend.as_str() {
+        "orca" => Ok(Arc::new(OrcaSlicer)),
+        "mock" => Ok(Arc::new(match &config.slicer_mock_fixture {
+            Some(path) => MockSlicer::from_fixture(path)?,
+            None => MockSlicer::mesh_derived(),
+        })),
+        other => bail!("Unknown SLICER_BACKEND: {} (expected orca or mock)", other),
+    }
 }
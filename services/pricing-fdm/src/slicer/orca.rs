@@ -1,44 +1,73 @@
-use super::{parser, SliceMetrics};
+use super::{parser, process, SliceError, SliceOutput, SliceSettings, Slicer};
 use crate::config::Config;
-use anyhow::{bail, Context, Result};
-use std::path::Path;
-use std::process::Stdio;
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
+use std::time::Duration;
 use tokio::process::Command;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
-pub async fn slice(
-    stl_path: &Path,
-    material: &str,
-    _infill: u8,
-    _layer_height: f32,
+/// Orca Slicer CLI, run headless under xvfb-run
+pub struct OrcaSlicer;
+
+#[async_trait]
+impl Slicer for OrcaSlicer {
+    fn name(&self) -> &'static str {
+        "orca"
+    }
+
+    async fn slice(
+        &self,
+        model_path: &Path,
+        settings: &SliceSettings<'_>,
+        config: &Config,
+    ) -> Result<SliceOutput, SliceError> {
+        slice(model_path, settings, config).await
+    }
+
+    async fn convert_step(&self, step_path: &Path, config: &Config) -> Result<PathBuf, SliceError> {
+        convert_to_3mf(step_path, config).await
+    }
+}
+
+async fn slice(
+    model_path: &Path,
+    settings: &SliceSettings<'_>,
     config: &Config,
-) -> Result<SliceMetrics> {
+) -> Result<SliceOutput, SliceError> {
     // Validate input file exists
-    if !stl_path.exists() {
-        bail!("STL file not found: {:?}", stl_path);
+    if !model_path.exists() {
+        return Err(anyhow!("Model file not found: {:?}", model_path).into());
     }
 
-    // Create unique output directory
-    let output_id = Uuid::new_v4();
-    let output_dir = Path::new(&config.temp_dir).join(format!("slice-{}", output_id));
-    tokio::fs::create_dir_all(&output_dir).await?;
+    // Create unique output directory, removed however slicing ends (also when cancelled)
+    let output_dir = OutputDir::create(config).await?;
+    slice_in_dir(model_path, settings, config, &output_dir.0).await
+}
 
+async fn slice_in_dir(
+    model_path: &Path,
+    settings: &SliceSettings<'_>,
+    config: &Config,
+    output_dir: &Path,
+) -> Result<SliceOutput, SliceError> {
     let output_3mf = output_dir.join("result.3mf");
 
     // Build Orca Slicer command
-    // For MVP: use dummy/default profiles
-    // TODO: Map material/infill/layer_height to specific profile
-    let machine_profile = Path::new(&config.orca_profiles_dir).join("machine.json");
-    let process_profile = Path::new(&config.orca_profiles_dir).join("process_standard.json");
-    let filament_profile = Path::new(&config.orca_profiles_dir).join(format!("filament_{}.json", material));
-
-    // Fallback to generic PLA if material-specific profile doesn't exist
-    let filament_profile = if filament_profile.exists() {
-        filament_profile
-    } else {
-        Path::new(&config.orca_profiles_dir).join("filament_pla.json")
-    };
+    // Process profile is generated per request from process_standard.json
+    let machine_profile = &settings.machine.profile_path;
+    let process_profile = process::write_process_profile(
+        &config.orca_profiles_dir,
+        output_dir,
+        settings.infill,
+        settings.layer_height_mm(),
+    )
+    .await?;
+    let filament_profile = config
+        .materials
+        .filament_profile_path(settings.material, &config.orca_profiles_dir)?;
 
     debug!(
         "Slicing with profiles: machine={:?}, process={:?}, filament={:?}",
@@ -46,7 +75,8 @@ pub async fn slice(
     );
 
     // Execute xvfb-run orca-slicer
-    let output = Command::new("xvfb-run")
+    let mut command = Command::new("xvfb-run");
+    command
         .arg("-a") // Auto-select display number
         .arg(&config.orca_binary)
         .arg("--datadir")
@@ -61,21 +91,111 @@ pub async fn slice(
         .arg(filament_profile.display().to_string())
         .arg("--arrange")
         .arg("1")
+        // The model is already rotated to the orientation we priced
         .arg("--orient")
-        .arg("1")
+        .arg("0")
         .arg("--slice")
         .arg("0")
         .arg("--export-3mf")
         .arg(&output_3mf)
         .arg("--outputdir")
-        .arg(&output_dir)
-        .arg(stl_path)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
+        .arg(output_dir)
+        .arg(model_path);
+
+    let output = run_with_deadline(command, settings.timeout).await?;
+
+    check_output(&output, output_dir, &output_3mf).await?;
+
+    info!("Slicing completed, extracting metrics from 3MF");
+
+    // Extract 3MF and parse G-code
+    let metrics = parser::extract_metrics(&output_3mf, settings.material)
         .await
-        .context("Failed to execute orca-slicer command")?;
+        .map_err(SliceError::Parse)?;
+
+    // Keep the project (it embeds the G-code) past the output directory cleanup
+    let project_path = model_path.with_extension("sliced.3mf");
+    tokio::fs::rename(&output_3mf, &project_path)
+        .await
+        .context("Failed to move sliced 3MF")?;
+
+    Ok(SliceOutput {
+        metrics,
+        project_path: Some(project_path),
+    })
+}
+
+/// Tessellate a STEP model into a 3MF project with Orca.
+///
+/// The 3MF is written next to `step_path` so it can be analyzed and sliced like any mesh upload.
+async fn convert_to_3mf(step_path: &Path, config: &Config) -> Result<PathBuf, SliceError> {
+    let output_dir = OutputDir::create(config).await?;
+    convert_in_dir(step_path, config, &output_dir.0).await
+}
+
+/// Per-run `slice-<uuid>` directory under `temp_dir`, removed on drop
+struct OutputDir(PathBuf);
+
+impl OutputDir {
+    async fn create(config: &Config) -> Result<Self> {
+        let path = Path::new(&config.temp_dir).join(format!("slice-{}", Uuid::new_v4()));
+        tokio::fs::create_dir_all(&path)
+            .await
+            .with_context(|| format!("Failed to create slice directory: {:?}", path))?;
+        Ok(OutputDir(path))
+    }
+}
+
+impl Drop for OutputDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0) {
+            debug!("Failed to cleanup temp directory: {}", e);
+        }
+    }
+}
 
+async fn convert_in_dir(
+    step_path: &Path,
+    config: &Config,
+    output_dir: &Path,
+) -> Result<PathBuf, SliceError> {
+    let output_3mf = output_dir.join("converted.3mf");
+    let profiles_dir = Path::new(&config.orca_profiles_dir);
+
+    // Export without --slice: Orca only imports (tessellates) the model
+    let mut command = Command::new("xvfb-run");
+    command
+        .arg("-a")
+        .arg(&config.orca_binary)
+        .arg("--datadir")
+        .arg(&config.orca_profiles_dir)
+        .arg("--load-settings")
+        .arg(format!(
+            "{};{}",
+            config.machines.default_machine().profile_path.display(),
+            profiles_dir.join("process_standard.json").display()
+        ))
+        .arg("--export-3mf")
+        .arg(&output_3mf)
+        .arg("--outputdir")
+        .arg(output_dir)
+        .arg(step_path);
+
+    let output =
+        run_with_deadline(command, Duration::from_secs(config.request_timeout_secs)).await?;
+    check_output(&output, output_dir, &output_3mf).await?;
+
+    let converted = step_path.with_extension("3mf");
+    tokio::fs::rename(&output_3mf, &converted)
+        .await
+        .context("Failed to move converted 3MF")?;
+
+    info!("Converted STEP model to {:?}", converted);
+    Ok(converted)
+}
+
+/// Check Orca's exit status, result.json and that the 3MF was written
+async fn check_output(output: &Output, output_dir: &Path, output_3mf: &Path) -> Result<()> {
     // Check if slicing succeeded
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -104,15 +224,137 @@ pub async fn slice(
         bail!("Orca Slicer did not produce 3MF output");
     }
 
-    info!("Slicing completed, extracting metrics from 3MF");
+    Ok(())
+}
 
-    // Extract 3MF and parse G-code
-    let metrics = parser::extract_metrics(&output_3mf).await?;
+/// Run a command in its own process group, killing the whole group at the deadline.
+///
+/// `xvfb-run` spawns Xvfb and orca-slicer as children, so killing only the direct
+/// child would leave both running. The group is also killed if this future is dropped.
+async fn run_with_deadline(mut command: Command, deadline: Duration) -> Result<Output, SliceError> {
+    command
+        .process_group(0)
+        .kill_on_drop(true)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let child = command
+        .spawn()
+        .context("Failed to execute orca-slicer command")?;
+    let mut group = ProcessGroup(child.id());
 
-    // Cleanup
-    if let Err(e) = tokio::fs::remove_dir_all(&output_dir).await {
-        debug!("Failed to cleanup temp directory: {}", e);
+    match tokio::time::timeout(deadline, child.wait_with_output()).await {
+        Ok(output) => {
+            // Exited on its own; the id may be reused from here on
+            group.0 = None;
+            Ok(output.context("Failed to wait for orca-slicer")?)
+        }
+        Err(_) => {
+            warn!("Slicer exceeded deadline of {:?}, killing process group", deadline);
+            Err(SliceError::Timeout(deadline.as_secs_f64().ceil() as u64))
+        }
     }
+}
+
+/// Kills the process group on drop unless it finished
+struct ProcessGroup(Option<u32>);
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        if let Some(pgid) = self.0 {
+            kill_process_group(pgid);
+        }
+    }
+}
+
+fn kill_process_group(pgid: u32) {
+    // SAFETY: killpg only sends a signal, the group was created by process_group(0) above
+    let rc = unsafe { libc::killpg(pgid as libc::pid_t, libc::SIGKILL) };
+    if rc != 0 {
+        warn!(
+            "Failed to kill process group {}: {}",
+            pgid,
+            std::io::Error::last_os_error()
+        );
+    }
+}
 
-    Ok(metrics)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// Script whose background child (like Xvfb under xvfb-run) writes its PID to a file
+    fn script_with_background_child(pid_file: &Path) -> Command {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(format!("sleep 30 & echo $! > {}; sleep 30", pid_file.display()));
+        command
+    }
+
+    async fn background_pid(pid_file: &Path) -> libc::pid_t {
+        for _ in 0..100 {
+            if let Ok(pid) = std::fs::read_to_string(pid_file) {
+                if let Ok(pid) = pid.trim().parse() {
+                    return pid;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("background child did not write its PID");
+    }
+
+    /// Wait for the process to be gone (killed and reaped)
+    async fn assert_exits(pid: libc::pid_t) {
+        for _ in 0..200 {
+            if unsafe { libc::kill(pid, 0) } != 0 {
+                let err = std::io::Error::last_os_error();
+                assert_eq!(err.raw_os_error(), Some(libc::ESRCH), "{}", err);
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("process {} is still running", pid);
+    }
+
+    #[tokio::test]
+    async fn test_deadline_kills_process_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("child.pid");
+
+        let started = Instant::now();
+        let command = script_with_background_child(&pid_file);
+        let result = run_with_deadline(command, Duration::from_millis(200)).await;
+
+        assert!(matches!(result, Err(SliceError::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_exits(background_pid(&pid_file).await).await;
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_run_kills_process_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("child.pid");
+
+        let command = script_with_background_child(&pid_file);
+        let run = run_with_deadline(command, Duration::from_secs(30));
+        let result = tokio::time::timeout(Duration::from_millis(200), run).await;
+
+        assert!(result.is_err());
+        assert_exits(background_pid(&pid_file).await).await;
+    }
+
+    #[tokio::test]
+    async fn test_completes_within_deadline() {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("echo sliced");
+
+        let output = run_with_deadline(command, Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "sliced");
+    }
 }
@@ -1,55 +1,265 @@
-use super::SliceMetrics;
-use anyhow::{bail, Context, Result};
+use super::gcode::{self, GcodeAnalysis};
+use super::{FeatureMetrics, PlateMetrics, SliceMetrics};
+use crate::materials::Material;
+use anyhow::{anyhow, bail, Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
 use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Seek};
 use std::path::Path;
-use tracing::debug;
+use tracing::{debug, warn};
 
-pub async fn extract_metrics(three_mf_path: &Path) -> Result<SliceMetrics> {
-    // 3MF is a ZIP archive, extract it
-    let temp_extract_dir = three_mf_path.with_extension("extracted");
-    tokio::fs::create_dir_all(&temp_extract_dir).await?;
+/// Orca's per-plate summary in `Metadata/slice_info.config`
+#[derive(Debug, Default)]
+struct PlateInfo {
+    index: u32,
+    prediction_secs: Option<f64>,
+    weight_g: Option<f64>,
+    filament_used_m: f64,
+    objects: Vec<String>,
+}
 
-    // Extract 3MF using zip crate
-    let file = std::fs::File::open(three_mf_path)?;
-    let mut archive = zip::ZipArchive::new(file)?;
+/// Extract metrics for every sliced plate in an Orca 3MF
+pub async fn extract_metrics(three_mf_path: &Path, material: &Material) -> Result<SliceMetrics> {
+    let path = three_mf_path.to_path_buf();
+    let material = material.clone();
 
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        let outpath = temp_extract_dir.join(file.name());
+    tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::open(&path)
+            .with_context(|| format!("Failed to open sliced 3MF: {:?}", path))?;
+        read_archive(file, &material)
+    })
+    .await
+    .context("Metrics extraction task panicked")?
+}
 
-        if file.is_dir() {
-            std::fs::create_dir_all(&outpath)?;
-        } else {
-            if let Some(p) = outpath.parent() {
-                std::fs::create_dir_all(p)?;
-            }
-            let mut outfile = std::fs::File::create(&outpath)?;
-            std::io::copy(&mut file, &mut outfile)?;
+fn read_archive<R: Read + Seek>(reader: R, material: &Material) -> Result<SliceMetrics> {
+    let mut archive = zip::ZipArchive::new(reader).context("Invalid 3MF archive")?;
+
+    // G-code is written per plate as Metadata/plate_<N>.gcode
+    let mut gcode_entries: BTreeMap<u32, String> = BTreeMap::new();
+    for name in archive.file_names() {
+        if let Some(index) = gcode_plate_index(name) {
+            gcode_entries.insert(index, name.to_string());
         }
     }
 
-    // Look for G-code file in Metadata/plate_1.gcode
-    let gcode_path = temp_extract_dir.join("Metadata/plate_1.gcode");
-    if !gcode_path.exists() {
+    let slice_info = "Metadata/slice_info.config";
+    let plate_info: BTreeMap<u32, PlateInfo> = if archive.file_names().any(|n| n == slice_info) {
+        match read_entry(&mut archive, slice_info).and_then(|xml| parse_slice_info(&xml)) {
+            Ok(plates) => plates.into_iter().map(|p| (p.index, p)).collect(),
+            Err(e) => {
+                warn!("Ignoring unreadable slice_info.config: {:#}", e);
+                BTreeMap::new()
+            }
+        }
+    } else {
+        BTreeMap::new()
+    };
+
+    let indices: BTreeSet<u32> = gcode_entries.keys().chain(plate_info.keys()).copied().collect();
+    if indices.is_empty() {
         bail!("G-code not found in 3MF archive");
     }
 
-    debug!("Parsing G-code from: {:?}", gcode_path);
+    let mut plates = Vec::with_capacity(indices.len());
+    for index in indices {
+        let info = plate_info.get(&index);
+        let gcode = match gcode_entries.get(&index) {
+            Some(name) => match read_entry(&mut archive, name) {
+                Ok(gcode) => Some(gcode),
+                Err(e) => {
+                    warn!("Plate {} G-code unreadable: {:#}", index, e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let mut plate = match (gcode, info) {
+            (Some(gcode), info) => match parse_gcode_comments(&gcode, material) {
+                Ok(mut plate) => {
+                    let analysis = gcode::analyze(&gcode);
+                    plate.layer_count = analysis.layer_count;
+                    plate.max_z_mm = analysis.max_z_mm;
+                    plate.travel_mm = analysis.travel_mm;
+                    plate.features = feature_breakdown(&analysis, &plate);
+                    plate
+                }
+                Err(e) => match info.and_then(|i| from_plate_info(i, material)) {
+                    Some(plate) => {
+                        warn!("Plate {} G-code unreadable ({}), using slice_info", index, e);
+                        plate
+                    }
+                    None => return Err(e.context(format!("Plate {}", index))),
+                },
+            },
+            (None, Some(info)) => match from_plate_info(info, material) {
+                Some(plate) => plate,
+                // Listed but not sliced (empty plate)
+                None => continue,
+            },
+            (None, None) => return Err(anyhow!("Plate {} G-code unreadable", index)),
+        };
+
+        plate.plate = index;
+        if let Some(info) = info {
+            plate.objects = info.objects.clone();
+        }
+        debug!("Plate {}: {:?}", index, plate);
+        plates.push(plate);
+    }
+
+    if plates.is_empty() {
+        bail!("No sliced plates found in 3MF archive");
+    }
+
+    Ok(SliceMetrics::from_plates(plates))
+}
+
+/// `Metadata/plate_12.gcode` -> 12
+fn gcode_plate_index(name: &str) -> Option<u32> {
+    name.strip_prefix("Metadata/plate_")?
+        .strip_suffix(".gcode")?
+        .parse()
+        .ok()
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut zip::ZipArchive<R>, name: &str) -> Result<String> {
+    let mut entry = archive
+        .by_name(name)
+        .with_context(|| format!("Failed to open {}", name))?;
+    let mut content = String::new();
+    entry
+        .read_to_string(&mut content)
+        .with_context(|| format!("Failed to read {}", name))?;
+    Ok(content)
+}
+
+fn parse_slice_info(xml: &str) -> Result<Vec<PlateInfo>> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut plates = Vec::new();
+    let mut current: Option<PlateInfo> = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) if e.local_name().as_ref() == b"plate" => {
+                current = Some(PlateInfo::default());
+            }
+            Event::End(e) if e.local_name().as_ref() == b"plate" => {
+                plates.extend(current.take());
+            }
+            Event::Start(e) | Event::Empty(e) => {
+                let Some(plate) = current.as_mut() else {
+                    continue;
+                };
+                match e.local_name().as_ref() {
+                    b"metadata" => {
+                        let key = attr(&e, b"key")?;
+                        let value = attr(&e, b"value")?;
+                        match (key.as_deref(), value) {
+                            (Some("index"), Some(v)) => plate.index = v.parse()?,
+                            (Some("prediction"), Some(v)) => plate.prediction_secs = v.parse().ok(),
+                            (Some("weight"), Some(v)) => plate.weight_g = v.parse().ok(),
+                            _ => {}
+                        }
+                    }
+                    b"object" => {
+                        let skipped = attr(&e, b"skipped")?.as_deref() == Some("true");
+                        match attr(&e, b"name")? {
+                            Some(name) if !skipped => plate.objects.push(name),
+                            _ => {}
+                        }
+                    }
+                    b"filament" => {
+                        if let Some(used_m) = attr(&e, b"used_m")?.and_then(|v| v.parse::<f64>().ok()) {
+                            plate.filament_used_m += used_m;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(plates)
+}
+
+fn attr(e: &BytesStart, name: &[u8]) -> Result<Option<String>> {
+    match e.try_get_attribute(name)? {
+        Some(a) => Ok(Some(a.unescape_value()?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+/// Metrics from slice_info when the plate's G-code is missing or unreadable
+fn from_plate_info(info: &PlateInfo, material: &Material) -> Option<PlateMetrics> {
+    let prediction_secs = info.prediction_secs.filter(|&s| s > 0.0)?;
+    let filament_weight_g = info.weight_g?;
+    let filament_length_mm = if info.filament_used_m > 0.0 {
+        info.filament_used_m * 1000.0
+    } else {
+        filament_weight_g / material.density_g_cm3 / material.filament_area_cm2() * 10.0
+    };
+
+    Some(PlateMetrics {
+        plate: info.index,
+        print_time_hours: prediction_secs / 3600.0,
+        filament_weight_g,
+        filament_length_mm,
+        filament_volume_cm3: filament_weight_g / material.density_g_cm3,
+        objects: Vec::new(),
+        layer_count: 0,
+        max_z_mm: 0.0,
+        travel_mm: 0.0,
+        features: Vec::new(),
+    })
+}
 
-    // Read G-code and extract metrics from comments
-    let gcode_content = tokio::fs::read_to_string(&gcode_path).await?;
+/// Scale the analyzer's naive per-feature values to the slicer's totals for the plate.
+///
+/// The analyzer ignores acceleration, so only the proportions are trusted.
+fn feature_breakdown(analysis: &GcodeAnalysis, plate: &PlateMetrics) -> Vec<FeatureMetrics> {
+    let total_time = analysis.total_time_secs();
+    let total_filament: f64 = analysis.features.values().map(|f| f.filament_mm.max(0.0)).sum();
+    let time_share = |secs: f64| if total_time > 0.0 { secs / total_time } else { 0.0 };
+    let filament_share = |mm: f64| {
+        if total_filament > 0.0 {
+            mm.max(0.0) / total_filament
+        } else {
+            0.0
+        }
+    };
 
-    let metrics = parse_gcode_comments(&gcode_content)?;
+    let mut features: Vec<FeatureMetrics> = analysis
+        .features
+        .iter()
+        .map(|(name, stats)| FeatureMetrics {
+            feature: name.clone(),
+            print_time_hours: plate.print_time_hours * time_share(stats.time_secs),
+            filament_length_mm: plate.filament_length_mm * filament_share(stats.filament_mm),
+            filament_weight_g: plate.filament_weight_g * filament_share(stats.filament_mm),
+        })
+        .collect();
 
-    // Cleanup temp extraction directory
-    if let Err(e) = tokio::fs::remove_dir_all(&temp_extract_dir).await {
-        debug!("Failed to cleanup extraction directory: {}", e);
+    if analysis.travel_time_secs > 0.0 {
+        features.push(FeatureMetrics {
+            feature: "Travel".to_string(),
+            print_time_hours: plate.print_time_hours * time_share(analysis.travel_time_secs),
+            filament_length_mm: 0.0,
+            filament_weight_g: 0.0,
+        });
     }
 
-    Ok(metrics)
+    features
 }
 
-fn parse_gcode_comments(gcode: &str) -> Result<SliceMetrics> {
+fn parse_gcode_comments(gcode: &str, material: &Material) -> Result<PlateMetrics> {
     // Regex patterns for common slicer comment formats
     let re_time = Regex::new(r"; estimated printing time.*?=\s*(?:(\d+)h\s*)?(?:(\d+)m)?\s*(?:(\d+)s)?")
         .unwrap();
@@ -90,12 +300,9 @@ fn parse_gcode_comments(gcode: &str) -> Result<SliceMetrics> {
             .unwrap_or(0.0)
             * 1000.0
     } else {
-        // Estimate from weight if not available (assume PLA density 1.24 g/cm³, 1.75mm filament)
-        let filament_radius_cm = 0.175 / 2.0; // 1.75mm diameter
-        let filament_area_cm2 = std::f64::consts::PI * filament_radius_cm * filament_radius_cm;
-        let filament_density = 1.24; // g/cm³
-        let volume_cm3 = filament_weight_g / filament_density;
-        let length_cm = volume_cm3 / filament_area_cm2;
+        // Estimate from weight if not available (material density and filament diameter)
+        let volume_cm3 = filament_weight_g / material.density_g_cm3;
+        let length_cm = volume_cm3 / material.filament_area_cm2();
         length_cm * 10.0 // Convert cm to mm
     };
 
@@ -104,12 +311,12 @@ fn parse_gcode_comments(gcode: &str) -> Result<SliceMetrics> {
         cap.get(1)
             .and_then(|m| m.as_str().parse::<f64>().ok())
             .unwrap_or_else(|| {
-                // Estimate from weight (assume PLA density)
-                filament_weight_g / 1.24
+                // Estimate from weight using material density
+                filament_weight_g / material.density_g_cm3
             })
     } else {
         // Estimate from weight
-        filament_weight_g / 1.24
+        filament_weight_g / material.density_g_cm3
     };
 
     debug!(
@@ -117,11 +324,17 @@ fn parse_gcode_comments(gcode: &str) -> Result<SliceMetrics> {
         print_time_hours, filament_weight_g, filament_length_mm, volume_cm3
     );
 
-    Ok(SliceMetrics {
+    Ok(PlateMetrics {
+        plate: 1,
         print_time_hours,
         filament_weight_g,
         filament_length_mm,
-        volume_cm3,
+        filament_volume_cm3: volume_cm3,
+        objects: Vec::new(),
+        layer_count: 0,
+        max_z_mm: 0.0,
+        travel_mm: 0.0,
+        features: Vec::new(),
     })
 }
 
@@ -129,6 +342,22 @@ fn parse_gcode_comments(gcode: &str) -> Result<SliceMetrics> {
 mod tests {
     use super::*;
 
+    fn pla() -> Material {
+        Material {
+            id: "pla".to_string(),
+            display_name: "PLA".to_string(),
+            density_g_cm3: 1.24,
+            filament_diameter_mm: 1.75,
+            cost_per_g_usd: 0.02,
+            filament_profile: "filament_pla.json".to_string(),
+            layer_heights_um: vec![100, 200, 300],
+            min_infill: 10,
+            max_infill: 100,
+            colors: Vec::new(),
+            enabled: true,
+        }
+    }
+
     #[test]
     fn test_parse_gcode_comments() {
         let gcode = r#"
@@ -138,11 +367,11 @@ mod tests {
 ; filament used [cm3] = 101.2
 "#;
 
-        let metrics = parse_gcode_comments(gcode).unwrap();
+        let metrics = parse_gcode_comments(gcode, &pla()).unwrap();
         assert!((metrics.print_time_hours - 2.5125).abs() < 0.001);
         assert!((metrics.filament_weight_g - 125.5).abs() < 0.001);
         assert!((metrics.filament_length_mm - 41234.56).abs() < 0.001);
-        assert!((metrics.volume_cm3 - 101.2).abs() < 0.001);
+        assert!((metrics.filament_volume_cm3 - 101.2).abs() < 0.001);
     }
 
     #[test]
@@ -152,8 +381,124 @@ mod tests {
 ; filament used [g] = 50.0
 "#;
 
-        let metrics = parse_gcode_comments(gcode).unwrap();
+        let metrics = parse_gcode_comments(gcode, &pla()).unwrap();
         assert!((metrics.print_time_hours - 0.758).abs() < 0.01);
         assert!((metrics.filament_weight_g - 50.0).abs() < 0.001);
+        // Length and volume estimated from PLA density and 1.75mm filament
+        assert!((metrics.filament_volume_cm3 - 40.32).abs() < 0.01);
+        assert!((metrics.filament_length_mm - 16765.0).abs() < 1.0);
+    }
+
+    fn archive<C: AsRef<[u8]>>(entries: &[(&str, C)]) -> std::io::Cursor<Vec<u8>> {
+        use std::io::Write;
+
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (name, content) in entries {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(content.as_ref()).unwrap();
+        }
+        let mut cursor = writer.finish().unwrap();
+        cursor.set_position(0);
+        cursor
+    }
+
+    const SLICE_INFO: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<config>
+  <header><header_item key="X-BBL-Client-Type" value="slicer"/></header>
+  <plate>
+    <metadata key="index" value="1"/>
+    <metadata key="prediction" value="3600"/>
+    <metadata key="weight" value="20.00"/>
+    <object identify_id="12" name="bracket.stl" skipped="false"/>
+    <filament id="1" type="PLA" used_m="6.70" used_g="20.00"/>
+  </plate>
+  <plate>
+    <metadata key="index" value="2"/>
+    <metadata key="prediction" value="1800"/>
+    <metadata key="weight" value="10.00"/>
+    <object identify_id="20" name="bracket.stl" skipped="false"/>
+    <object identify_id="21" name="spacer.stl" skipped="false"/>
+    <filament id="1" type="PLA" used_m="3.35" used_g="10.00"/>
+  </plate>
+</config>"#;
+
+    #[test]
+    fn test_sums_all_plates() {
+        let plate_1 = "; estimated printing time (normal mode) = 1h 0m 0s\n; filament used [g] = 20.0\n; filament used [mm] = 6700.0\n";
+        let plate_2 = "; estimated printing time (normal mode) = 30m 0s\n; filament used [g] = 10.0\n; filament used [mm] = 3350.0\n";
+        let data = archive(&[
+            ("Metadata/plate_1.gcode", plate_1),
+            ("Metadata/plate_2.gcode", plate_2),
+            ("Metadata/slice_info.config", SLICE_INFO),
+        ]);
+
+        let metrics = read_archive(data, &pla()).unwrap();
+
+        assert_eq!(metrics.plates.len(), 2);
+        assert!((metrics.print_time_hours - 1.5).abs() < 1e-9);
+        assert!((metrics.filament_weight_g - 30.0).abs() < 1e-9);
+        assert!((metrics.filament_length_mm - 10050.0).abs() < 1e-9);
+        assert_eq!(metrics.plates[1].plate, 2);
+        assert_eq!(metrics.plates[1].objects, vec!["bracket.stl", "spacer.stl"]);
+    }
+
+    #[test]
+    fn test_falls_back_to_slice_info_without_gcode() {
+        let plate_1 = "; estimated printing time (normal mode) = 1h 0m 0s\n; filament used [g] = 20.0\n";
+        let data = archive(&[
+            ("Metadata/plate_1.gcode", plate_1),
+            ("Metadata/slice_info.config", SLICE_INFO),
+        ]);
+
+        let metrics = read_archive(data, &pla()).unwrap();
+
+        assert_eq!(metrics.plates.len(), 2);
+        assert!((metrics.plates[1].print_time_hours - 0.5).abs() < 1e-9);
+        assert!((metrics.plates[1].filament_length_mm - 3350.0).abs() < 1e-6);
+        assert!((metrics.filament_weight_g - 30.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_archive_without_gcode_fails() {
+        let data = archive(&[("3D/3dmodel.model", "<model/>")]);
+        let err = read_archive(data, &pla()).unwrap_err();
+        assert!(err.to_string().contains("G-code not found"));
+    }
+
+    #[test]
+    fn test_unreadable_gcode_without_slice_info_fails() {
+        let data = archive(&[("Metadata/plate_1.gcode", b"; filament used [g] = \xff\xfe\n")]);
+        let err = read_archive(data, &pla()).unwrap_err();
+        assert_eq!(err.to_string(), "Plate 1 G-code unreadable");
+    }
+
+    #[test]
+    fn test_feature_breakdown_scaled_to_totals() {
+        let gcode = "\
+; estimated printing time (normal mode) = 1h 0m 0s
+; filament used [g] = 10.0
+; filament used [mm] = 3000.0
+M83
+; FEATURE: Outer wall
+G1 X30 Y0 E3 F600
+; FEATURE: Support
+G1 X30 Y10 E1 F600
+G0 X0 Y10 F600
+";
+        let data = archive(&[("Metadata/plate_1.gcode", gcode)]);
+        let metrics = read_archive(data, &pla()).unwrap();
+        let plate = &metrics.plates[0];
+
+        assert_eq!(plate.layer_count, 1);
+        assert!((plate.travel_mm - 30.0).abs() < 1e-9);
+        assert!((metrics.support_weight_g() - 2.5).abs() < 1e-9);
+
+        // 30 + 10 + 30 mm at the same feedrate, scaled to one hour
+        let support = metrics.features.iter().find(|f| f.is_support()).unwrap();
+        assert!((support.print_time_hours - 10.0 / 70.0).abs() < 1e-9);
+        let total: f64 = metrics.features.iter().map(|f| f.print_time_hours).sum();
+        assert!((total - 1.0).abs() < 1e-9);
     }
 }
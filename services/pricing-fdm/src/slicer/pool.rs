@@ -0,0 +1,185 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Bounded pool of slicing slots with a bounded wait queue.
+///
+/// At most `max_concurrent` Orca processes run at once, at most `max_queued`
+/// requests wait for a slot, everyone else is rejected immediately.
+#[derive(Clone)]
+pub struct SlicePool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    permits: Arc<Semaphore>,
+    max_concurrent: usize,
+    max_queued: usize,
+    queued: AtomicUsize,
+    active: AtomicUsize,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Slicing queue is full")]
+pub struct QueueFull;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PoolStats {
+    pub active: usize,
+    pub queued: usize,
+    pub max_concurrent: usize,
+    pub max_queued: usize,
+}
+
+/// Held while a slice runs, frees the slot on drop
+pub struct SliceSlot {
+    _permit: OwnedSemaphorePermit,
+    inner: Arc<Inner>,
+}
+
+impl Drop for SliceSlot {
+    fn drop(&mut self) {
+        self.inner.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Queue position, released on drop so cancelled requests don't leak it
+pub struct QueueTicket {
+    inner: Arc<Inner>,
+}
+
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        self.inner.queued.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl SlicePool {
+    pub fn new(max_concurrent: usize, max_queued: usize) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        SlicePool {
+            inner: Arc::new(Inner {
+                permits: Arc::new(Semaphore::new(max_concurrent)),
+                max_concurrent,
+                max_queued,
+                queued: AtomicUsize::new(0),
+                active: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Wait for a free slot, or fail fast if the wait queue is full
+    pub async fn acquire(&self) -> Result<SliceSlot, QueueFull> {
+        let inner = &self.inner;
+
+        // Fast path: free slot, no queueing
+        if let Ok(permit) = inner.permits.clone().try_acquire_owned() {
+            return Ok(self.slot(permit));
+        }
+
+        let ticket = self.reserve()?;
+        Ok(self.acquire_reserved(ticket).await)
+    }
+
+    /// Take a queue position now, to wait for a slot with later (async jobs).
+    ///
+    /// Fails fast like `acquire` if the wait queue is full.
+    pub fn reserve(&self) -> Result<QueueTicket, QueueFull> {
+        let inner = &self.inner;
+        if inner.queued.fetch_add(1, Ordering::SeqCst) >= inner.max_queued {
+            inner.queued.fetch_sub(1, Ordering::SeqCst);
+            return Err(QueueFull);
+        }
+        Ok(QueueTicket {
+            inner: self.inner.clone(),
+        })
+    }
+
+    /// Wait for a free slot on a reserved queue position, which is given up once it is taken
+    pub async fn acquire_reserved(&self, ticket: QueueTicket) -> SliceSlot {
+        let permit = self
+            .inner
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("slice pool semaphore is never closed");
+        drop(ticket);
+
+        self.slot(permit)
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            active: self.inner.active.load(Ordering::SeqCst),
+            queued: self.inner.queued.load(Ordering::SeqCst),
+            max_concurrent: self.inner.max_concurrent,
+            max_queued: self.inner.max_queued,
+        }
+    }
+
+    fn slot(&self, permit: OwnedSemaphorePermit) -> SliceSlot {
+        self.inner.active.fetch_add(1, Ordering::SeqCst);
+        SliceSlot {
+            _permit: permit,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_rejects_when_queue_full() {
+        let pool = SlicePool::new(1, 1);
+
+        let running = pool.acquire().await.unwrap();
+        assert_eq!(pool.stats().active, 1);
+
+        // Second request waits in the queue
+        let waiting_pool = pool.clone();
+        let waiting = tokio::spawn(async move { waiting_pool.acquire().await.map(|_| ()) });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(pool.stats().queued, 1);
+
+        // Third request is rejected
+        assert!(pool.acquire().await.is_err());
+
+        drop(running);
+        waiting.await.unwrap().unwrap();
+
+        let stats = pool.stats();
+        assert_eq!(stats.active, 0);
+        assert_eq!(stats.queued, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reserved_position_counts_until_slot_is_taken() {
+        let pool = SlicePool::new(1, 1);
+        let running = pool.acquire().await.unwrap();
+
+        let ticket = pool.reserve().unwrap();
+        assert_eq!(pool.stats().queued, 1);
+        assert!(pool.reserve().is_err());
+        assert!(pool.acquire().await.is_err());
+
+        drop(running);
+        let _slot = pool.acquire_reserved(ticket).await;
+        let stats = pool.stats();
+        assert_eq!(stats.active, 1);
+        assert_eq!(stats.queued, 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_releases_queue_position() {
+        let pool = SlicePool::new(1, 1);
+        let _running = pool.acquire().await.unwrap();
+
+        let result = tokio::time::timeout(Duration::from_millis(50), pool.acquire()).await;
+        assert!(result.is_err());
+        assert_eq!(pool.stats().queued, 0);
+    }
+}
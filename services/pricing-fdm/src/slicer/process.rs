@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Base process profile every per-request profile is derived from
+const BASE_PROCESS_PROFILE: &str = "process_standard.json";
+
+/// Top/bottom shell thickness (mm) kept constant across layer heights
+const SHELL_THICKNESS_MM: f32 = 0.8;
+
+/// Build a process profile for this request and write it into `output_dir`.
+///
+/// Returns the path to pass to `--load-settings`.
+pub async fn write_process_profile(
+    profiles_dir: &str,
+    output_dir: &Path,
+    infill: u8,
+    layer_height: f32,
+) -> Result<PathBuf> {
+    let base_path = Path::new(profiles_dir).join(BASE_PROCESS_PROFILE);
+    let base_json = tokio::fs::read_to_string(&base_path)
+        .await
+        .with_context(|| format!("Failed to read process profile: {:?}", base_path))?;
+    let base: Value = serde_json::from_str(&base_json)
+        .with_context(|| format!("Invalid process profile JSON: {:?}", base_path))?;
+
+    let profile = build_process_profile(base, infill, layer_height)?;
+
+    let profile_path = output_dir.join("process.json");
+    tokio::fs::write(&profile_path, serde_json::to_vec_pretty(&profile)?)
+        .await
+        .context("Failed to write process profile")?;
+
+    debug!(
+        "Wrote process profile: infill={}%, layer_height={}mm, path={:?}",
+        infill, layer_height, profile_path
+    );
+
+    Ok(profile_path)
+}
+
+/// Override infill and layer-dependent keys of the base process profile.
+///
+/// Orca stores every value as a string, so overrides follow the same format.
+fn build_process_profile(mut base: Value, infill: u8, layer_height: f32) -> Result<Value> {
+    let profile = base
+        .as_object_mut()
+        .context("Process profile must be a JSON object")?;
+
+    let layer_height_str = format_mm(layer_height);
+
+    // First layer stays at least as thick as the base profile for bed adhesion
+    let base_initial_layer = profile
+        .get("initial_layer_height")
+        .and_then(|v| v.as_str())
+        .and_then(|v| v.parse::<f32>().ok())
+        .unwrap_or(layer_height);
+    let initial_layer_height = base_initial_layer.max(layer_height);
+
+    // Keep top/bottom skin thickness constant regardless of layer height
+    // (small epsilon so 0.8 / 0.1 doesn't round up to 9 layers)
+    let shell_layers = (SHELL_THICKNESS_MM / layer_height - 1e-3).ceil().max(1.0) as u32;
+
+    profile.insert(
+        "name".to_string(),
+        Value::String(format!("{}mm {}% @RapidFab", layer_height_str, infill)),
+    );
+    profile.insert("layer_height".to_string(), Value::String(layer_height_str));
+    profile.insert(
+        "initial_layer_height".to_string(),
+        Value::String(format_mm(initial_layer_height)),
+    );
+    profile.insert(
+        "top_shell_layers".to_string(),
+        Value::String(shell_layers.to_string()),
+    );
+    profile.insert(
+        "bottom_shell_layers".to_string(),
+        Value::String(shell_layers.to_string()),
+    );
+    profile.insert(
+        "sparse_infill_density".to_string(),
+        Value::String(format!("{}%", infill)),
+    );
+
+    // Grid at 100% overlaps itself, Orca expects a rectilinear pattern for solid parts
+    if infill >= 100 {
+        profile.insert(
+            "sparse_infill_pattern".to_string(),
+            Value::String("zig-zag".to_string()),
+        );
+    }
+
+    Ok(base)
+}
+
+fn format_mm(value: f32) -> String {
+    // Round to micrometers to avoid values like 0.30000001
+    let rounded = (value * 1000.0).round() / 1000.0;
+    rounded.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn base_profile() -> Value {
+        json!({
+            "type": "process",
+            "name": "0.20mm Standard @Generic",
+            "layer_height": "0.2",
+            "initial_layer_height": "0.2",
+            "top_shell_layers": "4",
+            "bottom_shell_layers": "4",
+            "sparse_infill_density": "20%",
+            "sparse_infill_pattern": "grid",
+            "wall_loops": "3"
+        })
+    }
+
+    #[test]
+    fn test_overrides_infill_and_layer_height() {
+        let profile = build_process_profile(base_profile(), 10, 0.3).unwrap();
+
+        assert_eq!(profile["sparse_infill_density"], "10%");
+        assert_eq!(profile["layer_height"], "0.3");
+        assert_eq!(profile["initial_layer_height"], "0.3");
+        assert_eq!(profile["top_shell_layers"], "3");
+        assert_eq!(profile["bottom_shell_layers"], "3");
+        assert_eq!(profile["sparse_infill_pattern"], "grid");
+        // Untouched keys are preserved
+        assert_eq!(profile["wall_loops"], "3");
+    }
+
+    #[test]
+    fn test_fine_layers_keep_thick_first_layer() {
+        let profile = build_process_profile(base_profile(), 20, 0.1).unwrap();
+
+        assert_eq!(profile["layer_height"], "0.1");
+        assert_eq!(profile["initial_layer_height"], "0.2");
+        assert_eq!(profile["top_shell_layers"], "8");
+    }
+
+    #[test]
+    fn test_full_infill_switches_to_rectilinear() {
+        let profile = build_process_profile(base_profile(), 100, 0.2).unwrap();
+
+        assert_eq!(profile["sparse_infill_density"], "100%");
+        assert_eq!(profile["sparse_infill_pattern"], "zig-zag");
+    }
+
+    #[test]
+    fn test_rejects_non_object_profile() {
+        assert!(build_process_profile(json!([]), 20, 0.2).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use crate::config::Config;
+use anyhow::{anyhow, Context};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Who is asking for a quote: a signed-in user or an anonymous upload session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Principal {
+    User(Uuid),
+    Session(Uuid),
+}
+
+/// File as resolved by the upload service (`GET /internal/upload/file/{id}/read-url`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct UploadedFile {
+    pub file_id: Uuid,
+    /// Presigned read URL
+    pub url: String,
+    /// Owner: exactly one of user_id or session_id is set
+    pub user_id: Option<Uuid>,
+    pub session_id: Option<Uuid>,
+    pub size_bytes: u64,
+    /// Hex SHA-256 recorded at upload, when known
+    pub sha256: Option<String>,
+}
+
+impl UploadedFile {
+    pub fn is_owned_by(&self, principal: Principal) -> bool {
+        match principal {
+            Principal::User(id) => self.user_id == Some(id),
+            Principal::Session(id) => self.session_id == Some(id),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResolveError {
+    #[error("File not found: {0}")]
+    NotFound(Uuid),
+    #[error(transparent)]
+    Failed(#[from] anyhow::Error),
+}
+
+/// Look up a file and a presigned read URL for it, authenticated with `INTERNAL_SERVICE_TOKEN`
+pub async fn resolve_file(file_id: Uuid, config: &Config) -> Result<UploadedFile, ResolveError> {
+    let token = config
+        .internal_service_token
+        .as_deref()
+        .context("INTERNAL_SERVICE_TOKEN is not set")?;
+    let url = format!(
+        "{}/internal/upload/file/{}/read-url",
+        config.upload_service_url.trim_end_matches('/'),
+        file_id
+    );
+    debug!("Resolving file {} via the upload service", file_id);
+
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(config.download_connect_timeout_secs))
+        .timeout(Duration::from_secs(config.download_read_timeout_secs))
+        .build()
+        .context("Failed to build upload service client")?;
+    let response = client
+        .get(&url)
+        .header("x-internal-token", token)
+        .send()
+        .await
+        .context("Failed to reach the upload service")?;
+
+    match response.status() {
+        StatusCode::NOT_FOUND => return Err(ResolveError::NotFound(file_id)),
+        status if !status.is_success() => {
+            return Err(anyhow!("Upload service returned {}", status).into());
+        }
+        _ => {}
+    }
+
+    let file: UploadedFile = response
+        .json()
+        .await
+        .context("Invalid upload service response")?;
+    if file.file_id != file_id {
+        return Err(anyhow!("Upload service returned file {} for {}", file.file_id, file_id).into());
+    }
+
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Path, http, routing::get, Json, Router};
+    use serde_json::json;
+
+    const OWNER: Uuid = Uuid::from_u128(1);
+
+    /// Upload service stand-in that knows a single file
+    async fn upload_service(known: Uuid) -> String {
+        let app = Router::new().route(
+            "/internal/upload/file/:id/read-url",
+            get(move |Path(id): Path<Uuid>, headers: http::HeaderMap| async move {
+                if headers.get("x-internal-token").and_then(|v| v.to_str().ok()) != Some("secret") {
+                    return Err(http::StatusCode::UNAUTHORIZED);
+                }
+                if id != known {
+                    return Err(http::StatusCode::NOT_FOUND);
+                }
+                Ok(Json(json!({
+                    "file_id": id,
+                    "url": "https://s3.example.com/model.stl?sig=x",
+                    "expires_at": "2026-10-17T12:00:00Z",
+                    "user_id": OWNER,
+                    "session_id": null,
+                    "size_bytes": 684,
+                    "sha256": null
+                })))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/", addr)
+    }
+
+    #[tokio::test]
+    async fn test_resolves_file_with_owner() {
+        let file_id = Uuid::new_v4();
+        let mut config = Config::for_tests();
+        config.upload_service_url = upload_service(file_id).await;
+        config.internal_service_token = Some("secret".to_string());
+
+        let file = resolve_file(file_id, &config).await.unwrap();
+        assert_eq!(file.url, "https://s3.example.com/model.stl?sig=x");
+        assert!(file.is_owned_by(Principal::User(OWNER)));
+        assert!(!file.is_owned_by(Principal::Session(OWNER)));
+        assert!(!file.is_owned_by(Principal::User(Uuid::new_v4())));
+
+        let missing = resolve_file(Uuid::new_v4(), &config).await;
+        assert!(matches!(missing, Err(ResolveError::NotFound(_))));
+
+        config.internal_service_token = Some("wrong".to_string());
+        let rejected = resolve_file(file_id, &config).await.unwrap_err();
+        assert!(rejected.to_string().contains("401"), "{}", rejected);
+    }
+}
@@ -1,65 +1,385 @@
+use crate::config::Config;
 use anyhow::{bail, Context, Result};
-use std::path::PathBuf;
+use reqwest::header::LOCATION;
+use reqwest::Url;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::io::AsyncWriteExt;
 use tracing::debug;
 use uuid::Uuid;
 
-pub async fn download_stl(presigned_url: &str, temp_dir: &str) -> Result<PathBuf> {
-    debug!("Downloading STL from: {}", presigned_url);
+/// Limits and network restrictions applied to model downloads
+#[derive(Debug, Clone)]
+pub struct DownloadPolicy {
+    /// Exact hosts, or `*.example.com` for any subdomain
+    pub allowed_hosts: Vec<String>,
+    pub allow_private_networks: bool,
+    pub max_bytes: u64,
+    pub connect_timeout: Duration,
+    /// Maximum time to wait for the response headers, and then for each chunk of the body
+    pub read_timeout: Duration,
+    pub max_redirects: usize,
+}
+
+impl DownloadPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        DownloadPolicy {
+            allowed_hosts: config.download_allowed_hosts.clone(),
+            allow_private_networks: config.download_allow_private_networks,
+            max_bytes: config.max_file_size_mb * 1024 * 1024,
+            connect_timeout: Duration::from_secs(config.download_connect_timeout_secs),
+            read_timeout: Duration::from_secs(config.download_read_timeout_secs),
+            max_redirects: config.download_max_redirects,
+        }
+    }
+
+    /// Network rules for job callbacks: their own host allowlist, so opening callbacks to a
+    /// host does not let models be fetched from it (and the other way round)
+    pub fn for_callbacks(config: &Config) -> Self {
+        DownloadPolicy {
+            allowed_hosts: config.callback_allowed_hosts.clone(),
+            allow_private_networks: config.callback_allow_private_networks,
+            ..Self::from_config(config)
+        }
+    }
+}
 
+/// Download a model file into `temp_dir`.
+///
+/// The file has no extension yet; the caller detects the format and renames it.
+pub async fn download_model(
+    presigned_url: &str,
+    temp_dir: &str,
+    policy: &DownloadPolicy,
+) -> Result<PathBuf> {
     // Create unique temp file
     let file_id = Uuid::new_v4();
-    let temp_path = PathBuf::from(temp_dir).join(format!("download-{}.stl", file_id));
+    let temp_path = PathBuf::from(temp_dir).join(format!("download-{}", file_id));
 
-    // Download file
-    let response = reqwest::get(presigned_url)
-        .await
-        .context("Failed to send GET request")?;
+    match download_to(presigned_url, &temp_path, policy).await {
+        Ok(()) => Ok(temp_path),
+        Err(e) => {
+            // Never leave partial downloads behind
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            Err(e)
+        }
+    }
+}
+
+async fn download_to(presigned_url: &str, temp_path: &Path, policy: &DownloadPolicy) -> Result<()> {
+    let mut url = Url::parse(presigned_url).context("Invalid download URL")?;
+    let mut redirects = 0;
+
+    // Redirects are followed manually so every hop passes the same checks
+    let mut response = loop {
+        debug!("Downloading model from host {:?}", url.host_str());
+
+        let client = checked_client_builder(&url, policy)
+            .await?
+            .build()
+            .context("Failed to build HTTP client")?;
+        // A server that accepts but never answers must not hold the request until the deadline
+        let response = tokio::time::timeout(policy.read_timeout, client.get(url.clone()).send())
+            .await
+            .context("Timed out waiting for response headers")?
+            .context("Failed to send GET request")?;
+
+        if !response.status().is_redirection() {
+            break response;
+        }
+
+        redirects += 1;
+        if redirects > policy.max_redirects {
+            bail!("Too many redirects (limit {})", policy.max_redirects);
+        }
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .context("Redirect without Location header")?;
+        url = url.join(location).context("Invalid redirect location")?;
+    };
 
     if !response.status().is_success() {
         bail!("Download failed with status: {}", response.status());
     }
 
-    // Check content type (optional, but good practice)
-    if let Some(content_type) = response.headers().get("content-type") {
-        let ct_str = content_type.to_str().unwrap_or("");
-        debug!("Content-Type: {}", ct_str);
-        // Allow various STL MIME types
-        if !ct_str.contains("octet-stream")
-            && !ct_str.contains("stl")
-            && !ct_str.contains("model")
-        {
-            debug!("Warning: Unexpected content-type, proceeding anyway");
+    // Reject oversized files before reading the body
+    if let Some(len) = response.content_length() {
+        if len > policy.max_bytes {
+            bail!("File too large: {} bytes (limit {} bytes)", len, policy.max_bytes);
         }
     }
 
-    // Stream to file
-    let bytes = response
-        .bytes()
-        .await
-        .context("Failed to read response body")?;
+    if let Some(content_type) = response.headers().get("content-type") {
+        debug!("Content-Type: {}", content_type.to_str().unwrap_or(""));
+    }
 
-    let mut file = tokio::fs::File::create(&temp_path)
+    // Stream to file, enforcing the size cap and per-chunk read timeout
+    let mut file = tokio::fs::File::create(temp_path)
         .await
         .context("Failed to create temp file")?;
+    let mut total: u64 = 0;
 
-    file.write_all(&bytes)
-        .await
-        .context("Failed to write to temp file")?;
+    loop {
+        let chunk = tokio::time::timeout(policy.read_timeout, response.chunk())
+            .await
+            .context("Timed out reading response body")?
+            .context("Failed to read response body")?;
+        let Some(chunk) = chunk else {
+            break;
+        };
+
+        total += chunk.len() as u64;
+        if total > policy.max_bytes {
+            bail!("File too large: exceeds limit of {} bytes", policy.max_bytes);
+        }
+
+        file.write_all(&chunk)
+            .await
+            .context("Failed to write to temp file")?;
+    }
 
     file.flush().await.context("Failed to flush file")?;
 
-    debug!("Downloaded {} bytes to {:?}", bytes.len(), temp_path);
+    if total == 0 {
+        bail!("Downloaded file is empty");
+    }
+
+    debug!("Downloaded {} bytes to {:?}", total, temp_path);
 
-    // Validate it's an STL file (basic check: starts with "solid" or binary header)
-    let first_bytes = &bytes[..std::cmp::min(80, bytes.len())];
-    let is_ascii_stl = first_bytes.starts_with(b"solid");
-    let is_binary_stl = first_bytes.len() >= 80; // Binary STL has 80-byte header
+    Ok(())
+}
+
+/// Validate the URL against the policy and return a client builder pinned to the checked
+/// addresses, with redirects disabled.
+///
+/// Pinning the resolved addresses prevents DNS rebinding between check and connect.
+pub async fn checked_client_builder(url: &Url, policy: &DownloadPolicy) -> Result<reqwest::ClientBuilder> {
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("Unsupported URL scheme: {}", url.scheme());
+    }
+
+    let host = url.host_str().context("URL has no host")?;
+    if !host_allowed(host, &policy.allowed_hosts) {
+        bail!("Host not allowed: {}", host);
+    }
 
-    if !is_ascii_stl && !is_binary_stl {
-        tokio::fs::remove_file(&temp_path).await.ok();
-        bail!("Downloaded file does not appear to be a valid STL");
+    let port = url.port_or_known_default().context("URL has no port")?;
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.trim_matches(['[', ']']), port))
+        .await
+        .with_context(|| format!("Failed to resolve host: {}", host))?
+        .collect();
+
+    if addrs.is_empty() {
+        bail!("Host did not resolve to any address: {}", host);
+    }
+
+    if !policy.allow_private_networks {
+        if let Some(addr) = addrs.iter().find(|a| is_private(a.ip())) {
+            bail!("Host {} resolves to a private address: {}", host, addr.ip());
+        }
     }
 
-    Ok(temp_path)
+    let mut builder = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .connect_timeout(policy.connect_timeout)
+        .no_proxy();
+    if url.domain().is_some() {
+        builder = builder.resolve_to_addrs(host, &addrs);
+    }
+
+    Ok(builder)
+}
+
+fn host_allowed(host: &str, allowed_hosts: &[String]) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    allowed_hosts.iter().any(|allowed| {
+        let allowed = allowed.to_ascii_lowercase();
+        match allowed.strip_prefix("*.") {
+            Some(suffix) => host
+                .strip_suffix(suffix)
+                .is_some_and(|prefix| prefix.ends_with('.')),
+            None => host == allowed,
+        }
+    })
+}
+
+/// Loopback, private, link-local and other non-public addresses
+fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let octets = v4.octets();
+            v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                // Carrier-grade NAT 100.64.0.0/10
+                || (octets[0] == 100 && (octets[1] & 0xc0) == 64)
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_private(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // Unique local fc00::/7
+                || (first & 0xfe00) == 0xfc00
+                // Link-local fe80::/10
+                || (first & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::header, response::IntoResponse, routing::get, Router};
+
+    fn policy(allowed: &[&str], allow_private: bool) -> DownloadPolicy {
+        DownloadPolicy {
+            allowed_hosts: allowed.iter().map(|h| h.to_string()).collect(),
+            allow_private_networks: allow_private,
+            max_bytes: 1024,
+            connect_timeout: Duration::from_secs(2),
+            read_timeout: Duration::from_secs(2),
+            max_redirects: 2,
+        }
+    }
+
+    async fn serve() -> SocketAddr {
+        let app = Router::new()
+            .route("/model.stl", get(|| async { "solid test\nendsolid test\n" }))
+            .route("/big.stl", get(|| async { vec![b'x'; 4096] }))
+            .route(
+                "/redirect",
+                get(|| async {
+                    (
+                        axum::http::StatusCode::FOUND,
+                        [(header::LOCATION, "http://evil.example.com/model.stl")],
+                    )
+                        .into_response()
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        addr
+    }
+
+    #[test]
+    fn test_host_allowlist() {
+        let allowed = vec![
+            "fsn1.your-objectstorage.com".to_string(),
+            "*.fsn1.your-objectstorage.com".to_string(),
+        ];
+
+        assert!(host_allowed("fsn1.your-objectstorage.com", &allowed));
+        assert!(host_allowed("rapidfab.FSN1.your-objectstorage.com", &allowed));
+        assert!(!host_allowed("evilfsn1.your-objectstorage.com", &allowed));
+        assert!(!host_allowed("169.254.169.254", &allowed));
+        assert!(!host_allowed("anything", &[]));
+    }
+
+    #[test]
+    fn test_private_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(is_private(ip.parse().unwrap()), "{} should be private", ip);
+        }
+        for ip in ["1.1.1.1", "88.198.1.1", "2a01:4f8::1"] {
+            assert!(!is_private(ip.parse().unwrap()), "{} should be public", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refuses_private_network_by_default() {
+        let addr = serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("http://{}/model.stl", addr);
+
+        let err = download_model(&url, dir.path().to_str().unwrap(), &policy(&["127.0.0.1"], false))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("private address"));
+    }
+
+    #[tokio::test]
+    async fn test_downloads_allowed_host() {
+        let addr = serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("http://{}/model.stl", addr);
+
+        let path = download_model(&url, dir.path().to_str().unwrap(), &policy(&["127.0.0.1"], true))
+            .await
+            .unwrap();
+        assert!(std::fs::read(path).unwrap().starts_with(b"solid"));
+    }
+
+    #[tokio::test]
+    async fn test_size_cap_removes_partial_file() {
+        let addr = serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("http://{}/big.stl", addr);
+
+        let err = download_model(&url, dir.path().to_str().unwrap(), &policy(&["127.0.0.1"], true))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("too large"));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_times_out_when_server_never_responds() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            // Accept and hold connections without ever writing a response
+            let mut held = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                held.push(socket);
+            }
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("http://{}/model.stl", addr);
+        let policy = DownloadPolicy {
+            read_timeout: Duration::from_millis(200),
+            ..policy(&["127.0.0.1"], true)
+        };
+
+        let started = std::time::Instant::now();
+        let err = download_model(&url, dir.path().to_str().unwrap(), &policy)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("response headers"), "{:#}", err);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_redirect_to_unlisted_host_is_refused() {
+        let addr = serve().await;
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("http://{}/redirect", addr);
+
+        let err = download_model(&url, dir.path().to_str().unwrap(), &policy(&["127.0.0.1"], true))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Host not allowed: evil.example.com"));
+    }
 }
@@ -0,0 +1,728 @@
+//! End-to-end quote flow against the mock slicer backend.
+//!
+//! Runs without Orca or xvfb: models are served from a local HTTP server and
+//! sliced by `MockSlicer`.
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+    routing::get,
+    Router,
+};
+use pricing_fdm::{app, artifacts, calibration, config::Config, rules::RulesStore, slicer, AppState};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::ServiceExt;
+
+fn config() -> Config {
+    std::env::set_var(
+        "ORCA_PROFILES_DIR",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/profiles"),
+    );
+    std::env::set_var("TEMP_DIR", std::env::temp_dir());
+    std::env::set_var("DOWNLOAD_ALLOWED_HOSTS", "127.0.0.1");
+    std::env::set_var("DOWNLOAD_ALLOW_PRIVATE_NETWORKS", "true");
+    std::env::set_var("SLICE_CACHE_MAX_MB", "0");
+    std::env::set_var("QUOTE_TOKEN_SECRET", "test-secret-test-secret-test-secret");
+    Config::from_env().expect("test config")
+}
+
+fn state(slicer: Arc<dyn slicer::Slicer>) -> AppState {
+    let config = config();
+    AppState {
+        rules: RulesStore::load(&config.pricing_rules_path, &config).unwrap(),
+        slice_pool: slicer::SlicePool::new(1, 1),
+        slice_cache: slicer::SliceCache::new(&config.slice_cache_dir, 0),
+        jobs: app::jobs::JobStore::new(Duration::from_secs(60), 8),
+        artifacts: None,
+        calibration: calibration::CalibrationStore::load(
+            std::env::temp_dir().join(format!("calibration-{}.json", uuid::Uuid::new_v4())),
+            &config,
+        )
+        .unwrap(),
+        slicer,
+        config,
+    }
+}
+
+/// Mock slicer that also writes a sliced project, like Orca does
+struct ProjectSlicer(slicer::MockSlicer);
+
+#[async_trait::async_trait]
+impl slicer::Slicer for ProjectSlicer {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn slice(
+        &self,
+        model_path: &std::path::Path,
+        settings: &slicer::SliceSettings<'_>,
+        config: &Config,
+    ) -> Result<slicer::SliceOutput, slicer::SliceError> {
+        let mut output = self.0.slice(model_path, settings, config).await?;
+        let project = model_path.with_extension("sliced.3mf");
+        std::fs::write(&project, b"PK sliced project").unwrap();
+        output.project_path = Some(project);
+        Ok(output)
+    }
+}
+
+/// Mock slicer that takes `delay` per call and fails every call after the first `succeed`
+struct FlakySlicer {
+    inner: slicer::MockSlicer,
+    delay: Duration,
+    succeed: usize,
+    calls: std::sync::atomic::AtomicUsize,
+}
+
+impl FlakySlicer {
+    fn new(delay: Duration, succeed: usize) -> Self {
+        FlakySlicer {
+            inner: slicer::MockSlicer::mesh_derived(),
+            delay,
+            succeed,
+            calls: Default::default(),
+        }
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+#[async_trait::async_trait]
+impl slicer::Slicer for FlakySlicer {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn slice(
+        &self,
+        model_path: &std::path::Path,
+        settings: &slicer::SliceSettings<'_>,
+        config: &Config,
+    ) -> Result<slicer::SliceOutput, slicer::SliceError> {
+        let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        // Like Orca, enforce the timeout handed down with the settings
+        if tokio::time::timeout(settings.timeout, tokio::time::sleep(self.delay)).await.is_err() {
+            return Err(slicer::SliceError::Timeout(settings.timeout.as_secs()));
+        }
+        if call >= self.succeed {
+            return Err(slicer::SliceError::Failed(anyhow::anyhow!("orca crashed")));
+        }
+        self.inner.slice(model_path, settings, config).await
+    }
+}
+
+/// ASCII STL of an axis-aligned cube
+fn cube_stl(size: f32) -> String {
+    box_stl(size, 6)
+}
+
+/// ASCII STL of the first `faces` sides of an axis-aligned cube (fewer than 6 leaves it open)
+fn box_stl(size: f32, faces: usize) -> String {
+    let p = |x: f32, y: f32, z: f32| [x * size, y * size, z * size];
+    let quads = [
+        [p(0., 0., 0.), p(0., 1., 0.), p(1., 1., 0.), p(1., 0., 0.)],
+        [p(0., 0., 1.), p(1., 0., 1.), p(1., 1., 1.), p(0., 1., 1.)],
+        [p(0., 0., 0.), p(1., 0., 0.), p(1., 0., 1.), p(0., 0., 1.)],
+        [p(0., 1., 0.), p(0., 1., 1.), p(1., 1., 1.), p(1., 1., 0.)],
+        [p(0., 0., 0.), p(0., 0., 1.), p(0., 1., 1.), p(0., 1., 0.)],
+        [p(1., 0., 0.), p(1., 1., 0.), p(1., 1., 1.), p(1., 0., 1.)],
+    ];
+
+    let mut stl = String::from("solid cube\n");
+    for q in quads.into_iter().take(faces) {
+        for tri in [[q[0], q[1], q[2]], [q[0], q[2], q[3]]] {
+            stl.push_str("facet normal 0 0 0\nouter loop\n");
+            for v in tri {
+                stl.push_str(&format!("vertex {} {} {}\n", v[0], v[1], v[2]));
+            }
+            stl.push_str("endloop\nendfacet\n");
+        }
+    }
+    stl.push_str("endsolid cube\n");
+    stl
+}
+
+async fn serve_models() -> SocketAddr {
+    let app = Router::new()
+        .route("/cube.stl", get(|| async { cube_stl(20.0) }))
+        .route("/large.stl", get(|| async { cube_stl(400.0) }))
+        .route("/open.stl", get(|| async { box_stl(20.0, 5) }))
+        .route("/model.ply", get(|| async { "ply\nformat ascii 1.0\nend_header\n" }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
+
+/// Upload service stand-in resolving any file id to the cube, owned by `owner`
+async fn serve_upload_service(models: SocketAddr, owner: uuid::Uuid) -> String {
+    let app = Router::new().route(
+        "/internal/upload/file/:id/read-url",
+        get(move |axum::extract::Path(id): axum::extract::Path<uuid::Uuid>| async move {
+            axum::Json(json!({
+                "file_id": id,
+                "url": format!("http://{}/cube.stl", models),
+                "expires_at": "2026-10-17T12:00:00Z",
+                "user_id": owner,
+                "session_id": null,
+                "size_bytes": 684,
+                "sha256": null
+            }))
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+async fn post_quote(state: AppState, body: Value) -> (StatusCode, Value) {
+    let request = Request::post("/internal/pricing/fdm/quote")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app::router().with_state(state).oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
+
+#[tokio::test]
+async fn test_quote_with_mesh_derived_mock() {
+    let addr = serve_models().await;
+    let state = state(Arc::new(slicer::MockSlicer::mesh_derived()));
+
+    let (status, quote) = post_quote(
+        state,
+        json!({
+            "file_url": format!("http://{}/cube.stl", addr),
+            "material": "pla",
+            "infill": 20,
+            "layer_thickness": 200
+        }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK, "{}", quote);
+    assert_eq!(quote["model_format"], "stl");
+    assert_eq!(quote["dimensions_mm"], json!([20.0, 20.0, 20.0]));
+    assert!((quote["volume_cm3"].as_f64().unwrap() - 8.0).abs() < 1e-6);
+    assert_eq!(quote["plates"][0]["layer_count"], 100);
+    assert_eq!(quote["quantity"], 1);
+    assert_eq!(quote["machine"]["id"], "generic-200");
+    // A cube needs no support, so it is printed as uploaded
+    assert_eq!(
+        quote["orientation"]["rotation"],
+        json!([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+    );
+    assert_eq!(quote["orientation"]["candidates_sliced"], 1);
+    assert_eq!(quote["warnings"], json!([]));
+    assert_eq!(quote["unit_price_minor"], quote["total_net_minor"]);
+    assert!(quote["total_net_minor"].as_i64().unwrap() > quote["base_fee_minor"].as_i64().unwrap());
+
+    // Priced in PLN by default, with VAT on top of the net total
+    assert_eq!(quote["currency"], "PLN");
+    assert_eq!(quote["exchange_rate"]["from"], "USD");
+    let net = quote["total_net_minor"].as_i64().unwrap();
+    let vat = quote["vat_minor"].as_i64().unwrap();
+    assert_eq!(vat, (net as f64 * 0.23).round() as i64);
+    assert_eq!(quote["total_gross_minor"].as_i64().unwrap(), net + vat);
+}
+
+#[tokio::test]
+async fn test_quote_charges_support_from_canned_metrics() {
+    let addr = serve_models().await;
+    let canned: slicer::SliceMetrics = serde_json::from_value(json!({
+        "print_time_hours": 2.0,
+        "filament_weight_g": 50.0,
+        "filament_length_mm": 16800.0,
+        "filament_volume_cm3": 40.3,
+        "features": [
+            {"feature": "Outer wall", "print_time_hours": 1.5, "filament_length_mm": 13440.0, "filament_weight_g": 40.0},
+            {"feature": "Support", "print_time_hours": 0.5, "filament_length_mm": 3360.0, "filament_weight_g": 10.0}
+        ],
+        "plates": []
+    }))
+    .unwrap();
+    let state = state(Arc::new(slicer::MockSlicer::canned(canned)));
+    let rate = state.rules.current().support_removal_usd_per_g;
+
+    let (status, quote) = post_quote(
+        state,
+        json!({
+            "file_url": format!("http://{}/cube.stl", addr),
+            "material": "pla",
+            "infill": 20,
+            "layer_thickness": 200,
+            "currency": "usd"
+        }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK, "{}", quote);
+    assert_eq!(quote["currency"], "USD");
+    assert_eq!(quote["exchange_rate"]["rate"], 1.0);
+    assert_eq!(quote["print_time_hours"], 2.0);
+    assert_eq!(quote["support_weight_g"], 10.0);
+    assert_eq!(quote["support_removal_minor"], (10.0 * rate * 100.0).round() as i64);
+}
+
+#[tokio::test]
+async fn test_unsupported_format_is_rejected() {
+    let addr = serve_models().await;
+    let state = state(Arc::new(slicer::MockSlicer::mesh_derived()));
+
+    let (status, body) = post_quote(
+        state,
+        json!({
+            "file_url": format!("http://{}/model.ply", addr),
+            "material": "pla",
+            "infill": 20,
+            "layer_thickness": 200
+        }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert!(body["message"].as_str().unwrap().contains("PLY"));
+    assert!(
+        pricing_fdm::metrics::QUOTES
+            .with_label_values(&["pla", "unsupported_format"])
+            .get()
+            >= 1
+    );
+}
+
+#[tokio::test]
+async fn test_quantity_batch_pricing() {
+    let addr = serve_models().await;
+    let state = state(Arc::new(slicer::MockSlicer::mesh_derived()));
+
+    let (status, quote) = post_quote(
+        state,
+        json!({
+            "file_url": format!("http://{}/cube.stl", addr),
+            "material": "pla",
+            "infill": 20,
+            "layer_thickness": 200,
+            "quantity": 100
+        }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK, "{}", quote);
+    assert_eq!(quote["quantity"], 100);
+    // 20mm cubes, 5mm spacing on a 200x200 bed: 8 x 8 per plate
+    assert_eq!(quote["copies_per_plate"], 64);
+    assert_eq!(quote["batch_plates"], 2);
+    assert!(quote["discount_percent"].as_f64().unwrap() > 0.0);
+
+    let unit = quote["unit_price_minor"].as_i64().unwrap();
+    assert_eq!(quote["total_net_minor"].as_i64().unwrap(), unit * 100);
+}
+
+#[tokio::test]
+async fn test_part_too_large_for_every_machine() {
+    let addr = serve_models().await;
+    let state = state(Arc::new(slicer::MockSlicer::mesh_derived()));
+
+    let (status, body) = post_quote(
+        state,
+        json!({
+            "file_url": format!("http://{}/large.stl", addr),
+            "material": "pla",
+            "infill": 20,
+            "layer_thickness": 200
+        }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+    let message = body["message"].as_str().unwrap();
+    assert!(message.contains("400.0 x 400.0 x 400.0"), "{}", message);
+    assert!(message.contains("350.0 x 350.0 x 350.0"), "{}", message);
+}
+
+#[tokio::test]
+async fn test_sliced_artifact_is_stored_and_accepted() {
+    let addr = serve_models().await;
+    let store_dir = tempfile::tempdir().unwrap();
+    let mut state = state(Arc::new(ProjectSlicer(slicer::MockSlicer::mesh_derived())));
+    state.artifacts = Some(artifacts::Artifacts::new(
+        Arc::new(artifacts::LocalArtifactStore::new(store_dir.path())),
+        "pricing-fdm",
+        Duration::from_secs(3600),
+    ));
+
+    let (status, quote) = post_quote(
+        state.clone(),
+        json!({
+            "file_url": format!("http://{}/cube.stl", addr),
+            "material": "pla",
+            "infill": 20,
+            "layer_thickness": 200
+        }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK, "{}", quote);
+    let quote_id = quote["quote_id"].as_str().unwrap();
+    let artifact = &quote["artifact"];
+    assert_eq!(
+        artifact["key"],
+        format!("pricing-fdm/quotes/pending/{}/sliced.3mf", quote_id)
+    );
+    assert_eq!(artifact["status"], "pending");
+    assert_eq!(artifact["size_bytes"], 17);
+
+    let request = Request::post(format!("/internal/pricing/fdm/artifacts/{}/accept", quote_id))
+        .body(Body::empty())
+        .unwrap();
+    let response = app::router().with_state(state).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let accepted: Value = serde_json::from_slice(&bytes).unwrap();
+
+    let key = format!("pricing-fdm/quotes/accepted/{}/sliced.3mf", quote_id);
+    assert_eq!(accepted["keys"], json!([key]));
+    assert_eq!(std::fs::read(store_dir.path().join(&key)).unwrap(), b"PK sliced project");
+}
+
+#[tokio::test]
+async fn test_quote_by_file_id_checks_owner() {
+    let models = serve_models().await;
+    let owner = uuid::Uuid::new_v4();
+    let file_id = uuid::Uuid::new_v4();
+    let mut state = state(Arc::new(slicer::MockSlicer::mesh_derived()));
+    state.config.upload_service_url = serve_upload_service(models, owner).await;
+    state.config.internal_service_token = Some("secret".to_string());
+
+    let body = |user_id: uuid::Uuid| {
+        json!({
+            "file_id": file_id,
+            "user_id": user_id,
+            "material": "pla",
+            "infill": 20,
+            "layer_thickness": 200
+        })
+    };
+
+    let (status, quote) = post_quote(state.clone(), body(owner)).await;
+    assert_eq!(status, StatusCode::OK, "{}", quote);
+    assert_eq!(quote["file_id"], file_id.to_string());
+    assert_eq!(quote["model_sha256"].as_str().unwrap().len(), 64);
+
+    // Someone else's file looks the same as a missing one
+    let (status, error) = post_quote(state, body(uuid::Uuid::new_v4())).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{}", error);
+    assert_eq!(error["message"], format!("File not found: {}", file_id));
+}
+
+#[tokio::test]
+async fn test_quote_token_verifies_and_rejects_tampering() {
+    let addr = serve_models().await;
+    let state = state(Arc::new(slicer::MockSlicer::mesh_derived()));
+
+    let (status, quote) = post_quote(
+        state.clone(),
+        json!({
+            "file_url": format!("http://{}/cube.stl", addr),
+            "material": "pla",
+            "infill": 20,
+            "layer_thickness": 200,
+            "quantity": 3
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", quote);
+    let token = quote["quote_token"].as_str().unwrap().to_string();
+
+    let verify = |token: String| {
+        let state = state.clone();
+        async move {
+            let request = Request::post("/internal/pricing/fdm/quotes/verify")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "token": token }).to_string()))
+                .unwrap();
+            let response = app::router().with_state(state).oneshot(request).await.unwrap();
+            let status = response.status();
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<Value>(&bytes).unwrap())
+        }
+    };
+
+    let (status, claims) = verify(token.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", claims);
+    assert_eq!(claims["quote_id"], quote["quote_id"]);
+    assert_eq!(claims["model_sha256"], quote["model_sha256"]);
+    assert_eq!(claims["material"], "pla");
+    assert_eq!(claims["quantity"], 3);
+    assert_eq!(claims["total_gross_minor"], quote["total_gross_minor"]);
+    assert_eq!(claims["pricing_rules_version"], quote["pricing_rules_version"]);
+    assert_eq!(
+        chrono::DateTime::parse_from_rfc3339(quote["expires_at"].as_str().unwrap())
+            .unwrap()
+            .timestamp(),
+        claims["exp"].as_i64().unwrap()
+    );
+
+    // Flip one character of the signature
+    let mut tampered = token.into_bytes();
+    let last = tampered.len() - 2;
+    tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+    let (status, _) = verify(String::from_utf8(tampered).unwrap()).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_finishing_options_are_priced_line_items() {
+    let addr = serve_models().await;
+    let state = state(Arc::new(slicer::MockSlicer::mesh_derived()));
+    let request = |material: &str, finishing: Value| {
+        json!({
+            "file_url": format!("http://{}/cube.stl", addr),
+            "material": material,
+            "infill": 20,
+            "layer_thickness": 200,
+            "quantity": 2,
+            "finishing": finishing
+        })
+    };
+
+    let (status, plain) = post_quote(state.clone(), request("abs", json!([]))).await;
+    assert_eq!(status, StatusCode::OK, "{}", plain);
+    let (status, quote) = post_quote(
+        state.clone(),
+        request(
+            "abs",
+            json!([{ "id": "vapor_smoothing" }, { "id": "heat_set_inserts", "count": 3 }]),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", quote);
+
+    let finishing = quote["finishing"].as_array().unwrap();
+    assert_eq!(finishing.len(), 2);
+    assert_eq!(finishing[0]["id"], "vapor_smoothing");
+    assert_eq!(finishing[0]["unit"], "per_part");
+    assert_eq!(finishing[0]["lead_time_days"], 1);
+    assert_eq!(finishing[1]["units_per_part"], 3.0);
+    assert!(finishing[1]["amount_minor"].as_i64().unwrap() > 0);
+    assert!(quote["total_net_minor"].as_i64().unwrap() > plain["total_net_minor"].as_i64().unwrap());
+    assert_eq!(
+        quote["lead_time_days"].as_i64().unwrap(),
+        plain["lead_time_days"].as_i64().unwrap() + 1
+    );
+
+    // Vapor smoothing is only offered for ABS and ASA, inserts need a count
+    let (status, error) = post_quote(state.clone(), request("pla", json!([{ "id": "vapor_smoothing" }]))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error["message"].as_str().unwrap().contains("not available"), "{}", error);
+    let (status, _) = post_quote(state, request("abs", json!([{ "id": "heat_set_inserts" }]))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_options_list_catalog_and_rules() {
+    let state = state(Arc::new(slicer::MockSlicer::mesh_derived()));
+    let request = Request::get("/internal/pricing/fdm/options")
+        .body(Body::empty())
+        .unwrap();
+    let response = app::router().with_state(state.clone()).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let options: Value = serde_json::from_slice(&bytes).unwrap();
+
+    let pla = options["materials"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["id"] == "pla")
+        .unwrap();
+    assert_eq!(pla["density_g_cm3"], 1.24);
+    assert_eq!(pla["price_per_g_usd"], 0.02);
+    assert_eq!(pla["layer_heights_um"], json!([100, 200, 300]));
+    assert_eq!(pla["min_infill"], 10);
+    assert!(pla["colors"].as_array().unwrap().contains(&json!("black")));
+
+    let machines = options["machines"].as_array().unwrap();
+    assert_eq!(machines.len(), state.config.machines.all().len());
+    assert!(machines[0]["build_volume_mm"].is_array());
+    assert!(machines[0]["materials"].as_array().unwrap().contains(&json!("pla")));
+
+    let finishing = options["finishing"].as_array().unwrap();
+    let smoothing = finishing.iter().find(|f| f["id"] == "vapor_smoothing").unwrap();
+    assert_eq!(smoothing["materials"], json!(["abs", "asa"]));
+
+    assert_eq!(options["rush_tiers"][0]["id"], "standard");
+    assert_eq!(options["currencies"], json!(["PLN", "EUR", "USD"]));
+    assert_eq!(options["max_quantity"], state.config.max_quantity);
+}
+
+#[tokio::test]
+async fn test_calibration_reports_correct_later_quotes() {
+    let addr = serve_models().await;
+    let state = state(Arc::new(slicer::MockSlicer::mesh_derived()));
+    let body = json!({
+        "file_url": format!("http://{}/cube.stl", addr),
+        "material": "pla",
+        "infill": 20,
+        "layer_thickness": 200
+    });
+    let report = |quote: Value, time_factor: f64| {
+        let state = state.clone();
+        async move {
+            let calibration = &quote["calibration"];
+            let request = Request::post("/internal/pricing/fdm/calibration/reports")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "quote_token": quote["quote_token"],
+                        "actual_print_time_hours":
+                            calibration["sliced_print_time_hours"].as_f64().unwrap() * time_factor,
+                        "actual_filament_weight_g":
+                            calibration["sliced_filament_weight_g"].as_f64().unwrap() * 1.1
+                    })
+                    .to_string(),
+                ))
+                .unwrap();
+            let response = app::router().with_state(state).oneshot(request).await.unwrap();
+            let status = response.status();
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<Value>(&bytes).unwrap())
+        }
+    };
+
+    let (status, before) = post_quote(state.clone(), body.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{}", before);
+    assert_eq!(before["calibration"]["print_time_factor"], 1.0);
+    assert_eq!(before["calibration"]["samples"], 0);
+
+    // A failed print reported as 10x the estimate is rejected
+    let (status, _) = report(before.clone(), 10.0).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Estimates come from the signed token, which cannot be edited
+    let mut forged = before.clone();
+    forged["quote_token"] = json!(format!("{}x", before["quote_token"].as_str().unwrap()));
+    let (status, _) = report(forged, 1.25).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    for _ in 0..state.config.calibration_min_samples {
+        let (status, quote) = post_quote(state.clone(), body.clone()).await;
+        assert_eq!(status, StatusCode::OK, "{}", quote);
+        let (status, factor) = report(quote.clone(), 1.25).await;
+        assert_eq!(status, StatusCode::OK, "{}", factor);
+
+        // A retried report leaves the factors alone
+        let (status, retried) = report(quote, 1.25).await;
+        assert_eq!(status, StatusCode::OK, "{}", retried);
+        assert_eq!(retried["samples"], factor["samples"]);
+    }
+
+    let (status, after) = post_quote(state.clone(), body).await;
+    assert_eq!(status, StatusCode::OK, "{}", after);
+    let calibration = &after["calibration"];
+    assert!((calibration["print_time_factor"].as_f64().unwrap() - 1.25).abs() < 1e-9);
+    assert!((calibration["filament_weight_factor"].as_f64().unwrap() - 1.1).abs() < 1e-9);
+    assert_eq!(calibration["sliced_print_time_hours"], before["print_time_hours"]);
+    let corrected = before["print_time_hours"].as_f64().unwrap() * 1.25;
+    assert!((after["print_time_hours"].as_f64().unwrap() - corrected).abs() < 1e-9);
+    assert!(after["machine_cost_minor"].as_i64().unwrap() > before["machine_cost_minor"].as_i64().unwrap());
+}
+
+#[tokio::test]
+async fn test_failed_orientation_candidates_are_skipped() {
+    let addr = serve_models().await;
+    let slicer = Arc::new(FlakySlicer::new(Duration::ZERO, 1));
+    let mut state = state(slicer.clone());
+    state.config.orient_slice_candidates = 3;
+
+    let (status, quote) = post_quote(
+        state.clone(),
+        json!({
+            "file_url": format!("http://{}/cube.stl", addr),
+            "material": "pla",
+            "infill": 20,
+            "layer_thickness": 200
+        }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK, "{}", quote);
+    assert_eq!(slicer.calls(), 3);
+    assert_eq!(quote["orientation"]["candidates_sliced"], 1);
+
+    // Nothing priced: the first candidate's error is returned
+    let slicer = Arc::new(FlakySlicer::new(Duration::ZERO, 0));
+    state.slicer = slicer.clone();
+    let (status, body) = post_quote(
+        state,
+        json!({
+            "file_url": format!("http://{}/cube.stl", addr),
+            "material": "pla",
+            "infill": 20,
+            "layer_thickness": 200
+        }),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+    assert_eq!(slicer.calls(), 3);
+}
+
+#[tokio::test]
+async fn test_orientation_candidates_share_one_deadline() {
+    let addr = serve_models().await;
+    let slicer = Arc::new(FlakySlicer::new(Duration::from_millis(700), usize::MAX));
+    let mut state = state(slicer.clone());
+    state.config.orient_slice_candidates = 3;
+    state.config.request_timeout_secs = 1;
+
+    let started = std::time::Instant::now();
+    let (status, quote) = post_quote(
+        state,
+        json!({
+            "file_url": format!("http://{}/cube.stl", addr),
+            "material": "pla",
+            "infill": 20,
+            "layer_thickness": 200
+        }),
+    )
+    .await;
+
+    // The second candidate gets only the remaining time and times out; the first one is quoted
+    assert_eq!(status, StatusCode::OK, "{}", quote);
+    assert_eq!(slicer.calls(), 2);
+    assert!(started.elapsed() < Duration::from_millis(1500));
+}
+
+#[tokio::test]
+async fn test_open_mesh_is_rejected_before_slicing() {
+    let addr = serve_models().await;
+    let slicer = Arc::new(FlakySlicer::new(Duration::ZERO, usize::MAX));
+    let mut state = state(slicer.clone());
+    let body = json!({
+        "file_url": format!("http://{}/open.stl", addr),
+        "material": "pla",
+        "infill": 20,
+        "layer_thickness": 200
+    });
+
+    let (status, error) = post_quote(state.clone(), body.clone()).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", error);
+    assert!(error["message"].as_str().unwrap().contains("4 open edges"), "{}", error);
+    assert_eq!(slicer.calls(), 0);
+
+    // Tolerated defects are quoted, flagged as not watertight
+    state.config.max_boundary_edges = 4;
+    let (status, quote) = post_quote(state, body).await;
+    assert_eq!(status, StatusCode::OK, "{}", quote);
+    assert_eq!(quote["is_watertight"], false);
+}
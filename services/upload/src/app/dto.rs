@@ -52,8 +52,15 @@ pub struct FileInfo {
 // GET /internal/upload/file/{id}/read-url
 #[derive(Debug, Serialize)]
 pub struct ReadUrlResponse {
+    pub file_id: Uuid,
     pub url: String,
     pub expires_at: String,
+    /// Owner: exactly one of user_id (authenticated) or session_id (anonymous) is set
+    pub user_id: Option<Uuid>,
+    pub session_id: Option<Uuid>,
+    pub size_bytes: u64,
+    /// Hex-encoded SHA-256, when known
+    pub sha256: Option<String>,
 }
 
 // POST /internal/upload/transfer
@@ -247,11 +247,15 @@ impl UploadService {
     }
 
     /// Generate read URL for file (for pricing service)
+    /// Includes the owning user or session so callers can check access
     pub async fn generate_read_url(&self, file_id: Uuid) -> Result<ReadUrlResponse> {
-        // Get file
+        // Get file and its owner
         let file = sqlx::query!(
             r#"
-            SELECT s3_key FROM files WHERE id = $1
+            SELECT f.s3_key, f.size_bytes, f.sha256_hash, u.user_id, u.session_id
+            FROM files f
+            JOIN uploads u ON u.id = f.upload_id
+            WHERE f.id = $1
             "#,
             file_id,
         )
@@ -266,7 +270,15 @@ impl UploadService {
             .await?;
         let expires_at = (Utc::now() + chrono::Duration::seconds(expires_in as i64)).to_rfc3339();
 
-        Ok(ReadUrlResponse { url, expires_at })
+        Ok(ReadUrlResponse {
+            file_id,
+            url,
+            expires_at,
+            user_id: file.user_id,
+            session_id: file.session_id,
+            size_bytes: file.size_bytes as u64,
+            sha256: file.sha256_hash,
+        })
     }
 
     /// Transfer anonymous uploads to user account (post-registration)